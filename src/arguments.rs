@@ -1,18 +1,619 @@
+use std::{fmt, net::IpAddr, str::FromStr};
+
 #[cfg(feature = "clap")]
-use clap::{value_parser, Parser};
+use clap::{value_parser, Parser, ValueEnum};
+
+use crate::{
+    expression::Expression,
+    kind::LinkKind,
+    operstate::{OperStateRange, ParseOperStateError},
+    pattern::{InterfacePattern, ParseInterfacePatternError},
+    probe::Probe,
+    sockaddr::{
+        AddressFamily, AddressScope, Cidr, InterfacesFamilyTypeArgument,
+        ParseCidrError,
+    },
+};
+
+/// Source of the interfaces and their addresses
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "clap", derive(ValueEnum))]
+pub enum Backend {
+    /// Dump links and addresses over netlink (`RTM_GETLINK`/`RTM_GETADDR`),
+    /// needed to wait for (and fail on) duplicate address detection
+    Netlink,
+    /// Use libc's `getifaddrs`, doesn't know if duplicate address detection
+    /// finished
+    #[default]
+    Getifaddrs,
+}
+
+/// Address family of the default route required by `--require-default-route`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "clap", derive(ValueEnum))]
+pub enum RouteFamily {
+    /// An IPv4 or an IPv6 default route
+    #[default]
+    Any,
+    /// An IPv4 default route
+    Ipv4,
+    /// An IPv6 default route
+    Ipv6,
+}
+
+impl RouteFamily {
+    /// `None` for [`RouteFamily::Any`]
+    #[must_use]
+    pub const fn address_family(self) -> Option<AddressFamily> {
+        match self {
+            Self::Any => None,
+            Self::Ipv4 => Some(AddressFamily::Inet),
+            Self::Ipv6 => Some(AddressFamily::Inet6),
+        }
+    }
+}
+
+/// Where the address families required by `--ipv4`, `--ipv6` and
+/// `--ipv4-or-ipv6` have to be
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "clap", derive(ValueEnum))]
+pub enum FamilyScope {
+    /// Every interface taken into account needs the address families
+    #[default]
+    Interface,
+    /// The address families can be on different interfaces, e.g. IPv4 on
+    /// `eth0` and IPv6 on `wg0`
+    Host,
+}
+
+/// Address families an interface needs addresses of, see [`InterfaceArgument`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InterfaceFamily {
+    /// At least one IPv4 address
+    Ipv4,
+    /// At least one IPv6 address
+    Ipv6,
+    /// At least one IPv4 and one IPv6 address
+    Both,
+}
+
+impl InterfaceFamily {
+    #[must_use]
+    pub const fn ipv4(self) -> bool {
+        matches!(self, Self::Ipv4 | Self::Both)
+    }
+
+    #[must_use]
+    pub const fn ipv6(self) -> bool {
+        matches!(self, Self::Ipv6 | Self::Both)
+    }
+}
+
+impl FromStr for InterfaceFamily {
+    type Err = ParseInterfaceFamilyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ipv4" => Ok(Self::Ipv4),
+            "ipv6" => Ok(Self::Ipv6),
+            "ipv4+ipv6" => Ok(Self::Both),
+            _ => Err(ParseInterfaceFamilyError),
+        }
+    }
+}
+
+impl fmt::Display for InterfaceFamily {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Ipv4 => "ipv4",
+            Self::Ipv6 => "ipv6",
+            Self::Both => "ipv4+ipv6",
+        })
+    }
+}
+
+/// Error returned when parsing an invalid [`InterfaceFamily`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseInterfaceFamilyError;
+
+impl fmt::Display for ParseInterfaceFamilyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("expected ipv4, ipv6 or ipv4+ipv6")
+    }
+}
+
+impl std::error::Error for ParseInterfaceFamilyError {}
+
+/// Number of interfaces that have to be online, parsed from `N` or `N%`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MinOnline {
+    /// At least this many interfaces
+    Count(usize),
+    /// At least this percentage of the interfaces, rounded up
+    Percent(u8),
+}
+
+impl MinOnline {
+    /// Number of interfaces that have to be online out of `total`
+    #[must_use]
+    pub const fn required(self, total: usize) -> usize {
+        match self {
+            Self::Count(count) => count,
+            Self::Percent(percent) => (total * percent as usize + 99) / 100,
+        }
+    }
+}
+
+impl FromStr for MinOnline {
+    type Err = ParseMinOnlineError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let min_online = match s.strip_suffix('%') {
+            Some(percent) => percent
+                .parse()
+                .ok()
+                .filter(|percent| (1..=100).contains(percent))
+                .map(Self::Percent),
+            None => s.parse().ok().filter(|&count| count != 0).map(Self::Count),
+        };
+        min_online.ok_or_else(|| ParseMinOnlineError(s.into()))
+    }
+}
+
+impl fmt::Display for MinOnline {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Count(count) => write!(f, "{count}"),
+            Self::Percent(percent) => write!(f, "{percent}%"),
+        }
+    }
+}
+
+/// Error returned when parsing an invalid [`MinOnline`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseMinOnlineError(Box<str>);
+
+impl fmt::Display for ParseMinOnlineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "invalid minimum `{}`, expected a count of at least 1 or a \
+             percentage from 1% to 100%",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for ParseMinOnlineError {}
+
+/// How the states of the interfaces taken into account are combined, parsed
+/// from `all`, `any`, `N` or `N%`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Aggregation {
+    /// Every interface has to be online
+    #[default]
+    All,
+    /// At least one interface has to be online, see `--any`
+    Any,
+    /// Enough interfaces have to be online, see `--min-online`
+    AtLeast(MinOnline),
+}
+
+impl FromStr for Aggregation {
+    type Err = ParseMinOnlineError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "all" => Ok(Self::All),
+            "any" => Ok(Self::Any),
+            _ => s.parse().map(Self::AtLeast),
+        }
+    }
+}
+
+impl fmt::Display for Aggregation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::All => f.write_str("all"),
+            Self::Any => f.write_str("any"),
+            Self::AtLeast(min_online) => min_online.fmt(f),
+        }
+    }
+}
+
+/// Required interface, parsed from `PATTERN[:FAMILY][:MIN[:MAX]]`
+///
+/// The address family and operational state range override `--ipv4`,
+/// `--ipv6` and `--operational-state` for the interfaces matching the
+/// pattern.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InterfaceArgument {
+    pub pattern: InterfacePattern,
+    pub family: Option<InterfaceFamily>,
+    pub operational_state: Option<OperStateRange>,
+}
+
+impl InterfaceArgument {
+    #[must_use]
+    pub const fn new(
+        pattern: InterfacePattern,
+        operational_state: Option<OperStateRange>,
+    ) -> Self {
+        Self {
+            pattern,
+            family: None,
+            operational_state,
+        }
+    }
+
+    #[must_use]
+    pub const fn family(mut self, family: Option<InterfaceFamily>) -> Self {
+        self.family = family;
+        self
+    }
+}
+
+/// Interface without its own address family or operational state, `name` is
+/// used as is
+impl From<&str> for InterfaceArgument {
+    fn from(name: &str) -> Self {
+        Self::new(name.into(), None)
+    }
+}
+
+/// Prefixes of the patterns that aren't plain names or globs, see
+/// [`InterfacePattern`]
+const PREFIXED_PATTERNS: [&str; 3] = ["re:", "mac:", "index:"];
+
+/// Address family and operational state range of an `--interface`, parsed
+/// from what follows the pattern
+type InterfaceRequirements = (Option<InterfaceFamily>, Option<OperStateRange>);
+
+/// Parses the `FAMILY`, `FAMILY:MIN[:MAX]` or `MIN[:MAX]` after a pattern
+fn parse_requirements(
+    s: &str,
+) -> Result<InterfaceRequirements, ParseOperStateError> {
+    let (family, range) = match s.split_once(':') {
+        Some((family, range)) => match family.parse() {
+            Ok(family) => (Some(family), Some(range)),
+            Err(_) => (None, Some(s)),
+        },
+        None => match s.parse() {
+            Ok(family) => (Some(family), None),
+            Err(_) => (None, Some(s)),
+        },
+    };
+
+    Ok((family, range.map(str::parse).transpose()?))
+}
+
+impl FromStr for InterfaceArgument {
+    type Err = ParseInterfaceArgumentError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let prefix = PREFIXED_PATTERNS
+            .iter()
+            .find(|prefix| s.starts_with(*prefix))
+            .map_or(0, |prefix| prefix.len());
+        // A regex, a hardware address or an alias (e.g. `eth0:1`) contain
+        // `:` themselves, the requirements are split off at the first `:`
+        // they can be parsed after
+        let (pattern, (family, operational_state)) = s[prefix..]
+            .match_indices(':')
+            .find_map(|(i, _)| {
+                let requirements =
+                    parse_requirements(&s[prefix + i + 1..]).ok()?;
+                Some((&s[..prefix + i], requirements))
+            })
+            .unwrap_or((s, (None, None)));
+        if pattern.is_empty() {
+            return Err(ParseInterfaceArgumentError::EmptyName);
+        }
+
+        Ok(Self::new(pattern.parse()?, operational_state).family(family))
+    }
+}
+
+/// Error returned when parsing an invalid [`InterfaceArgument`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseInterfaceArgumentError {
+    EmptyName,
+    Pattern(ParseInterfacePatternError),
+}
+
+impl From<ParseInterfacePatternError> for ParseInterfaceArgumentError {
+    fn from(err: ParseInterfacePatternError) -> Self {
+        Self::Pattern(err)
+    }
+}
+
+impl fmt::Display for ParseInterfaceArgumentError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::EmptyName => f.write_str("interface name is empty"),
+            Self::Pattern(err) => err.fmt(f),
+        }
+    }
+}
 
+impl std::error::Error for ParseInterfaceArgumentError {}
+
+/// Named group of interfaces with its own [`Aggregation`], parsed from
+/// `NAME=AGGREGATION:INTERFACE[,INTERFACE...]`
+///
+/// Every interface is an [`InterfaceArgument`], e.g.
+/// `uplink=any:eth0,wlan0:ipv4`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InterfaceGroup {
+    pub name: Box<str>,
+    pub aggregation: Aggregation,
+    pub interfaces: Vec<InterfaceArgument>,
+}
+
+impl FromStr for InterfaceGroup {
+    type Err = ParseInterfaceGroupError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, group) = s
+            .split_once('=')
+            .filter(|(name, _)| !name.is_empty())
+            .ok_or(ParseInterfaceGroupError::MissingName)?;
+        let (aggregation, interfaces) = group
+            .split_once(':')
+            .filter(|(_, interfaces)| !interfaces.is_empty())
+            .ok_or(ParseInterfaceGroupError::MissingInterfaces)?;
+
+        Ok(Self {
+            name: name.into(),
+            aggregation: aggregation.parse().map_err(|_| {
+                ParseInterfaceGroupError::Aggregation(aggregation.into())
+            })?,
+            interfaces: interfaces
+                .split(',')
+                .map(str::parse)
+                .collect::<Result<_, _>>()?,
+        })
+    }
+}
+
+/// Error returned when parsing an invalid [`InterfaceGroup`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseInterfaceGroupError {
+    MissingName,
+    MissingInterfaces,
+    Aggregation(Box<str>),
+    Interface(ParseInterfaceArgumentError),
+}
+
+impl From<ParseInterfaceArgumentError> for ParseInterfaceGroupError {
+    fn from(err: ParseInterfaceArgumentError) -> Self {
+        Self::Interface(err)
+    }
+}
+
+impl fmt::Display for ParseInterfaceGroupError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingName => {
+                f.write_str("expected a group name followed by `=`")
+            }
+            Self::MissingInterfaces => f.write_str(
+                "expected the aggregation and the interfaces of the group, \
+                 e.g. `any:eth0,wlan0`",
+            ),
+            Self::Aggregation(aggregation) => write!(
+                f,
+                "invalid aggregation `{aggregation}`, expected all, any, N or \
+                 N%"
+            ),
+            Self::Interface(err) => err.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for ParseInterfaceGroupError {}
+
+/// Prefix an address has to be assigned from, optionally to an interface
+/// matching a pattern, parsed from `CIDR[@INTERFACE]`
+///
+/// E.g. `192.168.10.0/24` or `192.168.10.0/24@eth0`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AddressIn {
+    pub cidr: Cidr,
+    pub interface: Option<InterfacePattern>,
+}
+
+impl fmt::Display for AddressIn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.cidr.fmt(f)?;
+        if let Some(interface) = &self.interface {
+            write!(f, "@{interface}")?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for AddressIn {
+    type Err = ParseAddressInError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (cidr, interface) = match s.split_once('@') {
+            Some((cidr, interface)) => (cidr, Some(interface)),
+            None => (s, None),
+        };
+
+        Ok(Self {
+            cidr: cidr.parse().map_err(ParseAddressInError::Cidr)?,
+            interface: interface
+                .map(str::parse)
+                .transpose()
+                .map_err(ParseAddressInError::Interface)?,
+        })
+    }
+}
+
+/// Error returned when parsing an invalid [`AddressIn`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseAddressInError {
+    Cidr(ParseCidrError),
+    Interface(ParseInterfacePatternError),
+}
+
+impl fmt::Display for ParseAddressInError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Cidr(err) => err.fmt(f),
+            Self::Interface(err) => err.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for ParseAddressInError {}
+
+/// Error returned by [`Args::validate`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InvalidArgsError {
+    /// The same pattern is given to `--interface` and `--ignore`
+    RequiredAndIgnored(InterfacePattern),
+    /// The same kind is given to `--interface-kind` and `--ignore-kind`
+    KindRequiredAndIgnored(LinkKind),
+    /// `--solicit-gateway` without `--gateway-neighbor`
+    SolicitGatewayWithoutNeighbor,
+    /// `--ipv4-or-ipv6` together with `--ipv4` or `--ipv6`
+    EitherFamilyWithFamily,
+    /// `--min-online` together with `--any`
+    MinOnlineWithAny,
+    /// `--group` together with another way to select the interfaces,
+    /// `--any` or `--min-online`
+    GroupWithSelection,
+    /// Two groups with the same name
+    DuplicateGroup(Box<str>),
+    /// `--require` together with `--interface`, `--interface-kind`, `--any`,
+    /// `--min-online` or `--group`
+    RequireWithSelection,
+    /// `--presence-only` without `--interface`, `--interface-kind` or
+    /// `--group`
+    PresenceOnlyWithoutInterfaces,
+    /// `--presence-only` together with an address family, an operational
+    /// state (also given with an interface) or `--require`
+    PresenceOnlyWithState,
+}
+
+impl fmt::Display for InvalidArgsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::RequiredAndIgnored(pattern) => write!(
+                f,
+                "interface pattern `{pattern}` is both required and ignored"
+            ),
+            Self::KindRequiredAndIgnored(kind) => {
+                write!(f, "interface kind {kind} is both required and ignored")
+            }
+            Self::SolicitGatewayWithoutNeighbor => {
+                f.write_str("--solicit-gateway requires --gateway-neighbor")
+            }
+            Self::EitherFamilyWithFamily => f.write_str(
+                "--ipv4-or-ipv6 can't be combined with --ipv4 or --ipv6",
+            ),
+            Self::MinOnlineWithAny => {
+                f.write_str("--min-online can't be combined with --any")
+            }
+            Self::GroupWithSelection => f.write_str(
+                "--group can't be combined with --interface, --ignore, their \
+                 kind and virtual variants, --any or --min-online",
+            ),
+            Self::DuplicateGroup(name) => {
+                write!(f, "group `{name}` is given more than once")
+            }
+            Self::RequireWithSelection => f.write_str(
+                "--require can't be combined with --interface, \
+                 --interface-kind, --any, --min-online or --group",
+            ),
+            Self::PresenceOnlyWithoutInterfaces => f.write_str(
+                "--presence-only requires --interface, --interface-kind or \
+                 --group",
+            ),
+            Self::PresenceOnlyWithState => f.write_str(
+                "--presence-only can't be combined with address families, \
+                 operational states or --require",
+            ),
+        }
+    }
+}
+
+impl std::error::Error for InvalidArgsError {}
+
+#[allow(clippy::struct_excessive_bools)]
 #[derive(Debug)]
 #[cfg_attr(feature = "clap", derive(Parser), command(author, version, about))]
 pub struct Args {
     /// Block until at least these interfaces have appeared
-    #[cfg_attr(feature = "clap", arg(short, long, conflicts_with = "ignore"))]
-    pub interface: Option<Vec<Box<str>>>,
+    ///
+    /// A name with `*`, `?` or `[...]` is a shell glob and `re:` starts a
+    /// POSIX extended regex, both have to match the whole name. Names are
+    /// also matched against alternative names and the `ID_NET_NAME_PATH` and
+    /// `ID_NET_NAME_MAC` names of udev, which survive renames (alternative
+    /// names need `--backend netlink`).
+    /// `mac:ADDRESS` and `index:N` select an interface by its hardware
+    /// address or its index. At least one interface has to match a pattern,
+    /// every match is required.
+    /// The address families (`ipv4`, `ipv6` or `ipv4+ipv6`) and an
+    /// operational state range can be given per pattern as
+    /// `PATTERN[:FAMILY][:MIN[:MAX]]`, e.g. `eth0:ipv4` or `wg0:ipv6:routable`,
+    /// overriding `--ipv4`, `--ipv6` and `--operational-state`. A suffix
+    /// that isn't one of these is part of the name, e.g. the alias `eth0:1`.
+    /// With `--ignore` every other interface is taken into account too,
+    /// unless it's ignored.
+    #[cfg_attr(
+        feature = "clap",
+        arg(
+            short, long,
+            value_name = "PATTERN[:FAMILY][:MIN[:MAX]]",
+            value_parser = InterfaceArgument::from_str
+        )
+    )]
+    pub interface: Option<Vec<InterfaceArgument>>,
 
     /// Don't take these interfaces into account
     ///
-    /// By default only loopback interfaces are ignored.
-    #[cfg_attr(feature = "clap", arg(long, conflicts_with = "interface"))]
-    pub ignore: Option<Vec<Box<str>>>,
+    /// Takes the same patterns as `--interface`, e.g. `veth*` or `mac:...`.
+    /// By default only loopback interfaces are ignored. Interfaces required
+    /// by `--interface` or `--interface-kind` are never ignored.
+    #[cfg_attr(
+        feature = "clap",
+        arg(
+            long, value_name = "PATTERN",
+            value_parser = InterfacePattern::from_str
+        )
+    )]
+    pub ignore: Option<Vec<InterfacePattern>>,
+
+    /// Block until at least one interface of each of these kinds has
+    /// appeared, every interface of the kinds is required
+    ///
+    /// Can be combined with `--interface`, an interface is required if it
+    /// matches either.
+    #[cfg_attr(feature = "clap", arg(long, value_enum, value_name = "KIND"))]
+    pub interface_kind: Option<Vec<LinkKind>>,
+
+    /// Don't take interfaces of these kinds into account
+    ///
+    /// Can be combined with `--ignore`. The kinds are resolved from
+    /// `IFLA_INFO_KIND` and the device type with `--backend netlink` or from
+    /// `/sys/class/net` with `--backend getifaddrs`, which can't tell veth
+    /// pairs apart from other virtual links.
+    #[cfg_attr(feature = "clap", arg(long, value_enum, value_name = "KIND"))]
+    pub ignore_kind: Option<Vec<LinkKind>>,
+
+    /// Don't take virtual interfaces into account, e.g. `docker0`, `virbr0`
+    /// or `veth*`
+    ///
+    /// An interface is virtual if it has no backing device in
+    /// `/sys/class/net` or is a bridge, bond, vlan, wireguard, tun or veth
+    /// link. Can be combined with `--ignore` and `--ignore-kind`, the skipped
+    /// interfaces are reported.
+    #[cfg_attr(feature = "clap", arg(long, default_value_t = false))]
+    pub ignore_virtual: bool,
 
     /// Requires at least one IPv4 address
     #[cfg_attr(
@@ -28,6 +629,146 @@ pub struct Args {
     )]
     pub ipv6: bool,
 
+    /// Requires at least one IPv4 or one IPv6 address
+    #[cfg_attr(
+        feature = "clap",
+        arg(
+            long, default_value_t = false,
+            conflicts_with_all = ["ipv4", "ipv6"]
+        )
+    )]
+    pub ipv4_or_ipv6: bool,
+
+    /// Where the address families have to be, on every interface taken into
+    /// account or anywhere on the host
+    ///
+    /// With `host`, `-4 -6` is online when the host has an IPv4 address
+    /// somewhere and an IPv6 address somewhere. Every interface still needs
+    /// an address, of any family or scope.
+    #[cfg_attr(
+        feature = "clap",
+        arg(long, value_enum, value_name = "SCOPE", default_value_t)
+    )]
+    pub family_scope: FamilyScope,
+
+    /// Narrowest scope of an address that counts for `--ipv4` and `--ipv6`
+    ///
+    /// By default link-local and host addresses don't count.
+    #[cfg_attr(
+        feature = "clap",
+        arg(long, value_enum, default_value_t = Self::DEFAULT_ADDRESS_SCOPE)
+    )]
+    pub address_scope: AddressScope,
+
+    /// Operational state an interface needs to be online, as `MIN[:MAX]`
+    ///
+    /// One of missing, off, no-carrier, dormant, degraded-carrier, carrier,
+    /// degraded, enslaved or routable, `MAX` defaults to routable.
+    /// By default an interface is online when it has a carrier.
+    #[cfg_attr(feature = "clap", arg(short, long, value_name = "MIN[:MAX]"))]
+    pub operational_state: Option<OperStateRange>,
+
+    /// Require a default route, of any family if `FAMILY` isn't given
+    #[cfg_attr(
+        feature = "clap",
+        arg(
+            long, value_enum, value_name = "FAMILY",
+            num_args = 0..=1, default_missing_value = "any"
+        )
+    )]
+    pub require_default_route: Option<RouteFamily>,
+
+    /// Require a route that covers this prefix, e.g. `10.0.0.0/8`
+    ///
+    /// A default route covers every prefix of its family.
+    #[cfg_attr(feature = "clap", arg(long, value_name = "CIDR"))]
+    pub route_to: Vec<Cidr>,
+
+    /// Require that the gateway of a default route of this family is
+    /// resolved in the neighbor table (ARP or NDP), not just that the route
+    /// exists
+    ///
+    /// The entry has to be REACHABLE or STALE (or being confirmed again),
+    /// so a link with carrier but a dead switch port doesn't count.
+    #[cfg_attr(
+        feature = "clap",
+        arg(
+            long, value_enum, value_name = "FAMILY",
+            num_args = 0..=1, default_missing_value = "any"
+        )
+    )]
+    pub gateway_neighbor: Option<RouteFamily>,
+
+    /// Actively resolve a missing `--gateway-neighbor` entry instead of
+    /// waiting for other traffic to resolve it
+    ///
+    /// An empty UDP datagram is sent to the gateway, which has the kernel send
+    /// an ARP request or IPv6 neighbor solicitation.
+    #[cfg_attr(
+        feature = "clap",
+        arg(long, default_value_t = false, requires = "gateway_neighbor")
+    )]
+    pub solicit_gateway: bool,
+
+    /// Require that this address is assigned to an interface, e.g. a VIP or
+    /// a DHCP reservation
+    ///
+    /// IPv6 addresses have to pass duplicate address detection first. Can be
+    /// given multiple times, every address is required.
+    #[cfg_attr(feature = "clap", arg(long, value_name = "IP"))]
+    pub address: Vec<IpAddr>,
+
+    /// Require an address in this prefix, assigned to an interface matching
+    /// `INTERFACE` if given, e.g. `192.168.10.0/24@eth0`
+    ///
+    /// The interface is given like `--interface`, without families or
+    /// operational states. Can be given multiple times, every prefix needs an
+    /// address.
+    #[cfg_attr(
+        feature = "clap",
+        arg(
+            long,
+            value_name = "CIDR[@INTERFACE]",
+            value_parser = AddressIn::from_str
+        )
+    )]
+    pub address_in: Vec<AddressIn>,
+
+    /// Require a nameserver in `/etc/resolv.conf`
+    #[cfg_attr(feature = "clap", arg(long, default_value_t = false))]
+    pub dns: bool,
+
+    /// Require that this name can be resolved with `getaddrinfo`
+    ///
+    /// A lookup gives up after 5 seconds or at `--timeout`, like a probe
+    /// attempt.
+    #[cfg_attr(feature = "clap", arg(long, value_name = "NAME"))]
+    pub resolve: Vec<Box<str>>,
+
+    /// Require that this probe succeeds once the interfaces are online
+    ///
+    /// `tcp://HOST:PORT` completes a TCP handshake.
+    /// `icmp://HOST[?count=COUNT][&max-loss=PERCENT]` sends ICMP echo
+    /// requests over an unprivileged ping socket, `net.ipv4.ping_group_range`
+    /// has to allow the group. `icmp://gateway` pings the gateway of a
+    /// default route (use `gateway.` for a host with that name).
+    /// `http://HOST[:PORT][/PATH]` sends a GET request, a response that
+    /// doesn't match `--expect-status` and `--expect-body` (e.g. a redirect)
+    /// means a captive portal is in the way.
+    /// `dns://SERVER[:PORT]/NAME` queries the `A` record of `NAME` over UDP,
+    /// any `NOERROR` answer passes.
+    /// Probes are retried until they succeed or `--timeout` elapses.
+    #[cfg_attr(feature = "clap", arg(long, value_name = "URL"))]
+    pub probe: Vec<Probe>,
+
+    /// Status code `http://` probes have to get, by default any `2xx` status
+    #[cfg_attr(feature = "clap", arg(long, value_name = "CODE"))]
+    pub expect_status: Option<u16>,
+
+    /// Text the body of `http://` probe responses has to start with
+    #[cfg_attr(feature = "clap", arg(long, value_name = "TEXT"))]
+    pub expect_body: Option<Box<str>>,
+
     /// Wait until at least one of the interfaces is online
     ///
     /// If this options is specified with `--interface`, then wait until at
@@ -35,6 +776,78 @@ pub struct Args {
     #[cfg_attr(feature = "clap", arg(long, default_value_t = false))]
     pub any: bool,
 
+    /// Wait until at least `N` (or `N%`) of the interfaces are online, e.g.
+    /// 2 out of 4 uplinks
+    ///
+    /// A required interface that is missing counts as offline.
+    #[cfg_attr(
+        feature = "clap",
+        arg(long, value_name = "N[%]", conflicts_with = "any")
+    )]
+    pub min_online: Option<MinOnline>,
+
+    /// Named group of interfaces that has to be online on its own, as
+    /// `NAME=AGGREGATION:INTERFACE[,INTERFACE...]`
+    ///
+    /// The aggregation is all, any, `N` or `N%` like `--min-online`, the
+    /// interfaces are given like `--interface`, e.g. `uplink=any:eth0,wlan0`
+    /// or `mgmt=all:eno1,eno2`. Can be given multiple times, the network is
+    /// online when every group is. Interfaces outside of the groups aren't
+    /// taken into account.
+    #[cfg_attr(
+        feature = "clap",
+        arg(
+            long,
+            value_name = "NAME=AGGREGATION:INTERFACE[,INTERFACE...]",
+            value_parser = InterfaceGroup::from_str,
+            conflicts_with_all = [
+                "interface", "interface_kind", "ignore", "ignore_kind",
+                "ignore_virtual", "any", "min_online"
+            ]
+        )
+    )]
+    pub group: Vec<InterfaceGroup>,
+
+    /// Boolean expression the interfaces and the host have to satisfy
+    ///
+    /// Terms are combined with `&`, `|`, `!` and parentheses, `&` binds
+    /// tighter than `|`. A term is an interface pattern (quote it with `"` if
+    /// it's also a keyword), `up`, `ipv4`, `ipv6`, an operational state
+    /// (at least that state) or `dns`, e.g.
+    /// `(eth0 & ipv4) | (wlan0 & routable) & dns`. The properties next to an
+    /// interface pattern hold for that interface, without a pattern any
+    /// interface will do. A pattern on its own has to be online. Ignored
+    /// interfaces are left out.
+    #[cfg_attr(
+        feature = "clap",
+        arg(
+            long,
+            value_name = "EXPRESSION",
+            value_parser = Expression::from_str,
+            conflicts_with_all = [
+                "interface", "interface_kind", "any", "min_online", "group"
+            ]
+        )
+    )]
+    pub require: Option<Expression>,
+
+    /// Only wait for the interfaces given with `--interface`,
+    /// `--interface-kind` or `--group` to exist, e.g. a hot-plugged USB NIC
+    ///
+    /// Neither the link state nor the addresses are taken into account, so
+    /// the interfaces can't be given families or operational states.
+    #[cfg_attr(
+        feature = "clap",
+        arg(
+            long,
+            default_value_t = false,
+            conflicts_with_all = [
+                "ipv4", "ipv6", "ipv4_or_ipv6", "operational_state", "require"
+            ]
+        )
+    )]
+    pub presence_only: bool,
+
     /// Maximum time to wait for network connectivity in seconds
     ///
     /// Fail the service if the network is not online by the time the timeout
@@ -43,7 +856,7 @@ pub struct Args {
     #[cfg_attr(feature = "clap", arg( long, default_value_t = Self::DEFAULT_TIMOUT))]
     pub timeout: u64,
 
-    /// Time between checks in ms
+    /// Time between checks in ms when polling
     ///
     /// Must be between inclusive 10 and 10_000.
     #[cfg_attr(feature = "clap", arg(
@@ -51,6 +864,25 @@ pub struct Args {
         value_parser = value_parser!(u64).range(Self::MIN_INTERVAL..=Self::MAX_INTERVAL)
     ))]
     pub interval: u64,
+
+    /// Poll every `--interval` instead of waiting for netlink events
+    ///
+    /// By default the network is only checked again when the kernel reports
+    /// a link, address or route change, polling is used as a fallback if
+    /// subscribing to these changes fails.
+    /// Requirements the kernel doesn't report changes for, like `--dns`, are
+    /// always polled.
+    #[cfg_attr(feature = "clap", arg(long, default_value_t = false))]
+    pub poll: bool,
+
+    /// Source of the interfaces and their addresses
+    ///
+    /// Only netlink reports duplicate address detection, with getifaddrs
+    /// tentative and duplicate IPv6 addresses count like any other address.
+    /// By default netlink is used as soon as IPv6 addresses or address scopes
+    /// are checked, getifaddrs otherwise.
+    #[cfg_attr(feature = "clap", arg(long, value_enum))]
+    pub backend: Option<Backend>,
 }
 
 impl Args {
@@ -62,39 +894,114 @@ impl Args {
 
     const DEFAULT_TIMOUT: u64 = 120;
 
+    const DEFAULT_ADDRESS_SCOPE: AddressScope =
+        InterfacesFamilyTypeArgument::DEFAULT_SCOPE;
+
     #[must_use]
     pub const fn new() -> Self {
         Self {
             interface: None,
             ignore: None,
+            interface_kind: None,
+            ignore_kind: None,
+            ignore_virtual: false,
             timeout: Self::DEFAULT_TIMOUT,
             interval: Self::DEFAULT_INTERVAL,
             ipv4: false,
             ipv6: false,
+            ipv4_or_ipv6: false,
+            family_scope: FamilyScope::Interface,
+            address_scope: Self::DEFAULT_ADDRESS_SCOPE,
+            operational_state: None,
+            require_default_route: None,
+            route_to: Vec::new(),
+            address: Vec::new(),
+            address_in: Vec::new(),
+            gateway_neighbor: None,
+            solicit_gateway: false,
+            dns: false,
+            resolve: Vec::new(),
+            probe: Vec::new(),
+            expect_status: None,
+            expect_body: None,
             any: false,
+            min_online: None,
+            group: Vec::new(),
+            require: None,
+            presence_only: false,
+            poll: false,
+            backend: None,
         }
     }
 
+    /// Clears the ignored interfaces, set the `interface` and `ignore` fields
+    /// directly to use both
     #[must_use]
-    pub fn interface(mut self, interface: Vec<Box<str>>) -> Self {
+    pub fn interface(mut self, interface: Vec<InterfaceArgument>) -> Self {
         self.ignore = None;
+        self.ignore_kind = None;
+        self.ignore_virtual = false;
         self.interface = Some(interface);
         self
     }
 
+    /// Clears the required interfaces, see [`Args::interface()`]
     #[must_use]
-    pub fn ignore(mut self, ignore: Vec<Box<str>>) -> Self {
+    pub fn ignore(mut self, ignore: Vec<InterfacePattern>) -> Self {
         self.interface = None;
+        self.interface_kind = None;
         self.ignore = Some(ignore);
         self
     }
 
+    /// Clears the ignored interfaces, see [`Args::interface()`]
+    #[must_use]
+    pub fn interface_kind(mut self, interface_kind: Vec<LinkKind>) -> Self {
+        self.ignore = None;
+        self.ignore_kind = None;
+        self.ignore_virtual = false;
+        self.interface_kind = Some(interface_kind);
+        self
+    }
+
+    /// Clears the required interfaces, see [`Args::interface()`]
+    #[must_use]
+    pub fn ignore_kind(mut self, ignore_kind: Vec<LinkKind>) -> Self {
+        self.interface = None;
+        self.interface_kind = None;
+        self.ignore_kind = Some(ignore_kind);
+        self
+    }
+
+    /// Clears the required interfaces, see [`Args::interface()`]
+    #[must_use]
+    pub fn ignore_virtual(mut self, ignore_virtual: bool) -> Self {
+        if ignore_virtual {
+            self.interface = None;
+            self.interface_kind = None;
+        }
+        self.ignore_virtual = ignore_virtual;
+        self
+    }
+
     #[must_use]
     pub const fn interval(mut self, interval: u64) -> Self {
         self.interval = interval;
         self
     }
 
+    #[must_use]
+    pub const fn poll(mut self, poll: bool) -> Self {
+        self.poll = poll;
+        self
+    }
+
+    #[must_use]
+    pub const fn backend(mut self, backend: Backend) -> Self {
+        self.backend = Some(backend);
+        self
+    }
+
     #[must_use]
     pub const fn ipv4(mut self, ipv4: bool) -> Self {
         self.ipv4 = ipv4;
@@ -107,11 +1014,281 @@ impl Args {
         self
     }
 
+    #[must_use]
+    pub const fn ipv4_or_ipv6(mut self, ipv4_or_ipv6: bool) -> Self {
+        self.ipv4_or_ipv6 = ipv4_or_ipv6;
+        self
+    }
+
+    #[must_use]
+    pub const fn family_scope(mut self, family_scope: FamilyScope) -> Self {
+        self.family_scope = family_scope;
+        self
+    }
+
+    #[must_use]
+    pub const fn address_scope(mut self, address_scope: AddressScope) -> Self {
+        self.address_scope = address_scope;
+        self
+    }
+
+    #[must_use]
+    pub const fn operational_state(
+        mut self,
+        operational_state: Option<OperStateRange>,
+    ) -> Self {
+        self.operational_state = operational_state;
+        self
+    }
+
+    #[must_use]
+    pub const fn require_default_route(
+        mut self,
+        require_default_route: Option<RouteFamily>,
+    ) -> Self {
+        self.require_default_route = require_default_route;
+        self
+    }
+
+    #[must_use]
+    pub fn route_to(mut self, route_to: Vec<Cidr>) -> Self {
+        self.route_to = route_to;
+        self
+    }
+
+    #[must_use]
+    pub fn address(mut self, address: Vec<IpAddr>) -> Self {
+        self.address = address;
+        self
+    }
+
+    #[must_use]
+    pub fn address_in(mut self, address_in: Vec<AddressIn>) -> Self {
+        self.address_in = address_in;
+        self
+    }
+
+    #[must_use]
+    pub const fn gateway_neighbor(
+        mut self,
+        gateway_neighbor: Option<RouteFamily>,
+    ) -> Self {
+        self.gateway_neighbor = gateway_neighbor;
+        self
+    }
+
+    #[must_use]
+    pub const fn solicit_gateway(mut self, solicit_gateway: bool) -> Self {
+        self.solicit_gateway = solicit_gateway;
+        self
+    }
+
+    #[must_use]
+    pub const fn dns(mut self, dns: bool) -> Self {
+        self.dns = dns;
+        self
+    }
+
+    #[must_use]
+    pub fn resolve(mut self, resolve: Vec<Box<str>>) -> Self {
+        self.resolve = resolve;
+        self
+    }
+
+    #[must_use]
+    pub fn probe(mut self, probe: Vec<Probe>) -> Self {
+        self.probe = probe;
+        self
+    }
+
+    #[must_use]
+    pub const fn expect_status(mut self, expect_status: Option<u16>) -> Self {
+        self.expect_status = expect_status;
+        self
+    }
+
+    #[must_use]
+    pub fn expect_body(mut self, expect_body: Option<Box<str>>) -> Self {
+        self.expect_body = expect_body;
+        self
+    }
+
+    /// Checks if a requirement is used the kernel doesn't report changes for,
+    /// so waiting for netlink events isn't enough
+    #[must_use]
+    pub fn needs_polling(&self) -> bool {
+        self.dns
+            || !self.resolve.is_empty()
+            || !self.probe.is_empty()
+            || self.require.as_ref().is_some_and(Expression::uses_host)
+    }
+
+    /// The `--backend` given, by default [`Backend::Netlink`] if
+    /// [`Args::checks_ipv6`] so duplicate address detection is taken into
+    /// account
+    #[must_use]
+    pub fn selected_backend(&self) -> Backend {
+        self.backend.unwrap_or(if self.checks_ipv6() {
+            Backend::Netlink
+        } else {
+            Backend::Getifaddrs
+        })
+    }
+
+    /// Checks if IPv6 addresses or address scopes are required, e.g. by
+    /// `--ipv6`, `--address-scope` or an `--interface` with `ipv6`
+    #[must_use]
+    pub fn checks_ipv6(&self) -> bool {
+        let interfaces = self
+            .interface
+            .iter()
+            .flatten()
+            .chain(self.group.iter().flat_map(|group| &group.interfaces));
+
+        self.ipv6
+            || self.ipv4_or_ipv6
+            || self.address_scope != Self::DEFAULT_ADDRESS_SCOPE
+            || interfaces
+                .filter_map(|interface| interface.family)
+                .any(InterfaceFamily::ipv6)
+            || self.address.iter().any(IpAddr::is_ipv6)
+            || self
+                .address_in
+                .iter()
+                .any(|address_in| address_in.cidr.address().is_ipv6())
+    }
+
+    /// Checks for arguments that contradict each other, most of these are
+    /// already rejected by clap
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` for the first contradiction found.
+    pub fn validate(&self) -> Result<(), InvalidArgsError> {
+        let ignored = self.ignore.as_deref().unwrap_or_default();
+        if let Some(interface) = self
+            .interface
+            .iter()
+            .flatten()
+            .find(|interface| ignored.contains(&interface.pattern))
+        {
+            return Err(InvalidArgsError::RequiredAndIgnored(
+                interface.pattern.clone(),
+            ));
+        }
+
+        let ignored_kinds = self.ignore_kind.as_deref().unwrap_or_default();
+        if let Some(&kind) = self
+            .interface_kind
+            .iter()
+            .flatten()
+            .find(|kind| ignored_kinds.contains(kind))
+        {
+            return Err(InvalidArgsError::KindRequiredAndIgnored(kind));
+        }
+
+        if self.solicit_gateway && self.gateway_neighbor.is_none() {
+            return Err(InvalidArgsError::SolicitGatewayWithoutNeighbor);
+        }
+
+        if self.ipv4_or_ipv6 && (self.ipv4 || self.ipv6) {
+            return Err(InvalidArgsError::EitherFamilyWithFamily);
+        }
+
+        if self.min_online.is_some() && self.any {
+            return Err(InvalidArgsError::MinOnlineWithAny);
+        }
+
+        if !self.group.is_empty()
+            && (self.interface.is_some()
+                || self.interface_kind.is_some()
+                || self.ignore.is_some()
+                || self.ignore_kind.is_some()
+                || self.ignore_virtual
+                || self.any
+                || self.min_online.is_some())
+        {
+            return Err(InvalidArgsError::GroupWithSelection);
+        }
+        if let Some(group) =
+            self.group.iter().enumerate().find_map(|(i, group)| {
+                self.group[..i]
+                    .iter()
+                    .any(|other| other.name == group.name)
+                    .then_some(group)
+            })
+        {
+            return Err(InvalidArgsError::DuplicateGroup(group.name.clone()));
+        }
+
+        if self.require.is_some()
+            && (self.interface.is_some()
+                || self.interface_kind.is_some()
+                || self.any
+                || self.min_online.is_some()
+                || !self.group.is_empty())
+        {
+            return Err(InvalidArgsError::RequireWithSelection);
+        }
+
+        if self.presence_only {
+            if self.interface.is_none()
+                && self.interface_kind.is_none()
+                && self.group.is_empty()
+            {
+                return Err(InvalidArgsError::PresenceOnlyWithoutInterfaces);
+            }
+
+            let mut interfaces =
+                self.interface.iter().flatten().chain(
+                    self.group.iter().flat_map(|group| &group.interfaces),
+                );
+            if self.ipv4
+                || self.ipv6
+                || self.ipv4_or_ipv6
+                || self.operational_state.is_some()
+                || self.require.is_some()
+                || interfaces.any(|interface| {
+                    interface.family.is_some()
+                        || interface.operational_state.is_some()
+                })
+            {
+                return Err(InvalidArgsError::PresenceOnlyWithState);
+            }
+        }
+
+        Ok(())
+    }
+
     #[must_use]
     pub const fn any(mut self, any: bool) -> Self {
         self.any = any;
         self
     }
+
+    #[must_use]
+    pub const fn min_online(mut self, min_online: Option<MinOnline>) -> Self {
+        self.min_online = min_online;
+        self
+    }
+
+    #[must_use]
+    pub fn group(mut self, group: Vec<InterfaceGroup>) -> Self {
+        self.group = group;
+        self
+    }
+
+    #[must_use]
+    pub fn require(mut self, require: Option<Expression>) -> Self {
+        self.require = require;
+        self
+    }
+
+    #[must_use]
+    pub const fn presence_only(mut self, presence_only: bool) -> Self {
+        self.presence_only = presence_only;
+        self
+    }
 }
 
 impl Default for Args {
@@ -119,3 +1296,364 @@ impl Default for Args {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::operstate::OperState;
+
+    #[test]
+    fn parse_interface() {
+        assert_eq!("eth0".parse(), Ok(InterfaceArgument::from("eth0")));
+        assert_eq!(
+            "eth0:routable".parse(),
+            Ok(InterfaceArgument::new(
+                "eth0".into(),
+                Some(OperStateRange::at_least(OperState::Routable))
+            ))
+        );
+        assert_eq!(
+            "br0:no-carrier:carrier".parse(),
+            Ok(InterfaceArgument::new(
+                "br0".into(),
+                Some(OperStateRange::new(
+                    OperState::NoCarrier,
+                    OperState::Carrier
+                ))
+            ))
+        );
+        assert_eq!(
+            ":routable".parse::<InterfaceArgument>(),
+            Err(ParseInterfaceArgumentError::EmptyName)
+        );
+
+        // Aliases
+        assert_eq!("eth0:1".parse(), Ok(InterfaceArgument::from("eth0:1")));
+        assert_eq!(
+            "eth0:1:routable".parse(),
+            Ok(InterfaceArgument::new(
+                "eth0:1".into(),
+                Some(OperStateRange::at_least(OperState::Routable))
+            ))
+        );
+        assert_eq!("eth0:up".parse(), Ok(InterfaceArgument::from("eth0:up")));
+
+        assert_eq!(
+            "en*:carrier".parse(),
+            Ok(InterfaceArgument::new(
+                InterfacePattern::Glob("en*".into()),
+                Some(OperStateRange::at_least(OperState::Carrier))
+            ))
+        );
+        let regex: InterfaceArgument = "re:(a|b):c:routable".parse().unwrap();
+        assert_eq!(regex.pattern.to_string(), "re:(a|b):c");
+        assert_eq!(
+            regex.operational_state,
+            Some(OperStateRange::at_least(OperState::Routable))
+        );
+        let regex: InterfaceArgument = "re:a:b".parse().unwrap();
+        assert_eq!(regex.pattern.to_string(), "re:a:b");
+        assert_eq!(regex.operational_state, None);
+        assert!(matches!(
+            "re:(:routable".parse::<InterfaceArgument>(),
+            Err(ParseInterfaceArgumentError::Pattern(_))
+        ));
+
+        let mac: InterfaceArgument =
+            "mac:02:fc:00:00:00:01:carrier:routable".parse().unwrap();
+        assert_eq!(
+            mac.pattern,
+            InterfacePattern::Mac([0x02, 0xfc, 0, 0, 0, 0x01].into())
+        );
+        assert_eq!(
+            mac.operational_state,
+            Some(OperStateRange::new(OperState::Carrier, OperState::Routable))
+        );
+        assert_eq!(
+            "index:3:routable".parse(),
+            Ok(InterfaceArgument::new(
+                InterfacePattern::Index(3),
+                Some(OperStateRange::at_least(OperState::Routable))
+            ))
+        );
+        assert!(matches!(
+            "index:3:up".parse::<InterfaceArgument>(),
+            Err(ParseInterfaceArgumentError::Pattern(_))
+        ));
+    }
+
+    #[test]
+    fn parse_interface_family() {
+        assert_eq!(
+            "eth0:ipv4".parse(),
+            Ok(InterfaceArgument::from("eth0")
+                .family(Some(InterfaceFamily::Ipv4)))
+        );
+        assert_eq!(
+            "wg0:ipv6:routable".parse(),
+            Ok(InterfaceArgument::new(
+                "wg0".into(),
+                Some(OperStateRange::at_least(OperState::Routable))
+            )
+            .family(Some(InterfaceFamily::Ipv6)))
+        );
+        assert_eq!(
+            "br0:ipv4+ipv6:no-carrier:carrier".parse(),
+            Ok(InterfaceArgument::new(
+                "br0".into(),
+                Some(OperStateRange::new(
+                    OperState::NoCarrier,
+                    OperState::Carrier
+                ))
+            )
+            .family(Some(InterfaceFamily::Both)))
+        );
+        assert_eq!(
+            "eth0:1:ipv4".parse(),
+            Ok(InterfaceArgument::from("eth0:1")
+                .family(Some(InterfaceFamily::Ipv4)))
+        );
+        assert_eq!(
+            "eth0:ipv5".parse(),
+            Ok(InterfaceArgument::from("eth0:ipv5"))
+        );
+
+        let regex: InterfaceArgument = "re:(a|b):c:ipv6".parse().unwrap();
+        assert_eq!(regex.pattern.to_string(), "re:(a|b):c");
+        assert_eq!(regex.family, Some(InterfaceFamily::Ipv6));
+        assert_eq!(regex.operational_state, None);
+        let mac: InterfaceArgument =
+            "mac:02:fc:00:00:00:01:ipv4:routable".parse().unwrap();
+        assert_eq!(mac.pattern.to_string(), "mac:02:fc:00:00:00:01");
+        assert_eq!(mac.family, Some(InterfaceFamily::Ipv4));
+        assert_eq!(
+            mac.operational_state,
+            Some(OperStateRange::at_least(OperState::Routable))
+        );
+    }
+
+    #[test]
+    fn parse_min_online() {
+        assert_eq!("2".parse(), Ok(MinOnline::Count(2)));
+        assert_eq!("50%".parse(), Ok(MinOnline::Percent(50)));
+        assert_eq!("100%".parse::<MinOnline>().unwrap().to_string(), "100%");
+        for invalid in ["0", "0%", "101%", "-1", "%", "two", "2 %"] {
+            assert!(invalid.parse::<MinOnline>().is_err(), "{invalid}");
+        }
+
+        assert_eq!(MinOnline::Count(2).required(4), 2);
+        assert_eq!(MinOnline::Percent(50).required(4), 2);
+        assert_eq!(MinOnline::Percent(50).required(3), 2);
+        assert_eq!(MinOnline::Percent(1).required(1), 1);
+        assert_eq!(MinOnline::Percent(100).required(3), 3);
+    }
+
+    #[test]
+    fn parse_address_in() {
+        let address_in: AddressIn = "192.168.10.0/24".parse().unwrap();
+        assert_eq!(address_in.cidr, "192.168.10.0/24".parse().unwrap());
+        assert_eq!(address_in.interface, None);
+        assert_eq!(address_in.to_string(), "192.168.10.0/24");
+
+        let address_in: AddressIn = "fd00::/64@en*".parse().unwrap();
+        assert!(matches!(
+            address_in.interface,
+            Some(InterfacePattern::Glob(_))
+        ));
+        assert_eq!(address_in.to_string(), "fd00::/64@en*");
+        let address_in: AddressIn = "10.0.0.5@eth0".parse().unwrap();
+        assert_eq!(address_in.cidr.prefix_len(), 32);
+
+        assert!(matches!(
+            "10.0.0/8".parse::<AddressIn>(),
+            Err(ParseAddressInError::Cidr(ParseCidrError::Address(_)))
+        ));
+        assert!(matches!(
+            "10.0.0.0/33@eth0".parse::<AddressIn>(),
+            Err(ParseAddressInError::Cidr(ParseCidrError::PrefixLen(_)))
+        ));
+        assert_eq!(
+            "10.0.0.0/8@index:0".parse::<AddressIn>(),
+            Err(ParseAddressInError::Interface(
+                ParseInterfacePatternError::InvalidIndex
+            ))
+        );
+    }
+
+    #[test]
+    fn parse_interface_group() {
+        let group: InterfaceGroup =
+            "uplink=any:eth0,wlan0:ipv4".parse().unwrap();
+        assert_eq!(&*group.name, "uplink");
+        assert_eq!(group.aggregation, Aggregation::Any);
+        assert_eq!(
+            group.interfaces,
+            [
+                InterfaceArgument::from("eth0"),
+                InterfaceArgument::from("wlan0")
+                    .family(Some(InterfaceFamily::Ipv4))
+            ]
+        );
+
+        let group: InterfaceGroup = "mgmt=all:eno1".parse().unwrap();
+        assert_eq!(group.aggregation, Aggregation::All);
+        let group: InterfaceGroup = "wan=50%:re:wan[0-9]+".parse().unwrap();
+        assert_eq!(
+            group.aggregation,
+            Aggregation::AtLeast(MinOnline::Percent(50))
+        );
+        assert_eq!(group.interfaces[0].pattern.to_string(), "re:wan[0-9]+");
+
+        assert_eq!(
+            "any:eth0".parse::<InterfaceGroup>(),
+            Err(ParseInterfaceGroupError::MissingName)
+        );
+        assert_eq!(
+            "=any:eth0".parse::<InterfaceGroup>(),
+            Err(ParseInterfaceGroupError::MissingName)
+        );
+        assert_eq!(
+            "uplink=any".parse::<InterfaceGroup>(),
+            Err(ParseInterfaceGroupError::MissingInterfaces)
+        );
+        assert_eq!(
+            "uplink=some:eth0".parse::<InterfaceGroup>(),
+            Err(ParseInterfaceGroupError::Aggregation("some".into()))
+        );
+        assert_eq!(
+            "uplink=any:eth0,".parse::<InterfaceGroup>(),
+            Err(ParseInterfaceGroupError::Interface(
+                ParseInterfaceArgumentError::EmptyName
+            ))
+        );
+    }
+
+    #[test]
+    fn validate() {
+        assert_eq!(Args::new().validate(), Ok(()));
+
+        let args = Args {
+            interface: Some(vec!["eth0".into(), "en*".parse().unwrap()]),
+            ignore: Some(vec!["veth*".parse().unwrap()]),
+            interface_kind: Some(vec![LinkKind::Ethernet]),
+            ignore_kind: Some(vec![LinkKind::Bridge]),
+            ..Args::new()
+        };
+        assert_eq!(args.validate(), Ok(()));
+
+        let args = Args {
+            ignore: Some(vec!["en*".parse().unwrap()]),
+            ..args
+        };
+        assert_eq!(
+            args.validate(),
+            Err(InvalidArgsError::RequiredAndIgnored(
+                InterfacePattern::Glob("en*".into())
+            ))
+        );
+
+        let args = Args {
+            ignore: None,
+            ignore_kind: Some(vec![LinkKind::Ethernet]),
+            ..args
+        };
+        assert_eq!(
+            args.validate(),
+            Err(InvalidArgsError::KindRequiredAndIgnored(LinkKind::Ethernet))
+        );
+
+        let args = Args::new().solicit_gateway(true);
+        assert_eq!(
+            args.validate(),
+            Err(InvalidArgsError::SolicitGatewayWithoutNeighbor)
+        );
+        let args = args.gateway_neighbor(Some(RouteFamily::Any));
+        assert_eq!(args.validate(), Ok(()));
+
+        let args = Args::new().ipv4_or_ipv6(true).ipv6(true);
+        assert_eq!(
+            args.validate(),
+            Err(InvalidArgsError::EitherFamilyWithFamily)
+        );
+        let args = args.ipv6(false);
+        assert_eq!(args.validate(), Ok(()));
+
+        let args = Args::new().min_online(Some(MinOnline::Count(2))).any(true);
+        assert_eq!(args.validate(), Err(InvalidArgsError::MinOnlineWithAny));
+
+        let group = |group: &str| group.parse::<InterfaceGroup>().unwrap();
+        let args = Args::new().group(vec![group("a=any:eth0")]).any(true);
+        assert_eq!(args.validate(), Err(InvalidArgsError::GroupWithSelection));
+        let args =
+            Args::new().group(vec![group("a=any:eth0"), group("b=all:eth1")]);
+        assert_eq!(args.validate(), Ok(()));
+        let args = args.group(vec![group("a=any:eth0"), group("a=all:eth1")]);
+        assert_eq!(
+            args.validate(),
+            Err(InvalidArgsError::DuplicateGroup("a".into()))
+        );
+
+        let require = || "eth0 & ipv4".parse::<Expression>().ok();
+        let args = Args::new().require(require()).any(true);
+        assert_eq!(
+            args.validate(),
+            Err(InvalidArgsError::RequireWithSelection)
+        );
+        let args = Args::new().require(require()).ignore(vec!["wlan0".into()]);
+        assert_eq!(args.validate(), Ok(()));
+        assert!(!args.needs_polling());
+        let args = args.require("eth0 & dns".parse().ok());
+        assert!(args.needs_polling());
+
+        let args = Args::new().presence_only(true);
+        assert_eq!(
+            args.validate(),
+            Err(InvalidArgsError::PresenceOnlyWithoutInterfaces)
+        );
+        let args = args.interface(vec!["usb0".into()]);
+        assert_eq!(args.validate(), Ok(()));
+        let args = args.ipv4(true);
+        assert_eq!(
+            args.validate(),
+            Err(InvalidArgsError::PresenceOnlyWithState)
+        );
+        let args = Args::new()
+            .presence_only(true)
+            .interface(vec!["usb0:ipv4".parse().unwrap()]);
+        assert_eq!(
+            args.validate(),
+            Err(InvalidArgsError::PresenceOnlyWithState)
+        );
+    }
+
+    #[test]
+    fn selected_backend() {
+        assert_eq!(Args::new().selected_backend(), Backend::Getifaddrs);
+        assert_eq!(
+            Args::new().ipv4(true).selected_backend(),
+            Backend::Getifaddrs
+        );
+        assert_eq!(
+            Args::new()
+                .address(vec!["192.0.2.2".parse().unwrap()])
+                .selected_backend(),
+            Backend::Getifaddrs
+        );
+
+        // Tentative and duplicate IPv6 addresses mustn't count
+        for args in [
+            Args::new().ipv6(true),
+            Args::new().address_scope(AddressScope::Link),
+            Args::new().interface(vec!["eth0:ipv4+ipv6".parse().unwrap()]),
+            Args::new().address(vec!["2001:db8::2".parse().unwrap()]),
+            Args::new().address_in(vec!["2001:db8::/64".parse().unwrap()]),
+        ] {
+            assert_eq!(args.selected_backend(), Backend::Netlink);
+            assert_eq!(
+                args.backend(Backend::Getifaddrs).selected_backend(),
+                Backend::Getifaddrs
+            );
+        }
+    }
+}
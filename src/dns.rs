@@ -0,0 +1,170 @@
+use std::{
+    fs, io,
+    net::{IpAddr, SocketAddr, ToSocketAddrs},
+    path::Path,
+    sync::mpsc,
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::probe;
+
+/// Resolver configuration read by libc
+pub const RESOLV_CONF: &str = "/etc/resolv.conf";
+
+/// Gets the nameservers of a `resolv.conf(5)`
+///
+/// Unparsable and unspecified (`0.0.0.0`, `::`) addresses are skipped, as is
+/// the zone index of IPv6 link-local addresses.
+#[must_use]
+pub fn nameservers(resolv_conf: &str) -> Vec<IpAddr> {
+    resolv_conf
+        .lines()
+        .filter_map(|line| {
+            let mut words = line.split_whitespace();
+            (words.next()? == "nameserver")
+                .then(|| words.next())
+                .flatten()
+        })
+        .filter_map(|nameserver| {
+            let address = nameserver.split('%').next().unwrap_or(nameserver);
+            address.parse::<IpAddr>().ok()
+        })
+        .filter(|address| !address.is_unspecified())
+        .collect()
+}
+
+/// Checks if the resolver configuration at `path` has at least one usable
+/// nameserver, a missing file has none.
+///
+/// # Errors
+///
+/// Will return `Err` if `path` exists but can't be read.
+pub fn has_nameserver(path: &Path) -> Result<bool, io::Error> {
+    match fs::read_to_string(path) {
+        Ok(resolv_conf) => Ok(!nameservers(&resolv_conf).is_empty()),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(false),
+        Err(err) => Err(err),
+    }
+}
+
+/// Checks if `name` resolves to at least one address with `getaddrinfo(3)`
+///
+/// Like a probe attempt the lookup ends at `deadline` or after
+/// [`probe::ATTEMPT_TIMEOUT`], whichever comes first.
+#[must_use]
+pub fn resolves(name: &str, deadline: Option<Instant>) -> bool {
+    probe::attempt_timeout(deadline)
+        .and_then(|timeout| resolve(name, 0, timeout))
+        .is_some_and(|addresses| !addresses.is_empty())
+}
+
+/// Resolves `host` to the addresses to reach `port` on with `getaddrinfo(3)`
+/// within `timeout`, `None` if that fails or takes too long
+///
+/// `getaddrinfo` can't be interrupted, so it runs on a helper thread that's
+/// left behind on timeout. IP addresses are used as is.
+#[must_use]
+pub fn resolve(
+    host: &str,
+    port: u16,
+    timeout: Duration,
+) -> Option<Vec<SocketAddr>> {
+    if let Ok(address) = host.parse::<IpAddr>() {
+        return Some(vec![(address, port).into()]);
+    }
+    if timeout.is_zero() {
+        return None;
+    }
+
+    let (sender, receiver) = mpsc::channel();
+    let host = host.to_owned();
+    thread::spawn(move || {
+        let addresses = (host.as_str(), port)
+            .to_socket_addrs()
+            .map(Iterator::collect);
+        // The receiver is gone if the lookup took too long
+        let _ = sender.send(addresses);
+    });
+
+    receiver.recv_timeout(timeout).ok()?.ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::{env, process, time::Duration};
+
+    #[test]
+    fn parse_nameservers() {
+        let resolv_conf = "\
+# Generated by NetworkManager
+search example.com
+nameserver 192.0.2.53
+; nameserver 192.0.2.54
+#nameserver 192.0.2.55
+nameserver\t2001:db8::53
+nameserver fe80::1%eth0
+nameserver 0.0.0.0
+nameserver example.com
+nameserver
+options edns0
+";
+        assert_eq!(
+            nameservers(resolv_conf),
+            [
+                "192.0.2.53".parse::<IpAddr>().unwrap(),
+                "2001:db8::53".parse().unwrap(),
+                "fe80::1".parse().unwrap(),
+            ]
+        );
+        assert!(nameservers("").is_empty());
+        assert!(nameservers("search example.com\n").is_empty());
+    }
+
+    #[test]
+    fn has_nameserver_file() {
+        let path = env::temp_dir()
+            .join(format!("wait-online-resolv-{}.conf", process::id()));
+
+        assert!(!has_nameserver(&path).unwrap());
+
+        fs::write(&path, "search example.com\n").unwrap();
+        assert!(!has_nameserver(&path).unwrap());
+
+        fs::write(&path, "nameserver 127.0.0.53\n").unwrap();
+        assert!(has_nameserver(&path).unwrap());
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn resolves_name() {
+        assert!(resolves("127.0.0.1", None));
+        assert!(resolves(
+            "::1",
+            Some(Instant::now() + probe::ATTEMPT_TIMEOUT)
+        ));
+        assert!(resolves("localhost", None));
+        // The deadline passed
+        let passed = Instant::now().checked_sub(Duration::from_secs(1));
+        assert!(!resolves("127.0.0.1", passed));
+        assert!(!resolves("localhost", passed));
+    }
+
+    #[test]
+    fn resolve_addresses() {
+        assert_eq!(
+            resolve("192.0.2.1", 80, Duration::ZERO),
+            Some(vec!["192.0.2.1:80".parse().unwrap()])
+        );
+        let addresses = resolve("localhost", 80, probe::ATTEMPT_TIMEOUT);
+        assert!(addresses.is_some_and(|addresses| {
+            addresses.iter().all(|address| {
+                address.ip().is_loopback() && address.port() == 80
+            })
+        }));
+        assert_eq!(resolve("localhost", 80, Duration::ZERO), None);
+    }
+}
@@ -0,0 +1,680 @@
+use std::{fmt, str::FromStr};
+
+use crate::{
+    operstate::OperState,
+    pattern::{InterfacePattern, ParseInterfacePatternError},
+};
+
+/// Boolean expression over the interfaces and the host, parsed from
+/// `--require`
+///
+/// `!` binds tighter than `&`, which binds tighter than `|`, parentheses
+/// group. A term is a keyword, an interface pattern or a quoted (`"..."`)
+/// interface pattern, e.g. `(eth0 & ipv4) | (wlan0 & routable) & dns`.
+///
+/// The [`Property`] terms of a `&` hold for the interfaces selected next to
+/// them, `eth0 & wlan0 & ipv4` requires both interfaces to have an IPv4
+/// address. This includes properties combined with host terms,
+/// `eth0 & (ipv4 | dns)` requires eth0 to have an IPv4 address or a
+/// nameserver. Without a selected interface any interface will do, a pattern
+/// without properties requires an interface that is online.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Expression {
+    Term(Term),
+    Not(Box<Expression>),
+    And(Vec<Expression>),
+    Or(Vec<Expression>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Term {
+    /// Every word that isn't a keyword and every quoted word
+    Interface(InterfacePattern),
+    Property(Property),
+    Host(HostProperty),
+}
+
+/// Property of an interface
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Property {
+    /// `up`, the interface is online like it has to be without `--require`
+    Up,
+    /// `ipv4`, the interface is up and has an IPv4 address of at least
+    /// `--address-scope`
+    Ipv4,
+    /// `ipv6`, like [`Property::Ipv4`]
+    Ipv6,
+    /// The name of an operational state, the interface is at least in that
+    /// state
+    OperState(OperState),
+}
+
+/// Property of the host
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HostProperty {
+    /// `dns`, there is a nameserver in `/etc/resolv.conf`
+    Dns,
+}
+
+impl Term {
+    fn from_word(word: &str) -> Result<Self, ParseInterfacePatternError> {
+        Ok(match word {
+            "up" => Self::Property(Property::Up),
+            "ipv4" => Self::Property(Property::Ipv4),
+            "ipv6" => Self::Property(Property::Ipv6),
+            "dns" => Self::Host(HostProperty::Dns),
+            _ => match word.parse() {
+                Ok(state) => Self::Property(Property::OperState(state)),
+                Err(_) => Self::Interface(word.parse()?),
+            },
+        })
+    }
+}
+
+/// State of an interface an [`Expression`] is evaluated against
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct InterfaceFacts {
+    pub(crate) online: bool,
+    pub(crate) ipv4: bool,
+    pub(crate) ipv6: bool,
+    pub(crate) operstate: OperState,
+}
+
+impl InterfaceFacts {
+    fn has(self, property: Property) -> bool {
+        match property {
+            Property::Up => self.online,
+            Property::Ipv4 => self.ipv4,
+            Property::Ipv6 => self.ipv6,
+            Property::OperState(state) => self.operstate >= state,
+        }
+    }
+}
+
+/// What an [`Expression`] is evaluated against
+struct Context<'c> {
+    interfaces: &'c [InterfaceFacts],
+    /// Checks if the interface at an index of `interfaces` matches a pattern
+    matches: &'c dyn Fn(&InterfacePattern, usize) -> bool,
+    host: &'c dyn Fn(HostProperty) -> bool,
+}
+
+impl Expression {
+    /// Evaluates the expression, `matches` checks if the interface at an index
+    /// of `interfaces` matches a pattern and `host` gets the host properties
+    pub(crate) fn evaluate(
+        &self,
+        interfaces: &[InterfaceFacts],
+        matches: &dyn Fn(&InterfacePattern, usize) -> bool,
+        host: &dyn Fn(HostProperty) -> bool,
+    ) -> bool {
+        let context = Context {
+            interfaces,
+            matches,
+            host,
+        };
+        self.evaluate_host(&context, &[])
+    }
+
+    /// Evaluates the expression for the whole host, `properties` have to hold
+    /// for the interfaces selected by it
+    fn evaluate_host(&self, context: &Context, properties: &[&Self]) -> bool {
+        if self.is_property() {
+            let mut properties = properties.to_vec();
+            properties.push(self);
+            return Self::any_interface(context, None, &properties);
+        }
+
+        match self {
+            Self::Term(Term::Interface(pattern)) => {
+                Self::any_interface(context, Some(pattern), properties)
+            }
+            Self::Term(Term::Host(property)) => (context.host)(*property),
+            // Handled above
+            Self::Term(Term::Property(_)) => true,
+            Self::Not(expression) => {
+                !expression.evaluate_host(context, properties)
+            }
+            Self::Or(expressions) => expressions.iter().any(|expression| {
+                expression.evaluate_host(context, properties)
+            }),
+            Self::And(expressions) => {
+                let (own, rest): (Vec<_>, Vec<_>) = expressions
+                    .iter()
+                    .partition(|expression| expression.binds());
+                let mut properties = properties.to_vec();
+                properties.extend(own);
+
+                if rest.iter().any(|expression| expression.selects()) {
+                    rest.iter().all(|expression| {
+                        expression.evaluate_host(context, &properties)
+                    })
+                } else {
+                    (properties.is_empty()
+                        || Self::any_interface(context, None, &properties))
+                        && rest.iter().all(|expression| {
+                            expression.evaluate_host(context, &[])
+                        })
+                }
+            }
+        }
+    }
+
+    /// Checks if an interface matching `pattern` has all `properties`, or is
+    /// online without properties
+    fn any_interface(
+        context: &Context,
+        pattern: Option<&InterfacePattern>,
+        properties: &[&Self],
+    ) -> bool {
+        context
+            .interfaces
+            .iter()
+            .enumerate()
+            .any(|(i, &interface)| {
+                pattern.map_or(true, |pattern| (context.matches)(pattern, i))
+                    && if properties.is_empty() {
+                        interface.online
+                    } else {
+                        properties.iter().all(|expression| {
+                            expression.evaluate_interface(context, i, interface)
+                        })
+                    }
+            })
+    }
+
+    /// Evaluates the expression for the interface at index `i`
+    fn evaluate_interface(
+        &self,
+        context: &Context,
+        i: usize,
+        interface: InterfaceFacts,
+    ) -> bool {
+        match self {
+            Self::Term(Term::Interface(pattern)) => {
+                (context.matches)(pattern, i)
+            }
+            Self::Term(Term::Property(property)) => interface.has(*property),
+            Self::Term(Term::Host(property)) => (context.host)(*property),
+            Self::Not(expression) => {
+                !expression.evaluate_interface(context, i, interface)
+            }
+            Self::And(expressions) => expressions.iter().all(|expression| {
+                expression.evaluate_interface(context, i, interface)
+            }),
+            Self::Or(expressions) => expressions.iter().any(|expression| {
+                expression.evaluate_interface(context, i, interface)
+            }),
+        }
+    }
+
+    /// Checks if the expression only consists of interface properties
+    fn is_property(&self) -> bool {
+        match self {
+            Self::Term(term) => matches!(term, Term::Property(_)),
+            Self::Not(expression) => expression.is_property(),
+            Self::And(expressions) | Self::Or(expressions) => {
+                expressions.iter().all(Self::is_property)
+            }
+        }
+    }
+
+    /// Checks if the expression has to hold for the interfaces selected next
+    /// to it, i.e. it has properties but no interface pattern
+    fn binds(&self) -> bool {
+        self.any_term(&|term| matches!(term, Term::Property(_)))
+            && !self.selects()
+    }
+
+    /// Checks if the expression uses a [`HostProperty`], the kernel doesn't
+    /// report changes of these
+    #[must_use]
+    pub fn uses_host(&self) -> bool {
+        self.any_term(&|term| matches!(term, Term::Host(_)))
+    }
+
+    /// Checks if the expression contains an interface pattern
+    fn selects(&self) -> bool {
+        self.any_term(&|term| matches!(term, Term::Interface(_)))
+    }
+
+    fn any_term(&self, f: &dyn Fn(&Term) -> bool) -> bool {
+        match self {
+            Self::Term(term) => f(term),
+            Self::Not(expression) => expression.any_term(f),
+            Self::And(expressions) | Self::Or(expressions) => {
+                expressions.iter().any(|expression| expression.any_term(f))
+            }
+        }
+    }
+}
+
+impl FromStr for Expression {
+    type Err = ParseExpressionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser {
+            tokens: tokenize(s)?,
+            position: 0,
+            end: s.chars().count() + 1,
+        };
+        let expression = parser.or()?;
+
+        match parser.peek() {
+            None => Ok(expression),
+            Some((column, Token::Close)) => Err(ParseExpressionError::new(
+                column,
+                ParseExpressionErrorKind::UnmatchedClose,
+            )),
+            Some((column, _)) => Err(ParseExpressionError::new(
+                column,
+                ParseExpressionErrorKind::ExpectedOperator,
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Open,
+    Close,
+    And,
+    Or,
+    Not,
+    Word(Box<str>),
+    /// Always an interface pattern
+    Quoted(Box<str>),
+}
+
+/// Splits `s` into tokens with their (1-based) column
+fn tokenize(s: &str) -> Result<Vec<(usize, Token)>, ParseExpressionError> {
+    let mut tokens = Vec::new();
+    let mut chars = s.chars().zip(1..).peekable();
+
+    while let Some((c, column)) = chars.next() {
+        let token = match c {
+            _ if c.is_whitespace() => continue,
+            '(' => Token::Open,
+            ')' => Token::Close,
+            '&' => Token::And,
+            '|' => Token::Or,
+            '!' => Token::Not,
+            '"' => {
+                let mut quoted = String::new();
+                loop {
+                    match chars.next() {
+                        Some(('"', _)) => break,
+                        Some((c, _)) => quoted.push(c),
+                        None => {
+                            return Err(ParseExpressionError::new(
+                                column,
+                                ParseExpressionErrorKind::UnterminatedQuote,
+                            ))
+                        }
+                    }
+                }
+                Token::Quoted(quoted.into())
+            }
+            _ => {
+                let mut word = String::from(c);
+                while let Some(&(c, _)) = chars.peek() {
+                    if c.is_whitespace() || "()&|!\"".contains(c) {
+                        break;
+                    }
+                    word.push(c);
+                    chars.next();
+                }
+                Token::Word(word.into())
+            }
+        };
+        tokens.push((column, token));
+    }
+
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<(usize, Token)>,
+    position: usize,
+    /// Column after the last character
+    end: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<(usize, &Token)> {
+        self.tokens
+            .get(self.position)
+            .map(|(column, token)| (*column, token))
+    }
+
+    /// Column of the next token
+    fn column(&self) -> usize {
+        self.peek().map_or(self.end, |(column, _)| column)
+    }
+
+    fn next_if(&mut self, token: &Token) -> bool {
+        let found = self.peek().is_some_and(|(_, next)| next == token);
+        if found {
+            self.position += 1;
+        }
+        found
+    }
+
+    /// `AND ('|' AND)*`
+    fn or(&mut self) -> Result<Expression, ParseExpressionError> {
+        let mut expressions = vec![self.and()?];
+        while self.next_if(&Token::Or) {
+            expressions.push(self.and()?);
+        }
+
+        Ok(match expressions.len() {
+            1 => expressions.remove(0),
+            _ => Expression::Or(expressions),
+        })
+    }
+
+    /// `UNARY ('&' UNARY)*`
+    fn and(&mut self) -> Result<Expression, ParseExpressionError> {
+        let mut expressions = vec![self.unary()?];
+        while self.next_if(&Token::And) {
+            expressions.push(self.unary()?);
+        }
+
+        Ok(match expressions.len() {
+            1 => expressions.remove(0),
+            _ => Expression::And(expressions),
+        })
+    }
+
+    /// `'!' UNARY | '(' OR ')' | TERM`
+    fn unary(&mut self) -> Result<Expression, ParseExpressionError> {
+        let column = self.column();
+        let Some((_, token)) = self.peek() else {
+            return Err(ParseExpressionError::new(
+                column,
+                ParseExpressionErrorKind::ExpectedTerm,
+            ));
+        };
+
+        let expression = match token {
+            Token::Not => {
+                self.position += 1;
+                return Ok(Expression::Not(Box::new(self.unary()?)));
+            }
+            Token::Open => {
+                self.position += 1;
+                let expression = self.or()?;
+                if !self.next_if(&Token::Close) {
+                    return Err(ParseExpressionError::new(
+                        self.column(),
+                        ParseExpressionErrorKind::ExpectedClose(column),
+                    ));
+                }
+                return Ok(expression);
+            }
+            Token::Word(word) => Term::from_word(word),
+            Token::Quoted(pattern) => pattern.parse().map(Term::Interface),
+            Token::Close | Token::And | Token::Or => {
+                return Err(ParseExpressionError::new(
+                    column,
+                    ParseExpressionErrorKind::ExpectedTerm,
+                ))
+            }
+        }
+        .map_err(|err| {
+            ParseExpressionError::new(
+                column,
+                ParseExpressionErrorKind::Pattern(err),
+            )
+        })?;
+        self.position += 1;
+
+        Ok(Expression::Term(expression))
+    }
+}
+
+/// Error returned when parsing an invalid [`Expression`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseExpressionError {
+    /// Column (1-based, in characters) the error was found at
+    pub column: usize,
+    pub kind: ParseExpressionErrorKind,
+}
+
+impl ParseExpressionError {
+    const fn new(column: usize, kind: ParseExpressionErrorKind) -> Self {
+        Self { column, kind }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseExpressionErrorKind {
+    /// Nothing, an operator or `)` instead of a term
+    ExpectedTerm,
+    /// Two terms without an operator in between
+    ExpectedOperator,
+    /// The `(` at the column isn't closed
+    ExpectedClose(usize),
+    UnmatchedClose,
+    UnterminatedQuote,
+    Pattern(ParseInterfacePatternError),
+}
+
+impl fmt::Display for ParseExpressionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            ParseExpressionErrorKind::ExpectedTerm => {
+                f.write_str("expected an interface, a keyword, `!` or `(`")?;
+            }
+            ParseExpressionErrorKind::ExpectedOperator => {
+                f.write_str("expected `&`, `|` or `)`")?;
+            }
+            ParseExpressionErrorKind::ExpectedClose(open) => {
+                return write!(
+                    f,
+                    "expected `)` at column {}, to close the `(` at column \
+                     {open}",
+                    self.column
+                );
+            }
+            ParseExpressionErrorKind::UnmatchedClose => {
+                f.write_str("`)` without `(`")?;
+            }
+            ParseExpressionErrorKind::UnterminatedQuote => {
+                f.write_str("`\"` isn't closed")?;
+            }
+            ParseExpressionErrorKind::Pattern(err) => err.fmt(f)?,
+        }
+        write!(f, " at column {}", self.column)
+    }
+}
+
+impl std::error::Error for ParseExpressionError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn interface(name: &str) -> Expression {
+        Expression::Term(Term::Interface(name.into()))
+    }
+
+    fn property(property: Property) -> Expression {
+        Expression::Term(Term::Property(property))
+    }
+
+    #[test]
+    fn parse() {
+        let dns = Expression::Term(Term::Host(HostProperty::Dns));
+        assert_eq!(
+            "(eth0 & ipv4) | (wlan0 & routable) & dns".parse(),
+            Ok(Expression::Or(vec![
+                Expression::And(vec![
+                    interface("eth0"),
+                    property(Property::Ipv4)
+                ]),
+                Expression::And(vec![
+                    Expression::And(vec![
+                        interface("wlan0"),
+                        property(Property::OperState(OperState::Routable))
+                    ]),
+                    dns
+                ]),
+            ]))
+        );
+        assert_eq!(
+            "!eth0&\"dns\"|up".parse(),
+            Ok(Expression::Or(vec![
+                Expression::And(vec![
+                    Expression::Not(Box::new(interface("eth0"))),
+                    interface("dns"),
+                ]),
+                property(Property::Up),
+            ]))
+        );
+        assert_eq!(
+            "!!no-carrier".parse(),
+            Ok(Expression::Not(Box::new(Expression::Not(Box::new(
+                property(Property::OperState(OperState::NoCarrier))
+            )))))
+        );
+
+        let uses_host = |s: &str| s.parse::<Expression>().unwrap().uses_host();
+        assert!(uses_host("eth0 & !(ipv4 | dns)"));
+        assert!(!uses_host("eth0 & ipv4 | \"dns\""));
+
+        let pattern: Expression = "en* | re:^wl".parse().unwrap();
+        let Expression::Or(patterns) = pattern else {
+            panic!("{pattern:?}");
+        };
+        assert!(matches!(
+            patterns[..],
+            [
+                Expression::Term(Term::Interface(InterfacePattern::Glob(_))),
+                Expression::Term(Term::Interface(InterfacePattern::Regex(_)))
+            ]
+        ));
+    }
+
+    #[test]
+    fn parse_errors() {
+        let error = |s: &str| s.parse::<Expression>().unwrap_err();
+        let at = |column, kind| ParseExpressionError::new(column, kind);
+
+        assert_eq!(error(""), at(1, ParseExpressionErrorKind::ExpectedTerm));
+        assert_eq!(
+            error("eth0 &"),
+            at(7, ParseExpressionErrorKind::ExpectedTerm)
+        );
+        assert_eq!(
+            error("eth0 | & ipv4"),
+            at(8, ParseExpressionErrorKind::ExpectedTerm)
+        );
+        assert_eq!(
+            error("eth0 wlan0"),
+            at(6, ParseExpressionErrorKind::ExpectedOperator)
+        );
+        assert_eq!(
+            error("dns & (eth0 | wlan0"),
+            at(20, ParseExpressionErrorKind::ExpectedClose(7))
+        );
+        assert_eq!(
+            error("(eth0 ipv4)"),
+            at(7, ParseExpressionErrorKind::ExpectedClose(1))
+        );
+        assert_eq!(
+            error("eth0)"),
+            at(5, ParseExpressionErrorKind::UnmatchedClose)
+        );
+        assert_eq!(
+            error("ipv4 & \"eth0"),
+            at(8, ParseExpressionErrorKind::UnterminatedQuote)
+        );
+        assert_eq!(
+            error("up & index:0"),
+            at(
+                6,
+                ParseExpressionErrorKind::Pattern(
+                    ParseInterfacePatternError::InvalidIndex
+                )
+            )
+        );
+        // Columns count characters, not bytes
+        assert_eq!(
+            error("\"é\" ("),
+            at(5, ParseExpressionErrorKind::ExpectedOperator)
+        );
+
+        assert_eq!(
+            error("eth0 wlan0").to_string(),
+            "expected `&`, `|` or `)` at column 6"
+        );
+        assert_eq!(
+            error("dns & (eth0 | wlan0").to_string(),
+            "expected `)` at column 20, to close the `(` at column 7"
+        );
+    }
+
+    #[test]
+    fn evaluate() {
+        let facts = |online, ipv4, ipv6, operstate| InterfaceFacts {
+            online,
+            ipv4,
+            ipv6,
+            operstate,
+        };
+        let names = ["eth0", "wlan0", "wg0"];
+        let interfaces = [
+            facts(true, true, false, OperState::Routable),
+            facts(true, false, true, OperState::Degraded),
+            facts(false, false, false, OperState::NoCarrier),
+        ];
+        let evaluate = |expression: &str, dns: bool| {
+            expression.parse::<Expression>().unwrap().evaluate(
+                &interfaces,
+                &|pattern, i| pattern.matches(names[i].as_bytes()),
+                &|HostProperty::Dns| dns,
+            )
+        };
+
+        let example = "(eth0 & ipv4) | (wlan0 & routable) & dns";
+        assert!(evaluate(example, false));
+        assert!(!evaluate("(eth0 & ipv6) | (wlan0 & routable) & dns", true));
+        assert!(evaluate("(eth0 & ipv6) | (wlan0 & degraded) & dns", true));
+        assert!(!evaluate("(eth0 & ipv6) | (wlan0 & degraded) & dns", false));
+
+        // Patterns without properties need an online interface
+        assert!(evaluate("eth0 & wlan0", false));
+        assert!(!evaluate("eth0 & wg0", false));
+        assert!(evaluate("eth0 & !wg0", false));
+        assert!(evaluate("wg0 & no-carrier", false));
+        assert!(!evaluate("missing0", false));
+
+        // Properties hold for every interface next to them
+        assert!(!evaluate("eth0 & wlan0 & ipv4", false));
+        assert!(evaluate("(eth0 | wlan0) & ipv6", false));
+        assert!(evaluate("eth0 & wlan0 & (ipv4 | ipv6)", false));
+        assert!(evaluate("eth* & !ipv6", false));
+        assert!(!evaluate("eth* & !ipv4", false));
+        assert!(!evaluate("wlan0 & !ipv6", false));
+
+        // Also when they are combined with host terms
+        assert!(evaluate("eth0 & (ipv4 | dns)", false));
+        assert!(!evaluate("wlan0 & (ipv4 | dns)", false));
+        assert!(evaluate("wlan0 & (ipv4 | dns)", true));
+        assert!(evaluate("wlan0 & !(ipv4 | dns)", false));
+        assert!(!evaluate("eth0 & !(ipv4 | dns)", false));
+        assert!(!evaluate("eth0 & wlan0 & (ipv4 & dns)", true));
+        assert!(evaluate("(ipv6 | dns) & degraded", false));
+        assert!(!evaluate("(ipv6 | dns) & routable", false));
+        assert!(evaluate("(ipv6 | dns) & routable", true));
+
+        // Without a pattern any interface will do
+        assert!(evaluate("ipv4 & ipv6 | dns", true));
+        assert!(!evaluate("ipv4 & ipv6", false));
+        assert!(evaluate("ipv4 & routable & dns", true));
+        assert!(!evaluate("ipv6 & routable", true));
+        assert!(evaluate("ipv6 & dns & wlan0", true));
+        assert!(!evaluate("ipv4 & dns & wlan0", true));
+    }
+}
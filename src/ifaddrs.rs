@@ -1,10 +1,203 @@
-use std::{ffi, io, mem};
+use std::{
+    borrow::Cow, cell::OnceCell, ffi, fs, io, marker::PhantomData, mem,
+    net::IpAddr, os::unix::ffi::OsStrExt, path::Path,
+};
 
-use crate::{errno, libc};
+use crate::{
+    arguments::{Args, InterfaceArgument},
+    errno,
+    kind::{self, LinkKind, SYS_CLASS_NET},
+    libc,
+    pattern::{parse_hardware_address, InterfacePattern},
+    sockaddr::{
+        get_addres_family, get_address, AddressFamily, AddressScope, DadState,
+    },
+    udev,
+};
 
 // Re-export in case we need a wrapper later
-pub(crate) use libc::ifaddrs;
-pub(crate) use nix::net::if_::InterfaceFlags;
+pub use nix::net::if_::InterfaceFlags;
+
+/// Entry of an interface address list
+///
+/// Implemented for [`Ifaddr`] from [`getifaddrs`] and for
+/// [`crate::netlink::InterfaceEntry`] from [`crate::netlink::dump`].
+pub trait InterfaceAddress<'a>: Copy {
+    /// Name of the interface
+    fn name(&self) -> &'a [u8];
+
+    /// Flags of the interface
+    fn flags(&self) -> InterfaceFlags;
+
+    /// Family of the entry's address,
+    /// `None` if there is no address or the family is unknown
+    fn family(&self) -> Option<AddressFamily>;
+
+    /// The entry's address, `None` if there is no IPv4 or IPv6 address
+    fn address(&self) -> Option<IpAddr>;
+
+    /// Scope of the entry's address,
+    /// `None` if there is no IPv4 or IPv6 address
+    fn scope(&self) -> Option<AddressScope>;
+
+    /// Duplicate address detection state of the entry's address,
+    /// `None` if there is no address or this is unknown
+    fn dad_state(&self) -> Option<DadState> {
+        None
+    }
+
+    /// RFC 2863 operational state the kernel reports for the interface
+    /// (`IF_OPER_*`), `None` if this is unknown
+    fn oper_status(&self) -> Option<u8> {
+        None
+    }
+
+    /// Whether the interface has a carrier, `None` if this is unknown
+    fn carrier(&self) -> Option<bool> {
+        None
+    }
+
+    /// Index of the bond or bridge master the interface is enslaved to,
+    /// `None` if it isn't enslaved or this is unknown
+    fn master(&self) -> Option<u32> {
+        None
+    }
+
+    /// Kind of the interface's link,
+    /// `None` if it isn't one of the [`LinkKind`]s or this is unknown
+    fn kind(&self) -> Option<LinkKind> {
+        None
+    }
+
+    /// Checks if the interface is virtual, i.e. has no backing device or is
+    /// of a virtual [`LinkKind`]
+    fn is_virtual(&self) -> bool {
+        !kind::has_device(self.name())
+            || self.kind().is_some_and(LinkKind::is_virtual)
+    }
+
+    /// Index of the interface, `None` if this is unknown
+    fn index(&self) -> Option<u32> {
+        None
+    }
+
+    /// Hardware (MAC) address of the interface,
+    /// `None` if it has none or this is unknown
+    fn hardware_address(&self) -> Option<Cow<'a, [u8]>> {
+        None
+    }
+
+    /// Alternative names of the interface (`IFLA_ALT_IFNAME`)
+    fn alt_names(&self) -> &'a [Box<[u8]>] {
+        &[]
+    }
+
+    /// Predictable names udev generated for the interface, see
+    /// [`udev::net_names`]
+    fn udev_names(&self) -> Cow<'a, [Box<[u8]>]> {
+        Cow::Owned(self.index().map_or_else(Vec::new, udev::net_names))
+    }
+}
+
+/// Entry of the list from [`getifaddrs`], borrowing the list
+#[derive(Clone, Copy)]
+pub struct Ifaddr<'a> {
+    ifaddr: libc::ifaddrs,
+    list: PhantomData<&'a libc::ifaddrs>,
+    /// Lookups shared by the entries of the interface, `None` if the entry
+    /// isn't part of a [`InterfaceAddresses`]
+    lookups: Option<&'a Lookups>,
+}
+
+impl<'a> Ifaddr<'a> {
+    /// Wraps `ifaddr`, the index and udev names of its interface are looked
+    /// up on every call
+    ///
+    /// # Safety
+    ///
+    /// `ifa_name` must be a valid C string and `ifa_addr` a valid `sockaddr`
+    /// or null for `'a`, this is the case for entries of [`getifaddrs`] as
+    /// long as the list is alive.
+    #[must_use]
+    pub const unsafe fn new(ifaddr: libc::ifaddrs) -> Self {
+        Self {
+            ifaddr,
+            list: PhantomData,
+            lookups: None,
+        }
+    }
+
+    /// Index of the interface from `if_nametoindex`
+    fn lookup_index(&self) -> Option<u32> {
+        // SAFETY: `ifa_name` is valid for `'a`, see `Ifaddr::new`
+        let index = unsafe { libc::if_nametoindex(self.ifaddr.ifa_name) };
+        (index != 0).then_some(index)
+    }
+
+    /// Predictable names of the interface from the udev database
+    fn lookup_udev_names(&self) -> Vec<Box<[u8]>> {
+        self.index().map_or_else(Vec::new, udev::net_names)
+    }
+}
+
+impl<'a> InterfaceAddress<'a> for Ifaddr<'a> {
+    fn name(&self) -> &'a [u8] {
+        // SAFETY: `ifa_name` is valid for `'a`, see `Ifaddr::new`
+        unsafe { ffi::CStr::from_ptr(self.ifaddr.ifa_name) }.to_bytes()
+    }
+
+    fn flags(&self) -> InterfaceFlags {
+        #[allow(clippy::cast_possible_wrap)]
+        InterfaceFlags::from_bits_truncate(self.ifaddr.ifa_flags as libc::c_int)
+    }
+
+    fn family(&self) -> Option<AddressFamily> {
+        // SAFETY: `ifa_addr` is valid or null for `'a`, see `Ifaddr::new`
+        unsafe { get_addres_family(self.ifaddr.ifa_addr) }
+    }
+
+    fn address(&self) -> Option<IpAddr> {
+        // SAFETY: `ifa_addr` is valid or null for `'a`, see `Ifaddr::new`
+        unsafe { get_address(self.ifaddr.ifa_addr) }
+    }
+
+    fn scope(&self) -> Option<AddressScope> {
+        self.address().map(AddressScope::from_ip)
+    }
+
+    fn kind(&self) -> Option<LinkKind> {
+        LinkKind::from_sysfs(self.name())
+    }
+
+    fn index(&self) -> Option<u32> {
+        match self.lookups {
+            Some(lookups) => *lookups.index.get_or_init(|| self.lookup_index()),
+            None => self.lookup_index(),
+        }
+    }
+
+    fn hardware_address(&self) -> Option<Cow<'a, [u8]>> {
+        hardware_address_from_sysfs(self.name()).map(Cow::Owned)
+    }
+
+    fn udev_names(&self) -> Cow<'a, [Box<[u8]>]> {
+        match self.lookups {
+            Some(lookups) => Cow::Borrowed(
+                lookups.udev_names.get_or_init(|| self.lookup_udev_names()),
+            ),
+            None => Cow::Owned(self.lookup_udev_names()),
+        }
+    }
+}
+
+/// Hardware address of the interface `name` from `/sys/class/net`, `None` for
+/// interfaces without one (e.g. tun devices)
+fn hardware_address_from_sysfs(name: &[u8]) -> Option<Vec<u8>> {
+    let path = Path::new(SYS_CLASS_NET)
+        .join(ffi::OsStr::from_bytes(name))
+        .join("address");
+    parse_hardware_address(fs::read_to_string(path).ok()?.trim())
+}
 
 /// Checks if an interface is up.
 /// Loopback interfaces return None
@@ -20,11 +213,13 @@ pub(crate) use nix::net::if_::InterfaceFlags;
 /// | `IFF_UP`       | LOWERLAYERDOWN |
 /// | `IFF_LOWER_UP` | UP             |
 #[must_use]
-pub fn is_interface_up(ifaddr: libc::ifaddrs) -> Option<bool> {
+pub fn is_interface_up<'a, T>(ifaddr: T) -> Option<bool>
+where
+    T: InterfaceAddress<'a>,
+{
     const MASK: i32 = InterfaceFlags::IFF_LOWER_UP.bits();
 
-    #[allow(clippy::cast_possible_wrap)]
-    let ifa_flags = ifaddr.ifa_flags as i32;
+    let ifa_flags = ifaddr.flags().bits();
 
     (ifa_flags & InterfaceFlags::IFF_LOOPBACK.bits() == 0)
         .then_some(ifa_flags & MASK != 0)
@@ -36,7 +231,7 @@ pub fn is_interface_up(ifaddr: libc::ifaddrs) -> Option<bool> {
 ///
 /// Will return `Err` if [`libc::getifaddrs`] errors.
 /// For more info see [getifaddrs(3)](https://man7.org/linux/man-pages/man3/getifaddrs.3.html#ERRORS)
-pub fn getifaddrs() -> Result<InterfaceAddressIterator, io::Error> {
+pub fn getifaddrs() -> Result<InterfaceAddresses, io::Error> {
     let mut addrs = mem::MaybeUninit::<*mut libc::ifaddrs>::uninit();
     unsafe {
         let ret: libc::c_int = libc::getifaddrs(addrs.as_mut_ptr());
@@ -44,64 +239,110 @@ pub fn getifaddrs() -> Result<InterfaceAddressIterator, io::Error> {
             return Err(errno::last());
         };
 
-        Ok(InterfaceAddressIterator {
+        let mut ifaddrs = InterfaceAddresses {
             base: addrs.assume_init(),
-            next: addrs.assume_init(),
-        })
+            lookups: Vec::new(),
+        };
+        let mut names: Vec<&[u8]> =
+            ifaddrs.iter().map(|ifaddr| ifaddr.name()).collect();
+        names.sort_unstable();
+        names.dedup();
+        let lookups = names
+            .into_iter()
+            .map(|name| Lookups {
+                name: name.into(),
+                ..Lookups::default()
+            })
+            .collect();
+        ifaddrs.lookups = lookups;
+
+        Ok(ifaddrs)
     }
 }
 
-/// Safe to use iterator over `libc::ifaddrs` from `libc::getifaddrs`
-pub struct InterfaceAddressIterator {
+/// List of `libc::ifaddrs` from `libc::getifaddrs`, freed on drop
+pub struct InterfaceAddresses {
     /// Head linked list returned by `ifaddrs`
     ///
     /// needed for [`libc::freeifaddrs()`].
     base: *mut libc::ifaddrs,
-    next: *mut libc::ifaddrs,
+    /// One entry per interface, sorted by name
+    lookups: Vec<Lookups>,
 }
 
-impl Iterator for InterfaceAddressIterator {
-    type Item = libc::ifaddrs;
-    fn next(&mut self) -> Option<<Self as Iterator>::Item> {
-        match unsafe { self.next.as_ref() } {
-            Some(ifaddr) => {
-                self.next = ifaddr.ifa_next;
-                Some(*ifaddr)
-            }
-            None => None,
+/// Data of an interface that is looked up at most once per
+/// [`InterfaceAddresses`], no matter how many entries and patterns need it
+#[derive(Debug, Default)]
+struct Lookups {
+    name: Box<[u8]>,
+    index: OnceCell<Option<u32>>,
+    udev_names: OnceCell<Vec<Box<[u8]>>>,
+}
+
+impl InterfaceAddresses {
+    /// Iterates over the entries, which borrow the list
+    #[must_use]
+    pub const fn iter(&self) -> InterfaceAddressIterator<'_> {
+        InterfaceAddressIterator {
+            next: self.base,
+            list: self,
         }
     }
 }
 
-impl Drop for InterfaceAddressIterator {
+impl Drop for InterfaceAddresses {
     fn drop(&mut self) {
         unsafe { libc::freeifaddrs(self.base) };
     }
 }
 
-/// Checks if waiting is needed for provided `ifa_name`
-///
-/// # Safety
-///
-/// `ifa_name` must be a valid ptr from [`ifaddrs`]
-pub(crate) unsafe fn check_require_or_ignore(
-    ifa_name: *mut libc::c_char,
+/// Safe to use iterator over the entries of [`InterfaceAddresses`]
+pub struct InterfaceAddressIterator<'a> {
+    next: *mut libc::ifaddrs,
+    list: &'a InterfaceAddresses,
+}
+
+impl<'a> Iterator for InterfaceAddressIterator<'a> {
+    type Item = Ifaddr<'a>;
+    fn next(&mut self) -> Option<<Self as Iterator>::Item> {
+        // SAFETY: `next` is null or an entry of the list, which is alive for
+        // `'a`
+        let ifaddr = unsafe { self.next.as_ref() }?;
+        self.next = ifaddr.ifa_next;
+        // SAFETY: The list and so the entry's pointers are alive for `'a`
+        let mut ifaddr = unsafe { Ifaddr::new(*ifaddr) };
+        ifaddr.lookups = self
+            .list
+            .lookups
+            .binary_search_by(|lookups| (*lookups.name).cmp(ifaddr.name()))
+            .ok()
+            .map(|position| &self.list.lookups[position]);
+
+        Some(ifaddr)
+    }
+}
+
+/// Checks if waiting is needed for `ifaddr`'s interface
+pub(crate) fn check_require_or_ignore<'a, T>(
+    ifaddr: T,
     require_or_irgnore_argument: InterfacesRequireOrIgnoreArgument,
-) -> bool {
-    // _not_ in interfaces => require => true
-    //                     => ignore  => false
-    //
-    // _in_ interfaces     => require => false
-    //                     => ignore  => true
-    let ifa_name = ffi::CStr::from_ptr(ifa_name);
-    let ifa_name = ifa_name.to_bytes();
-    let in_interface = require_or_irgnore_argument
-        .interfaces
-        .iter()
-        .any(|interface| interface.as_bytes() == ifa_name);
-
-    (require_or_irgnore_argument.action == InterfacesActionArgument::Ignore)
-        ^ in_interface
+) -> bool
+where
+    T: InterfaceAddress<'a>,
+{
+    match require_or_irgnore_argument {
+        InterfacesRequireOrIgnoreArgument::Require(required) => {
+            required.contains(ifaddr)
+        }
+        InterfacesRequireOrIgnoreArgument::Ignore(ignored) => {
+            !ignored.contains(ifaddr)
+        }
+        // A required interface is never ignored
+        InterfacesRequireOrIgnoreArgument::RequireAndIgnore(
+            required,
+            ignored,
+        ) => required.contains(ifaddr) || !ignored.contains(ifaddr),
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -110,42 +351,135 @@ pub enum InterfacesActionArgument {
     Require,
 }
 
+/// Interfaces required by `--interface` and `--interface-kind`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RequiredInterfaces<'a> {
+    pub interfaces: &'a [InterfaceArgument],
+    pub kinds: &'a [LinkKind],
+}
+
+/// Interfaces ignored by `--ignore`, `--ignore-kind` and `--ignore-virtual`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct IgnoredInterfaces<'a> {
+    pub patterns: &'a [InterfacePattern],
+    pub kinds: &'a [LinkKind],
+    /// Also ignore virtual interfaces, see
+    /// [`InterfaceAddress::is_virtual`]
+    pub virtual_interfaces: bool,
+}
+
+impl RequiredInterfaces<'_> {
+    /// Checks if `ifaddr`'s interface matches one of the interface patterns
+    /// or kinds, see [`InterfacePattern::matches_interface`]
+    #[must_use]
+    pub fn contains<'i, T>(self, ifaddr: T) -> bool
+    where
+        T: InterfaceAddress<'i>,
+    {
+        self.interfaces
+            .iter()
+            .any(|interface| interface.pattern.matches_interface(ifaddr))
+            || matches_kind(self.kinds, ifaddr)
+    }
+}
+
+impl IgnoredInterfaces<'_> {
+    /// Checks if `ifaddr`'s interface matches one of the interface patterns
+    /// or kinds or is virtual and virtual interfaces are ignored
+    #[must_use]
+    pub fn contains<'i, T>(self, ifaddr: T) -> bool
+    where
+        T: InterfaceAddress<'i>,
+    {
+        self.patterns
+            .iter()
+            .any(|pattern| pattern.matches_interface(ifaddr))
+            || matches_kind(self.kinds, ifaddr)
+            || (self.virtual_interfaces && ifaddr.is_virtual())
+    }
+}
+
+/// Checks if `ifaddr`'s interface is of one of the `kinds`
+///
+/// The kind is only looked up if needed, this can mean reading sysfs.
+fn matches_kind<'i, T>(kinds: &[LinkKind], ifaddr: T) -> bool
+where
+    T: InterfaceAddress<'i>,
+{
+    !kinds.is_empty() && ifaddr.kind().is_some_and(|kind| kinds.contains(&kind))
+}
+
+/// Interfaces selected by name pattern or by kind, from `--interface` and
+/// `--interface-kind` and/or from `--ignore`, `--ignore-kind` and
+/// `--ignore-virtual`
 #[derive(Debug, Clone, Copy)]
-pub struct InterfacesRequireOrIgnoreArgument<'a> {
-    pub(crate) interfaces: &'a [Box<str>],
-    pub(crate) action: InterfacesActionArgument,
+pub enum InterfacesRequireOrIgnoreArgument<'a> {
+    Require(RequiredInterfaces<'a>),
+    Ignore(IgnoredInterfaces<'a>),
+    /// The required interfaces and every other interface that isn't ignored
+    RequireAndIgnore(RequiredInterfaces<'a>, IgnoredInterfaces<'a>),
 }
 
 impl<'a> InterfacesRequireOrIgnoreArgument<'a> {
+    /// [`InterfacesActionArgument::Require`] as soon as interfaces are
+    /// required, these have to be waited for even if other interfaces are
+    /// ignored
     #[must_use]
-    pub(crate) const fn new(
-        interfaces: &'a [Box<str>],
-        action: InterfacesActionArgument,
-    ) -> Self {
-        Self { interfaces, action }
+    pub const fn action(self) -> InterfacesActionArgument {
+        match self {
+            Self::Require(_) | Self::RequireAndIgnore(..) => {
+                InterfacesActionArgument::Require
+            }
+            Self::Ignore(_) => InterfacesActionArgument::Ignore,
+        }
     }
 
+    /// The required interfaces, if any
     #[must_use]
-    pub fn from_args(
-        interface: Option<&'a [Box<str>]>,
-        ignore: Option<&'a [Box<str>]>,
-    ) -> Option<Self> {
-        Self::parse_args(interface, ignore)
-            .map(|(interfaces, action)| Self::new(interfaces, action))
+    pub const fn required(self) -> Option<RequiredInterfaces<'a>> {
+        match self {
+            Self::Require(required) | Self::RequireAndIgnore(required, _) => {
+                Some(required)
+            }
+            Self::Ignore(_) => None,
+        }
     }
+
+    /// The ignored interfaces, if any
     #[must_use]
-    pub fn parse_args(
-        interface: Option<&'a [Box<str>]>,
-        ignore: Option<&'a [Box<str>]>,
-    ) -> Option<(&'a [Box<str>], InterfacesActionArgument)> {
-        use InterfacesActionArgument::{Ignore, Require};
-        match (interface, ignore) {
+    pub const fn ignored(self) -> Option<IgnoredInterfaces<'a>> {
+        match self {
+            Self::Ignore(ignored) | Self::RequireAndIgnore(_, ignored) => {
+                Some(ignored)
+            }
+            Self::Require(_) => None,
+        }
+    }
+
+    #[must_use]
+    pub fn from_args(args: &'a Args) -> Option<Self> {
+        let required = (args.interface.is_some()
+            || args.interface_kind.is_some())
+        .then(|| RequiredInterfaces {
+            interfaces: args.interface.as_deref().unwrap_or_default(),
+            kinds: args.interface_kind.as_deref().unwrap_or_default(),
+        });
+        let ignored = (args.ignore.is_some()
+            || args.ignore_kind.is_some()
+            || args.ignore_virtual)
+            .then(|| IgnoredInterfaces {
+                patterns: args.ignore.as_deref().unwrap_or_default(),
+                kinds: args.ignore_kind.as_deref().unwrap_or_default(),
+                virtual_interfaces: args.ignore_virtual,
+            });
+
+        match (required, ignored) {
             (None, None) => None,
-            (Some(interfaces), None) => Some((interfaces, Require)),
-            (None, Some(interfaces)) => Some((interfaces, Ignore)),
-            _ => unreachable!(
-                "`interfaces` and `ignore` can never be set at the same time"
-            ),
+            (Some(required), None) => Some(Self::Require(required)),
+            (None, Some(ignored)) => Some(Self::Ignore(ignored)),
+            (Some(required), Some(ignored)) => {
+                Some(Self::RequireAndIgnore(required, ignored))
+            }
         }
     }
 }
@@ -154,24 +488,63 @@ impl<'a> InterfacesRequireOrIgnoreArgument<'a> {
 mod tests {
     use super::*;
 
-    use std::{ffi::CString, iter};
+    use std::{iter, ptr};
+
+    /// Interface with only a name and a kind, without a kind it's virtual
+    #[derive(Clone, Copy)]
+    struct MockInterface<'a>(&'a str, Option<LinkKind>);
+
+    impl<'a> InterfaceAddress<'a> for MockInterface<'a> {
+        fn name(&self) -> &'a [u8] {
+            self.0.as_bytes()
+        }
+
+        fn flags(&self) -> InterfaceFlags {
+            InterfaceFlags::empty()
+        }
+
+        fn family(&self) -> Option<AddressFamily> {
+            None
+        }
+
+        fn address(&self) -> Option<IpAddr> {
+            None
+        }
+
+        fn scope(&self) -> Option<AddressScope> {
+            None
+        }
+
+        fn kind(&self) -> Option<LinkKind> {
+            self.1
+        }
 
-    use crate::libc;
+        fn is_virtual(&self) -> bool {
+            self.1.map_or(true, LinkKind::is_virtual)
+        }
+    }
 
     #[test]
     fn check_require_or_ignore_ignore() {
-        let action = InterfacesActionArgument::Ignore;
-
-        let interfaces: Box<[Box<str>]> =
+        let interfaces: Box<[InterfacePattern]> =
             iter::once("eth0").map(Into::into).collect();
-        let arg = InterfacesRequireOrIgnoreArgument::new(&interfaces, action);
+        let arg =
+            InterfacesRequireOrIgnoreArgument::Ignore(IgnoredInterfaces {
+                patterns: &interfaces,
+                kinds: &[],
+                virtual_interfaces: false,
+            });
         let combinations = [("eth0", arg, false), ("eth1", arg, true)];
         check_combinations(&combinations);
 
-        let interfaces: Box<[Box<str>]> =
+        let interfaces: Box<[InterfacePattern]> =
             ["eth0", "eth1"].into_iter().map(Into::into).collect();
-        let action = InterfacesActionArgument::Ignore;
-        let arg = InterfacesRequireOrIgnoreArgument::new(&interfaces, action);
+        let arg =
+            InterfacesRequireOrIgnoreArgument::Ignore(IgnoredInterfaces {
+                patterns: &interfaces,
+                kinds: &[],
+                virtual_interfaces: false,
+            });
         let combinations = [
             ("eth0", arg, false),
             ("eth1", arg, false),
@@ -183,17 +556,23 @@ mod tests {
 
     #[test]
     fn check_require_or_ignore_require() {
-        let action = InterfacesActionArgument::Require;
-
-        let interfaces: Box<[Box<str>]> =
+        let interfaces: Box<[InterfaceArgument]> =
             iter::once("eth0").map(Into::into).collect();
-        let arg = InterfacesRequireOrIgnoreArgument::new(&interfaces, action);
+        let arg =
+            InterfacesRequireOrIgnoreArgument::Require(RequiredInterfaces {
+                interfaces: &interfaces,
+                kinds: &[],
+            });
         let combinations = [("eth0", arg, true), ("eth1", arg, false)];
         check_combinations(&combinations);
 
-        let interfaces: Box<[Box<str>]> =
+        let interfaces: Box<[InterfaceArgument]> =
             ["eth0", "eth1"].into_iter().map(Into::into).collect();
-        let arg = InterfacesRequireOrIgnoreArgument::new(&interfaces, action);
+        let arg =
+            InterfacesRequireOrIgnoreArgument::Require(RequiredInterfaces {
+                interfaces: &interfaces,
+                kinds: &[],
+            });
         let combinations = [
             ("eth0", arg, true),
             ("eth1", arg, true),
@@ -203,16 +582,124 @@ mod tests {
         check_combinations(&combinations);
     }
 
+    #[test]
+    fn check_require_or_ignore_pattern() {
+        let patterns: Box<[InterfacePattern]> = ["veth*", "re:docker[0-9]+"]
+            .into_iter()
+            .map(|pattern| pattern.parse().unwrap())
+            .collect();
+        let arg =
+            InterfacesRequireOrIgnoreArgument::Ignore(IgnoredInterfaces {
+                patterns: &patterns,
+                kinds: &[],
+                virtual_interfaces: false,
+            });
+        let combinations = [
+            ("veth1a2b3c", arg, false),
+            ("docker0", arg, false),
+            ("dockerx", arg, true),
+            ("eth0", arg, true),
+        ];
+        check_combinations(&combinations);
+
+        let interfaces: Box<[InterfaceArgument]> = ["en*", "wl?0"]
+            .into_iter()
+            .map(|interface| interface.parse().unwrap())
+            .collect();
+        let arg =
+            InterfacesRequireOrIgnoreArgument::Require(RequiredInterfaces {
+                interfaces: &interfaces,
+                kinds: &[],
+            });
+        let combinations = [
+            ("enp3s0", arg, true),
+            ("wlp0", arg, true),
+            ("wlp10", arg, false),
+            ("eth0", arg, false),
+        ];
+        check_combinations(&combinations);
+    }
+
+    #[test]
+    fn check_require_or_ignore_kind() {
+        let veth = MockInterface("veth0", Some(LinkKind::Veth));
+        let bridge = MockInterface("br0", Some(LinkKind::Bridge));
+        let wlan = MockInterface("wlan0", Some(LinkKind::Wlan));
+        let unknown = MockInterface("dummy0", None);
+
+        let patterns: Box<[InterfacePattern]> =
+            iter::once("dummy0").map(Into::into).collect();
+        let arg =
+            InterfacesRequireOrIgnoreArgument::Ignore(IgnoredInterfaces {
+                patterns: &patterns,
+                kinds: &[LinkKind::Veth, LinkKind::Bridge],
+                virtual_interfaces: false,
+            });
+        assert!(!check_require_or_ignore(veth, arg));
+        assert!(!check_require_or_ignore(bridge, arg));
+        assert!(check_require_or_ignore(wlan, arg));
+        assert!(!check_require_or_ignore(unknown, arg));
+
+        let arg =
+            InterfacesRequireOrIgnoreArgument::Require(RequiredInterfaces {
+                interfaces: &[],
+                kinds: &[LinkKind::Wlan, LinkKind::Ethernet],
+            });
+        assert!(!check_require_or_ignore(veth, arg));
+        assert!(check_require_or_ignore(wlan, arg));
+        assert!(!check_require_or_ignore(unknown, arg));
+    }
+
+    #[test]
+    fn check_require_or_ignore_virtual() {
+        let patterns: Box<[InterfacePattern]> =
+            iter::once("eth1").map(Into::into).collect();
+        let arg =
+            InterfacesRequireOrIgnoreArgument::Ignore(IgnoredInterfaces {
+                patterns: &patterns,
+                kinds: &[],
+                virtual_interfaces: true,
+            });
+
+        for (ifaddr, expected) in [
+            (MockInterface("eth0", Some(LinkKind::Ethernet)), true),
+            (MockInterface("eth1", Some(LinkKind::Ethernet)), false),
+            (MockInterface("wlan0", Some(LinkKind::Wlan)), true),
+            (MockInterface("docker0", Some(LinkKind::Bridge)), false),
+            (MockInterface("veth0", Some(LinkKind::Veth)), false),
+            (MockInterface("dummy0", None), false),
+        ] {
+            assert_eq!(check_require_or_ignore(ifaddr, arg), expected);
+        }
+    }
+
     fn check_combinations(
         combinations: &[(&str, InterfacesRequireOrIgnoreArgument, bool)],
     ) {
         for combination in combinations {
-            let ifa_name = CString::new(combination.0).unwrap();
-            let ifa_name_ptr: *mut libc::c_char =
-                ifa_name.as_ptr().cast_mut().cast::<libc::c_char>();
-            let ret =
-                unsafe { check_require_or_ignore(ifa_name_ptr, combination.1) };
+            let ret = check_require_or_ignore(
+                MockInterface(combination.0, None),
+                combination.1,
+            );
             assert_eq!(ret, combination.2);
         }
     }
+
+    #[test]
+    fn lookups_shared() {
+        let ifaddrs = getifaddrs().unwrap();
+        let entries: Vec<_> = ifaddrs
+            .iter()
+            .filter(|ifaddr| ifaddr.name() == b"lo")
+            .collect();
+        assert!(!entries.is_empty());
+
+        for ifaddr in &entries {
+            let lookups = ifaddr.lookups.unwrap();
+            assert!(ptr::eq(lookups, entries[0].lookups.unwrap()));
+            assert_eq!(ifaddr.index(), entries[0].lookup_index());
+            assert_eq!(lookups.index.get(), Some(&ifaddr.index()));
+            assert!(matches!(ifaddr.udev_names(), Cow::Borrowed(_)));
+        }
+    }
 }
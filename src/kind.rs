@@ -0,0 +1,278 @@
+use std::{
+    ffi::OsStr,
+    fmt, fs,
+    os::unix::ffi::OsStrExt,
+    path::{Path, PathBuf},
+};
+
+#[cfg(feature = "clap")]
+use clap::ValueEnum;
+
+use crate::libc;
+
+/// Where the kernel exposes the network interfaces
+pub(crate) const SYS_CLASS_NET: &str = "/sys/class/net";
+
+/// Kind of link, selected with `--interface-kind` and `--ignore-kind`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "clap", derive(ValueEnum))]
+pub enum LinkKind {
+    /// Physical (or paravirtualized) ethernet device
+    Ethernet,
+    /// Wireless LAN device
+    Wlan,
+    Bridge,
+    Bond,
+    Vlan,
+    Wireguard,
+    /// TUN or TAP device
+    Tun,
+    /// Virtual ethernet pair
+    Veth,
+}
+
+impl LinkKind {
+    /// Kind of a link with the `IFLA_INFO_KIND` `kind`
+    #[must_use]
+    pub fn from_info_kind(kind: &str) -> Option<Self> {
+        match kind {
+            "bridge" => Some(Self::Bridge),
+            "bond" => Some(Self::Bond),
+            "vlan" => Some(Self::Vlan),
+            "wireguard" => Some(Self::Wireguard),
+            "tun" => Some(Self::Tun),
+            "veth" => Some(Self::Veth),
+            _ => None,
+        }
+    }
+
+    /// Kind of a device with the `DEVTYPE` `devtype` (see `uevent` in sysfs)
+    #[must_use]
+    pub fn from_devtype(devtype: &str) -> Option<Self> {
+        match devtype {
+            "wlan" => Some(Self::Wlan),
+            "bridge" => Some(Self::Bridge),
+            "bond" => Some(Self::Bond),
+            "vlan" => Some(Self::Vlan),
+            "wireguard" => Some(Self::Wireguard),
+            _ => None,
+        }
+    }
+
+    /// Kind of a netlink link with the `IFLA_INFO_KIND` `info_kind` and the
+    /// device type `link_type` (`ARPHRD_*`)
+    ///
+    /// Ethernet and wireless devices have no `IFLA_INFO_KIND`, they're
+    /// recognized in sysfs like by [`LinkKind::from_sysfs`].
+    #[must_use]
+    pub fn from_link(
+        name: &[u8],
+        info_kind: Option<&str>,
+        link_type: u16,
+    ) -> Option<Self> {
+        Self::from_link_at(Path::new(SYS_CLASS_NET), name, info_kind, link_type)
+    }
+
+    fn from_link_at(
+        root: &Path,
+        name: &[u8],
+        info_kind: Option<&str>,
+        link_type: u16,
+    ) -> Option<Self> {
+        match info_kind {
+            // Every other virtual link (e.g. `dummy` or `macvlan`) has a kind
+            Some(info_kind) => Self::from_info_kind(info_kind),
+            None => Self::from_device(&interface_path(root, name), link_type),
+        }
+    }
+
+    /// Kind of the ethernet or wireless device at `path` in sysfs, only
+    /// links backed by a device (a `device` link) are either
+    fn from_device(path: &Path, link_type: u16) -> Option<Self> {
+        if link_type != libc::ARPHRD_ETHER || !path.join("device").exists() {
+            return None;
+        }
+
+        match devtype(path).as_deref() {
+            Some("wlan") => Some(Self::Wlan),
+            _ => Some(Self::Ethernet),
+        }
+    }
+
+    /// Checks if links of the kind are virtual, only ethernet and wireless
+    /// devices are backed by hardware
+    #[must_use]
+    pub const fn is_virtual(self) -> bool {
+        !matches!(self, Self::Ethernet | Self::Wlan)
+    }
+
+    /// Kind of the interface `name` from `/sys/class/net`
+    ///
+    /// Without `IFLA_INFO_KIND` an ethernet device is only recognized by its
+    /// `device` link and veth pairs aren't recognized at all.
+    #[must_use]
+    pub fn from_sysfs(name: &[u8]) -> Option<Self> {
+        Self::from_sysfs_at(Path::new(SYS_CLASS_NET), name)
+    }
+
+    fn from_sysfs_at(root: &Path, name: &[u8]) -> Option<Self> {
+        let path = interface_path(root, name);
+
+        if let Some(kind) =
+            devtype(&path).as_deref().and_then(Self::from_devtype)
+        {
+            return Some(kind);
+        }
+        if path.join("tun_flags").exists() {
+            return Some(Self::Tun);
+        }
+
+        let link_type = fs::read_to_string(path.join("type"))
+            .ok()?
+            .trim()
+            .parse()
+            .ok()?;
+        Self::from_device(&path, link_type)
+    }
+}
+
+impl fmt::Display for LinkKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Ethernet => "ethernet",
+            Self::Wlan => "wlan",
+            Self::Bridge => "bridge",
+            Self::Bond => "bond",
+            Self::Vlan => "vlan",
+            Self::Wireguard => "wireguard",
+            Self::Tun => "tun",
+            Self::Veth => "veth",
+        })
+    }
+}
+
+/// Checks if the interface `name` is backed by a device, i.e. has a `device`
+/// link in `/sys/class/net`
+#[must_use]
+pub fn has_device(name: &[u8]) -> bool {
+    has_device_at(Path::new(SYS_CLASS_NET), name)
+}
+
+fn has_device_at(root: &Path, name: &[u8]) -> bool {
+    interface_path(root, name).join("device").exists()
+}
+
+fn interface_path(root: &Path, name: &[u8]) -> PathBuf {
+    root.join(OsStr::from_bytes(name))
+}
+
+/// `DEVTYPE` of the device at `path`, from its `uevent` file
+fn devtype(path: &Path) -> Option<String> {
+    fs::read_to_string(path.join("uevent"))
+        .ok()?
+        .lines()
+        .find_map(|line| line.strip_prefix("DEVTYPE="))
+        .map(Into::into)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::{env, process};
+
+    #[test]
+    fn info_kind() {
+        assert_eq!(LinkKind::from_info_kind("veth"), Some(LinkKind::Veth));
+        assert_eq!(LinkKind::from_info_kind("dummy"), None);
+
+        assert_eq!(
+            LinkKind::from_link(b"br0", Some("bridge"), libc::ARPHRD_ETHER),
+            Some(LinkKind::Bridge)
+        );
+        assert_eq!(
+            LinkKind::from_link(b"ifb0", Some("ifb"), libc::ARPHRD_ETHER),
+            None
+        );
+        assert_eq!(
+            LinkKind::from_link(b"wg0", Some("wireguard"), libc::ARPHRD_NONE),
+            Some(LinkKind::Wireguard)
+        );
+        // Not backed by a device
+        assert_eq!(
+            LinkKind::from_link(
+                b"wait-online-missing",
+                None,
+                libc::ARPHRD_ETHER
+            ),
+            None
+        );
+        assert_eq!(
+            LinkKind::from_link(b"lo", None, libc::ARPHRD_LOOPBACK),
+            None
+        );
+
+        assert!(!LinkKind::Wlan.is_virtual());
+        assert!(LinkKind::Bridge.is_virtual());
+    }
+
+    #[test]
+    fn sysfs() {
+        let root = env::temp_dir()
+            .join(format!("wait-online-sys-class-net-{}", process::id()));
+        let device = |name: &str, link_type: u16, files: &[(&str, &str)]| {
+            let path = root.join(name);
+            fs::create_dir_all(&path).unwrap();
+            fs::write(path.join("type"), format!("{link_type}\n")).unwrap();
+            for (file, content) in files {
+                fs::write(path.join(file), content).unwrap();
+            }
+        };
+
+        device("eth0", libc::ARPHRD_ETHER, &[("device", "")]);
+        device(
+            "wlan0",
+            libc::ARPHRD_ETHER,
+            &[
+                ("device", ""),
+                ("uevent", "DEVTYPE=wlan\nINTERFACE=wlan0\n"),
+            ],
+        );
+        device(
+            "br0",
+            libc::ARPHRD_ETHER,
+            &[("uevent", "DEVTYPE=bridge\nINTERFACE=br0\n")],
+        );
+        device("tun0", libc::ARPHRD_NONE, &[("tun_flags", "0x1001\n")]);
+        device("veth0", libc::ARPHRD_ETHER, &[]);
+        device("lo", libc::ARPHRD_LOOPBACK, &[]);
+
+        let kind = |name: &str| LinkKind::from_sysfs_at(&root, name.as_bytes());
+        assert_eq!(kind("eth0"), Some(LinkKind::Ethernet));
+        assert_eq!(kind("wlan0"), Some(LinkKind::Wlan));
+        assert_eq!(kind("br0"), Some(LinkKind::Bridge));
+        assert_eq!(kind("tun0"), Some(LinkKind::Tun));
+        assert_eq!(kind("veth0"), None);
+        assert_eq!(kind("lo"), None);
+        assert_eq!(kind("missing"), None);
+
+        // Both backends agree on links without `IFLA_INFO_KIND`
+        for name in ["eth0", "wlan0", "veth0", "lo", "missing"] {
+            let link_type = fs::read_to_string(root.join(name).join("type"))
+                .map_or(libc::ARPHRD_ETHER, |link_type| {
+                    link_type.trim().parse().unwrap()
+                });
+            assert_eq!(
+                LinkKind::from_link_at(&root, name.as_bytes(), None, link_type),
+                kind(name),
+                "{name}"
+            );
+        }
+
+        assert!(has_device_at(&root, b"eth0"));
+        assert!(!has_device_at(&root, b"veth0"));
+        assert!(!has_device_at(&root, b"missing"));
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
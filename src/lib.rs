@@ -1,15 +1,24 @@
 #[cfg(not(target_os = "linux"))]
 compile_error!("only linux is supported");
 
-use std::{collections::HashMap, ffi};
+use std::{collections::HashMap, fmt, io, net::IpAddr, path::Path};
 
-use arguments::Args;
+use arguments::{
+    AddressIn, Aggregation, Args, FamilyScope, InterfaceArgument,
+    InterfaceGroup, MinOnline, RouteFamily,
+};
+use expression::{Expression, HostProperty, InterfaceFacts};
 use ifaddrs::{
-    check_require_or_ignore, is_interface_up, InterfaceFlags,
+    check_require_or_ignore, is_interface_up, InterfaceAddress, InterfaceFlags,
     InterfacesActionArgument, InterfacesRequireOrIgnoreArgument,
+    RequiredInterfaces,
 };
+use kind::LinkKind;
+use netlink::{Neighbors, Routes};
+use operstate::{LinkState, OperState, OperStateRange};
+use pattern::InterfacePattern;
 use sockaddr::{
-    get_addres_family, AddressFamily, InterfacesFamilyTypeArgument,
+    AddressFamily, AddressScope, Cidr, DadState, InterfacesFamilyTypeArgument,
 };
 
 // Re-exported external crates
@@ -18,24 +27,157 @@ pub use nix::libc;
 mod errno;
 
 pub mod arguments;
+pub mod dns;
+pub mod expression;
 pub mod ifaddrs;
+pub mod kind;
+pub mod neighbor;
+pub mod netlink;
 pub mod operstate;
+pub mod pattern;
+pub mod probe;
 pub mod sockaddr;
+pub mod udev;
+pub mod wait;
 
-#[derive(Debug, Clone, Copy, Default)]
+#[derive(Debug, Clone, Copy)]
 struct InterfacesArgument<'a> {
     require_or_ignore: Option<InterfacesRequireOrIgnoreArgument<'a>>,
     family_type: Option<InterfacesFamilyTypeArgument>,
+    /// Address families the interfaces need together, with
+    /// `--family-scope host` instead of `family_type`
+    host_family_type: Option<InterfacesFamilyTypeArgument>,
+    operational_state: Option<OperStateRange>,
+    /// Scope of the address families given per `--interface`
+    address_scope: AddressScope,
+    /// Interfaces only have to exist, see [`Args::presence_only`]
+    presence_only: bool,
+}
+
+impl Default for InterfacesArgument<'_> {
+    fn default() -> Self {
+        Self {
+            require_or_ignore: None,
+            family_type: None,
+            host_family_type: None,
+            operational_state: None,
+            address_scope: InterfacesFamilyTypeArgument::DEFAULT_SCOPE,
+            presence_only: false,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy, Default)]
 pub struct NetworkArgument<'a> {
     interfaces_argument: Option<InterfacesArgument<'a>>,
     exact: bool,
-    any: bool,
+    aggregation: Aggregation,
+    /// Checked instead of the interfaces selected by `interfaces_argument`
+    groups: &'a [InterfaceGroup],
+    /// Checked instead of the interfaces selected by `interfaces_argument`,
+    /// the ignored interfaces are left out
+    require: Option<&'a Expression>,
+}
+
+impl<'a> NetworkArgument<'a> {
+    /// The first interface of a `--group` matching `ifaddr`'s interface
+    fn group_member<'i, T>(&self, ifaddr: T) -> Option<&'a InterfaceArgument>
+    where
+        T: InterfaceAddress<'i>,
+    {
+        self.groups
+            .iter()
+            .flat_map(|group| &group.interfaces)
+            .find(|interface| interface.pattern.matches_interface(ifaddr))
+    }
+}
+
+/// Route requirements, checked next to [`NetworkArgument`] with
+/// [`routes_online`] and [`gateways_resolved`]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RouteArgument<'a> {
+    default_route: Option<RouteFamily>,
+    route_to: &'a [Cidr],
+    gateway_neighbor: Option<RouteFamily>,
+}
+
+impl RouteArgument<'_> {
+    /// Checks if there are no route requirements, the routes don't have to be
+    /// dumped then
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.default_route.is_none()
+            && self.route_to.is_empty()
+            && self.gateway_neighbor.is_none()
+    }
+
+    /// Checks if the neighbors have to be dumped for [`gateways_resolved`]
+    #[must_use]
+    pub const fn needs_neighbors(&self) -> bool {
+        self.gateway_neighbor.is_some()
+    }
+}
+
+/// Address requirements, checked next to [`NetworkArgument`] with
+/// [`missing_addresses`]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AddressArgument<'a> {
+    addresses: &'a [IpAddr],
+    addresses_in: &'a [AddressIn],
+}
+
+impl AddressArgument<'_> {
+    /// Checks if there are no address requirements
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.addresses.is_empty() && self.addresses_in.is_empty()
+    }
+}
+
+/// An `--address` or `--address-in` requirement, see [`missing_addresses`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RequiredAddress<'a> {
+    Address(IpAddr),
+    In(&'a AddressIn),
+}
+
+impl fmt::Display for RequiredAddress<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Address(address) => write!(f, "address {address}"),
+            Self::In(address_in) => write!(f, "an address in {address_in}"),
+        }
+    }
+}
+
+/// State of an interface collected from all of its entries
+#[derive(Debug, Clone, Copy, Default)]
+struct InterfaceState {
+    has_ipv4: bool,
+    has_ipv6: bool,
+    /// At least one entry with a requested address family is up
+    up: bool,
+    /// At least one entry with a requested address family is down
+    down: bool,
+    link: LinkState,
+    /// Only looked up when an interface kind is required
+    kind: Option<LinkKind>,
+    /// Range the interface has to be in, see
+    /// [`InterfacesArgument::operational_state`]
+    operational_state: Option<OperStateRange>,
+    /// Address families the interface needs, see
+    /// [`InterfacesArgument::family_type`]
+    family_type: Option<InterfacesFamilyTypeArgument>,
+    /// An entry that is up has an IPv4 address in
+    /// [`InterfacesArgument::address_scope`], for
+    /// [`InterfacesArgument::host_family_type`] and `--require`
+    up_ipv4: bool,
+    /// Same as `up_ipv4` for IPv6
+    up_ipv6: bool,
 }
 
-type InterfaceMap<'a> = HashMap<&'a [u8], Option<(bool, bool)>>;
+/// Interfaces taken into account
+type InterfaceMap<'a> = HashMap<&'a [u8], InterfaceState>;
 
 struct InterfacesChecker;
 
@@ -58,42 +200,426 @@ impl InterfacesChecker {
     {
         results.next() == Some(true) && results.all(|x| x)
     }
+
+    /// Checks if `results` has _at least 1_ element and if at least
+    /// `min_online` elements are true
+    fn at_least<I>(results: I, min_online: MinOnline) -> bool
+    where
+        I: Iterator<Item = bool>,
+    {
+        let (total, online) = results.fold((0, 0), |(total, online), x| {
+            (total + 1, online + usize::from(x))
+        });
+        total != 0 && online >= min_online.required(total)
+    }
+
+    fn check<I>(results: I, aggregation: Aggregation) -> bool
+    where
+        I: Iterator<Item = bool>,
+    {
+        match aggregation {
+            Aggregation::All => Self::all(results),
+            Aggregation::Any => Self::any(results),
+            Aggregation::AtLeast(min_online) => {
+                Self::at_least(results, min_online)
+            }
+        }
+    }
 }
 
 /// Checks if network if online given the requirements provided by
 /// `network_online_arguments`
-pub fn network_online<I>(ifaddrs: I, network_argument: NetworkArgument) -> bool
+///
+/// A `--require` expression replaces the interface requirements, it's
+/// checked with [`requirement_met`] instead.
+pub fn network_online<'a, I, T>(
+    ifaddrs: I,
+    network_argument: NetworkArgument,
+) -> bool
 where
-    I: Iterator<Item = ifaddrs::ifaddrs>,
+    I: Iterator<Item = T>,
+    T: InterfaceAddress<'a>,
 {
-    match (
-        network_argument.exact,
-        network_argument.any,
-        network_argument.interfaces_argument,
-    ) {
-        (_, false, None) => {
-            InterfacesChecker::all(ifaddrs.filter_map(is_interface_up))
-        }
-        (_, true, None) => {
-            InterfacesChecker::any(ifaddrs.filter_map(is_interface_up))
+    if !network_argument.groups.is_empty() {
+        return offline_groups(ifaddrs, network_argument).is_empty();
+    }
+    if network_argument.require.is_some() {
+        return true;
+    }
+
+    let aggregation = network_argument.aggregation;
+    match (network_argument.exact, network_argument.interfaces_argument) {
+        (_, None) => {
+            // Every entry is an interface of its own, only `--min-online`
+            // needs them combined and it's _always_ exact
+            debug_assert!(!matches!(aggregation, Aggregation::AtLeast(_)));
+            InterfacesChecker::check(
+                ifaddrs.filter_map(is_interface_up),
+                aggregation,
+            )
         }
-        (true, any, Some(interface_argument)) => {
-            network_online_exact(ifaddrs, any, interface_argument)
+        (true, Some(interface_argument)) => {
+            network_online_exact(ifaddrs, aggregation, interface_argument)
         }
-        (false, any, Some(interface_argument)) => {
-            // If `exact` is false `any` is also _always_ false
-            debug_assert!(!any);
+        (false, Some(interface_argument)) => {
+            // If `exact` is false the aggregation is _always_ all
+            debug_assert_eq!(aggregation, Aggregation::All);
             network_online_lazy(ifaddrs, interface_argument)
         }
     }
 }
 
-fn network_online_lazy<I>(
+/// Names of the `--group`s of `network_argument` that aren't online, in the
+/// order they were given
+#[must_use]
+pub fn offline_groups<'a, 'p, I, T>(
+    ifaddrs: I,
+    network_argument: NetworkArgument<'p>,
+) -> Vec<&'p str>
+where
+    I: Iterator<Item = T>,
+    T: InterfaceAddress<'a>,
+{
+    if network_argument.groups.is_empty() {
+        return Vec::new();
+    }
+
+    // Every group needs its own pass over the entries
+    let entries: Vec<T> = ifaddrs.collect();
+    let interface_argument =
+        network_argument.interfaces_argument.unwrap_or_default();
+
+    network_argument
+        .groups
+        .iter()
+        .filter(|group| {
+            !network_online_exact(
+                entries.iter().copied(),
+                group.aggregation,
+                interface_argument.group(group),
+            )
+        })
+        .map(|group| &*group.name)
+        .collect()
+}
+
+/// Addresses of `address_argument` that aren't assigned to an interface of
+/// `ifaddrs`, in the order they were given
+///
+/// Addresses that are still being (or failed to be) checked for duplicates
+/// don't count, loopback interfaces do.
+#[must_use]
+pub fn missing_addresses<'a, 'p, I, T>(
+    ifaddrs: I,
+    address_argument: AddressArgument<'p>,
+) -> Vec<RequiredAddress<'p>>
+where
+    I: Iterator<Item = T>,
+    T: InterfaceAddress<'a>,
+{
+    let mut found = vec![false; address_argument.addresses.len()];
+    let mut found_in = vec![false; address_argument.addresses_in.len()];
+
+    for ifaddr in ifaddrs {
+        let Some(address) = ifaddr.address().filter(|_| {
+            ifaddr.dad_state().map_or(true, |dad| dad == DadState::Done)
+        }) else {
+            continue;
+        };
+
+        for (found, required) in
+            found.iter_mut().zip(address_argument.addresses)
+        {
+            *found |= address == *required;
+        }
+        for (found, required) in
+            found_in.iter_mut().zip(address_argument.addresses_in)
+        {
+            *found |= !*found
+                && required.cidr.contains_address(address)
+                && required.interface.as_ref().map_or(true, |interface| {
+                    interface.matches_interface(ifaddr)
+                });
+        }
+    }
+
+    let missing = address_argument
+        .addresses
+        .iter()
+        .zip(found)
+        .filter(|(_, found)| !found)
+        .map(|(&address, _)| RequiredAddress::Address(address));
+    let missing_in = address_argument
+        .addresses_in
+        .iter()
+        .zip(found_in)
+        .filter(|(_, found)| !found)
+        .map(|(address_in, _)| RequiredAddress::In(address_in));
+
+    missing.chain(missing_in).collect()
+}
+
+/// Interfaces matching a required `--interface` pattern, see
+/// [`interface_matches`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PatternMatches<'a> {
+    pub pattern: &'a InterfacePattern,
+    /// Names of the matching interfaces, sorted
+    pub interfaces: Vec<Box<str>>,
+}
+
+impl fmt::Display for PatternMatches<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.interfaces.is_empty() {
+            write!(f, "`{}` matches no interface", self.pattern)
+        } else {
+            write!(
+                f,
+                "`{}` matches {}",
+                self.pattern,
+                self.interfaces.join(", ")
+            )
+        }
+    }
+}
+
+/// Matches every required interface pattern of `network_argument` (including
+/// the ones of its groups) against the interfaces of `ifaddrs`, loopback
+/// interfaces are never matched
+#[must_use]
+pub fn interface_matches<'a, 'p, I, T>(
+    ifaddrs: I,
+    network_argument: NetworkArgument<'p>,
+) -> Vec<PatternMatches<'p>>
+where
+    I: Iterator<Item = T>,
+    T: InterfaceAddress<'a>,
+{
+    let required = network_argument
+        .interfaces_argument
+        .and_then(|interface_argument| interface_argument.require_or_ignore)
+        .and_then(InterfacesRequireOrIgnoreArgument::required)
+        .map_or(&[][..], |required| required.interfaces);
+    let interfaces: Vec<_> = required
+        .iter()
+        .chain(
+            network_argument
+                .groups
+                .iter()
+                .flat_map(|group| &group.interfaces),
+        )
+        .collect();
+    if interfaces.is_empty() {
+        return Vec::new();
+    }
+
+    // One entry per interface
+    let mut entries: Vec<T> = ifaddrs
+        .filter(|ifaddr| !ifaddr.flags().contains(InterfaceFlags::IFF_LOOPBACK))
+        .collect();
+    entries.sort_by_key(|ifaddr| ifaddr.name());
+    entries.dedup_by_key(|ifaddr| ifaddr.name());
+
+    interfaces
+        .iter()
+        .map(|interface| PatternMatches {
+            pattern: &interface.pattern,
+            interfaces: entries
+                .iter()
+                .filter(|ifaddr| interface.pattern.matches_interface(**ifaddr))
+                .map(|ifaddr| String::from_utf8_lossy(ifaddr.name()).into())
+                .collect(),
+        })
+        .collect()
+}
+
+/// Names of the virtual interfaces of `ifaddrs` that are skipped because of
+/// `--ignore-virtual`, sorted
+///
+/// Loopback interfaces are never taken into account, so aren't included.
+#[must_use]
+pub fn skipped_virtual_interfaces<'a, I, T>(
+    ifaddrs: I,
+    network_argument: NetworkArgument,
+) -> Vec<Box<str>>
+where
+    I: Iterator<Item = T>,
+    T: InterfaceAddress<'a>,
+{
+    let ignores_virtual = network_argument
+        .interfaces_argument
+        .and_then(|interface_argument| interface_argument.require_or_ignore)
+        .and_then(InterfacesRequireOrIgnoreArgument::ignored)
+        .is_some_and(|ignored| ignored.virtual_interfaces);
+    if !ignores_virtual {
+        return Vec::new();
+    }
+
+    // One entry per interface
+    let mut entries: Vec<T> = ifaddrs
+        .filter(|ifaddr| !ifaddr.flags().contains(InterfaceFlags::IFF_LOOPBACK))
+        .collect();
+    entries.sort_by_key(|ifaddr| ifaddr.name());
+    entries.dedup_by_key(|ifaddr| ifaddr.name());
+
+    entries
+        .iter()
+        .filter(|ifaddr| ifaddr.is_virtual())
+        .map(|ifaddr| String::from_utf8_lossy(ifaddr.name()).into())
+        .collect()
+}
+
+/// Duplicate address detection failed for an address, waiting won't help
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DadFailedError {
+    pub interface: Box<str>,
+    pub address: IpAddr,
+}
+
+impl fmt::Display for DadFailedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "duplicate address detection failed for {} on {}",
+            self.address, self.interface
+        )
+    }
+}
+
+impl std::error::Error for DadFailedError {}
+
+/// Checks if duplicate address detection failed for an address that is taken
+/// into account given the requirements provided by `network_argument`
+///
+/// Only addresses of a requested family in `--address-scope` count, without
+/// `--ipv4`/`--ipv6` addresses of any family in the default scope. Duplicate
+/// address detection isn't checked for the others.
+///
+/// Addresses are only marked by the netlink backend, see
+/// [`InterfaceAddress::dad_state`].
+///
+/// # Errors
+///
+/// Will return `Err` for the first address for which duplicate address
+/// detection failed.
+pub fn check_dad<'a, I, T>(
+    ifaddrs: I,
+    network_argument: NetworkArgument,
+) -> Result<(), DadFailedError>
+where
+    I: Iterator<Item = T>,
+    T: InterfaceAddress<'a>,
+{
+    let interface_argument =
+        network_argument.interfaces_argument.unwrap_or_default();
+    // Addresses don't matter if the interfaces only have to exist
+    if interface_argument.presence_only {
+        return Ok(());
+    }
+
+    let failed = ifaddrs
+        .filter(|ifaddr| {
+            !ifaddr.flags().contains(InterfaceFlags::IFF_LOOPBACK)
+                && ifaddr.dad_state() == Some(DadState::Failed)
+        })
+        .filter(|ifaddr| {
+            interface_argument.require_or_ignore.map_or(
+                true,
+                |require_or_ignore_arg| {
+                    check_require_or_ignore(*ifaddr, require_or_ignore_arg)
+                },
+            ) && (network_argument.groups.is_empty()
+                || network_argument.group_member(*ifaddr).is_some())
+        })
+        .find(|ifaddr| {
+            let interface = interface_argument
+                .first_match(*ifaddr, &mut [])
+                .or_else(|| network_argument.group_member(*ifaddr));
+            match interface_argument
+                .family_type(interface)
+                .or(interface_argument.host_family_type)
+            {
+                Some(family_arg) => {
+                    family_arg.in_scope(ifaddr.scope())
+                        && match ifaddr.family() {
+                            Some(AddressFamily::Inet) => family_arg.ipv4,
+                            Some(AddressFamily::Inet6) => family_arg.ipv6,
+                            _ => false,
+                        }
+                }
+                None => ifaddr.scope().map_or(true, |scope| {
+                    scope >= InterfacesFamilyTypeArgument::DEFAULT_SCOPE
+                }),
+            }
+        });
+
+    match failed.and_then(|ifaddr| Some((ifaddr.name(), ifaddr.address()?))) {
+        Some((name, address)) => Err(DadFailedError {
+            interface: String::from_utf8_lossy(name).into(),
+            address,
+        }),
+        None => Ok(()),
+    }
+}
+
+/// Checks if `routes` meet the requirements provided by `route_argument`
+#[must_use]
+pub fn routes_online(routes: &Routes, route_argument: RouteArgument) -> bool {
+    route_argument.default_route.map_or(true, |family| {
+        routes.default_route(family.address_family()).is_some()
+    }) && route_argument
+        .route_to
+        .iter()
+        .all(|destination| routes.route_to(destination).is_some())
+}
+
+/// Checks if the gateway of a default route is resolved in `neighbors`, as
+/// required by `route_argument`
+///
+/// A default route without a gateway (e.g. over a point-to-point link or a
+/// multipath route) doesn't need a neighbor entry.
+#[must_use]
+pub fn gateways_resolved(
+    routes: &Routes,
+    neighbors: &Neighbors,
+    route_argument: RouteArgument,
+) -> bool {
+    route_argument.gateway_neighbor.map_or(true, |family| {
+        routes.default_routes(family.address_family()).any(|route| {
+            match (route.gateway, route.oif) {
+                (Some(gateway), Some(oif)) => {
+                    neighbors.is_resolved(gateway, oif)
+                }
+                _ => true,
+            }
+        })
+    })
+}
+
+/// Gateways of the default routes required by `route_argument` that aren't
+/// resolved in `neighbors`, with the index of their interface
+///
+/// See [`neighbor::solicit`] to resolve them.
+pub fn unresolved_gateways<'a>(
+    routes: &'a Routes,
+    neighbors: &'a Neighbors,
+    route_argument: RouteArgument,
+) -> impl Iterator<Item = (IpAddr, u32)> + 'a {
+    route_argument
+        .gateway_neighbor
+        .into_iter()
+        .flat_map(|family| routes.default_routes(family.address_family()))
+        .filter_map(|route| Some((route.gateway?, route.oif?)))
+        .filter(|(gateway, oif)| !neighbors.is_resolved(*gateway, *oif))
+}
+
+fn network_online_lazy<'a, I, T>(
     ifaddrs: I,
     interface_argument: InterfacesArgument<'_>,
 ) -> bool
 where
-    I: Iterator<Item = ifaddrs::ifaddrs>,
+    I: Iterator<Item = T>,
+    T: InterfaceAddress<'a>,
 {
     let online_iter = ifaddrs.filter_map(|ifaddr| {
         is_interface_online_lazy(ifaddr, interface_argument)
@@ -102,76 +628,172 @@ where
     InterfacesChecker::all(online_iter)
 }
 
-fn network_online_exact<I>(
+fn network_online_exact<'a, I, T>(
     ifaddrs: I,
-    any: bool,
+    aggregation: Aggregation,
     interface_argument: InterfacesArgument<'_>,
 ) -> bool
 where
-    I: Iterator<Item = ifaddrs::ifaddrs>,
+    I: Iterator<Item = T>,
+    T: InterfaceAddress<'a>,
 {
-    if any {
-        return InterfacesChecker::any(ifaddrs.filter_map(|ifaddr| {
-            is_interface_online_exact(ifaddr, interface_argument, None)
+    let any = aggregation == Aggregation::Any;
+    let RequiredInterfaces {
+        interfaces: required,
+        kinds: required_kinds,
+    } = interface_argument
+        .require_or_ignore
+        .and_then(InterfacesRequireOrIgnoreArgument::required)
+        .unwrap_or_default();
+
+    let mut map = InterfaceMap::new();
+    // Required patterns that matched an interface
+    let mut matched = vec![false; required.len()];
+
+    for ifaddr in ifaddrs {
+        update_interface_map(
+            ifaddr,
+            interface_argument,
+            &mut map,
+            &mut matched,
+        );
+    }
+
+    // Every pattern has to match at least one interface
+    let unmatched = required
+        .iter()
+        .zip(&matched)
+        .filter(|(_, &matched)| !matched)
+        .map(|(interface, _)| interface);
+    // Like a pattern at least one interface has to be of a required kind
+    let unmatched_kinds = required_kinds
+        .iter()
+        .filter(|&&kind| !map.values().any(|state| state.kind == Some(kind)));
+
+    // Interfaces in the map exist, missing ones are never online
+    let online_iter = map
+        .values()
+        .map(|state| {
+            interface_argument.presence_only
+                || is_interface_online_exact(
+                    Some(*state),
+                    any,
+                    state.operational_state,
+                )
+        })
+        .chain(unmatched.map(|interface| {
+            is_interface_online_exact(
+                None,
+                any,
+                interface_argument.operational_state(Some(interface)),
+            )
+        }))
+        .chain(unmatched_kinds.map(|_| {
+            is_interface_online_exact(
+                None,
+                any,
+                interface_argument.operational_state,
+            )
         }));
+
+    // With `--family-scope host` the address families can be spread over the
+    // interfaces
+    let host_has_family =
+        interface_argument
+            .host_family_type
+            .map_or(true, |family_arg| {
+                family_arg.satisfied(
+                    map.values().any(|state| state.up_ipv4),
+                    map.values().any(|state| state.up_ipv6),
+                )
+            });
+
+    // Missing required interfaces count against `--min-online` as well
+    host_has_family && InterfacesChecker::check(online_iter, aggregation)
+}
+
+/// Evaluates the `--require` expression of `network_argument` against the
+/// interfaces that aren't ignored, always true without an expression
+///
+/// # Errors
+///
+/// Will return `Err` if a host property the expression uses can't be read,
+/// e.g. `/etc/resolv.conf` for `dns`.
+pub fn requirement_met<'a, I, T>(
+    ifaddrs: I,
+    network_argument: NetworkArgument,
+) -> Result<bool, io::Error>
+where
+    I: Iterator<Item = T>,
+    T: InterfaceAddress<'a>,
+{
+    let Some(require) = network_argument.require else {
+        return Ok(true);
+    };
+    let interface_argument =
+        network_argument.interfaces_argument.unwrap_or_default();
+    // Read before the interfaces are evaluated, the expression only gets the
+    // result
+    let dns = require.uses_host()
+        && dns::has_nameserver(Path::new(dns::RESOLV_CONF))?;
+
+    // The first entry of every interface is kept to match the patterns
+    // against
+    let entries: Vec<T> = ifaddrs.collect();
+    let mut map = InterfaceMap::new();
+    for &ifaddr in &entries {
+        update_interface_map(ifaddr, interface_argument, &mut map, &mut []);
     }
 
-    let mut map: InterfaceMap=
-        // Prepopulate Hashmap with required interfaces
-        if let InterfacesArgument {
-            require_or_ignore:
-                Some(InterfacesRequireOrIgnoreArgument {
-                    interfaces,
-                    action: InterfacesActionArgument::Require,
-                }),
-            ..
-        } = interface_argument
+    let mut interfaces: Vec<T> = Vec::with_capacity(map.len());
+    for ifaddr in entries {
+        if map.contains_key(ifaddr.name())
+            && !interfaces.iter().any(|other| other.name() == ifaddr.name())
         {
-            interfaces.iter().map(|name| (name.as_bytes(), None)).collect()
-        } else {
-            HashMap::default()
-        };
-
-    let all_up = InterfacesChecker::all(ifaddrs.filter_map(|ifaddr| {
-        is_interface_online_exact(ifaddr, interface_argument, Some(&mut map))
-    }));
-
-    let all_present = map.values().all(|value| {
-        if let Some((has_ipv4, has_ipv6)) = value {
-            if let InterfacesArgument {
-                family_type:
-                    Some(InterfacesFamilyTypeArgument {
-                        ipv4: require_ipv4,
-                        ipv6: require_ipv6,
-                    }),
-                ..
-            } = interface_argument
-            {
-                return *has_ipv4 && *has_ipv6
-                    || ((require_ipv4 ^ require_ipv6)
-                        && (require_ipv4 && *has_ipv4
-                            || require_ipv6 && *has_ipv6));
-            }
-            return *has_ipv4 || *has_ipv6;
+            interfaces.push(ifaddr);
         }
+    }
+    let facts: Vec<InterfaceFacts> = interfaces
+        .iter()
+        .map(|ifaddr| {
+            let state = map[ifaddr.name()];
+            InterfaceFacts {
+                online: is_interface_online_exact(
+                    Some(state),
+                    false,
+                    state.operational_state,
+                ),
+                ipv4: state.up_ipv4,
+                ipv6: state.up_ipv6,
+                operstate: state.link.operstate(),
+            }
+        })
+        .collect();
 
-        false
-    });
-    all_up && all_present
+    Ok(require.evaluate(
+        &facts,
+        &|pattern, i| pattern.matches_interface(interfaces[i]),
+        &|property| match property {
+            HostProperty::Dns => dns,
+        },
+    ))
 }
 
-fn is_interface_online_lazy(
-    ifaddr: ifaddrs::ifaddrs,
+fn is_interface_online_lazy<'a, T>(
+    ifaddr: T,
     interfaces_argument: InterfacesArgument,
-) -> Option<bool> {
+) -> Option<bool>
+where
+    T: InterfaceAddress<'a>,
+{
     const MASK: i32 = InterfaceFlags::IFF_LOWER_UP.bits();
 
-    #[allow(clippy::cast_possible_wrap)]
-    let ifa_flags = ifaddr.ifa_flags as i32;
+    let ifa_flags = ifaddr.flags().bits();
 
     debug_assert!(interfaces_argument.family_type.is_none());
 
-    (ifa_flags & InterfaceFlags::IFF_LOOPBACK.bits() == 0).then(|| ifa_flags & MASK != 0
+    (ifa_flags & InterfaceFlags::IFF_LOOPBACK.bits() == 0).then(|| {
+        ifa_flags & MASK != 0
         // `interfaces_argument.family_type` is _always_ None in `fn is_interface_online_lazy`
         // || interfaces_argument
         //     .family_type
@@ -183,135 +805,285 @@ fn is_interface_online_lazy(
         //     )
         || interfaces_argument
             .require_or_ignore
-            .map_or(false, |require_or_ignore_arg|
-                // SAFETY: We know `ifa_name` if a valid ptr from `ifaddr`
-                unsafe {
-                        !check_require_or_ignore(ifaddr.ifa_name, require_or_ignore_arg)
-                },
-            ))
+            .is_some_and(|require_or_ignore_arg|
+                !check_require_or_ignore(ifaddr, require_or_ignore_arg)
+            )
+    })
 }
 
-fn is_interface_online_exact(
-    ifaddr: ifaddrs::ifaddrs,
+/// `matched` is updated with the required patterns matching a new interface
+fn update_interface_map<'a: 'm, 'm, T>(
+    ifaddr: T,
     interface_argument: InterfacesArgument<'_>,
-    map: Option<&mut InterfaceMap>,
-) -> Option<bool> {
+    map: &mut InterfaceMap<'m>,
+    matched: &mut [bool],
+) where
+    T: InterfaceAddress<'a>,
+{
     const MASK: i32 = InterfaceFlags::IFF_LOWER_UP.bits();
 
-    #[allow(clippy::cast_possible_wrap)]
-    let ifa_flags = ifaddr.ifa_flags as i32;
+    let ifa_flags = ifaddr.flags().bits();
 
     if ifa_flags & InterfaceFlags::IFF_LOOPBACK.bits() != 0 {
-        return None;
+        return;
     }
 
-    // SAFETY: We know `ifa_name` if a valid ptr from `ifaddr`
-    let ifa_name = unsafe { ffi::CStr::from_ptr(ifaddr.ifa_name) };
-    // SAFETY: We know `ifa_addr` is a valid or null ptr from `ifaddr`
-    let ifa_addr_family = unsafe { get_addres_family(ifaddr.ifa_addr) };
+    let ifa_name = ifaddr.name();
+    let correct_name = interface_argument.require_or_ignore.map_or(
+        true,
+        |require_or_ignore_arg| {
+            check_require_or_ignore(ifaddr, require_or_ignore_arg)
+        },
+    );
+
+    if !correct_name {
+        return;
+    }
+
+    // Insert the interface into the hash map if needed, a required interface
+    // matches one of the patterns (loopback interfaces don't get here)
+    let state = map.entry(ifa_name).or_insert_with(|| {
+        let interface = interface_argument.first_match(ifaddr, matched);
+        InterfaceState {
+            operational_state: interface_argument.operational_state(interface),
+            family_type: interface_argument.family_type(interface),
+            ..InterfaceState::default()
+        }
+    });
+
+    // Addresses with a too narrow scope or that are still being (or failed
+    // to be) checked for duplicates don't count for `--ipv4`/`--ipv6`
+    let ifa_addr_family = ifaddr.family().filter(|_| {
+        state.family_type.map_or(true, |family_arg| {
+            family_arg.in_scope(ifaddr.scope())
+                && ifaddr.dad_state().map_or(true, |dad| dad == DadState::Done)
+        })
+    });
 
     let interface_up = ifa_flags & MASK != 0;
-    let correct_family: Option<AddressFamily> = interface_argument
-        .family_type
-        .map_or(ifa_addr_family, |family_arg| match ifa_addr_family {
-            Some(AddressFamily::Inet) if family_arg.ipv4 => {
-                Some(AddressFamily::Inet)
-            }
-            Some(AddressFamily::Inet6) if family_arg.ipv6 => {
-                Some(AddressFamily::Inet6)
+    let correct_family: Option<AddressFamily> =
+        state.family_type.map_or(ifa_addr_family, |family_arg| {
+            match ifa_addr_family {
+                Some(AddressFamily::Inet) if family_arg.ipv4 => {
+                    Some(AddressFamily::Inet)
+                }
+                Some(AddressFamily::Inet6) if family_arg.ipv6 => {
+                    Some(AddressFamily::Inet6)
+                }
+                _ => None,
             }
-            _ => None,
         });
-    let (correct_name, action_ignore): (bool, bool) = interface_argument
-        .require_or_ignore
-        .map_or((true, true), |require_or_ignore_arg| {
-            let in_interface = require_or_ignore_arg
-                .interfaces
-                .iter()
-                .any(|interface| interface.as_bytes() == ifa_name.to_bytes());
 
-            let a = require_or_ignore_arg.action
-                == InterfacesActionArgument::Ignore;
+    match ifa_addr_family {
+        Some(AddressFamily::Inet) => state.has_ipv4 = true,
+        Some(AddressFamily::Inet6) => state.has_ipv6 = true,
+        _ => {}
+    }
+    if correct_family.is_some() {
+        state.up |= interface_up;
+        state.down |= !interface_up;
+    }
+    let counts = interface_up
+        && ifaddr
+            .scope()
+            .map_or(true, |scope| scope >= interface_argument.address_scope)
+        && ifaddr.dad_state().map_or(true, |dad| dad == DadState::Done);
+    match ifaddr.family() {
+        Some(AddressFamily::Inet) if counts => state.up_ipv4 = true,
+        Some(AddressFamily::Inet6) if counts => state.up_ipv6 = true,
+        _ => {}
+    }
+    state.link.update(ifaddr);
+    if state.kind.is_none()
+        && interface_argument
+            .require_or_ignore
+            .and_then(InterfacesRequireOrIgnoreArgument::required)
+            .is_some_and(|required| !required.kinds.is_empty())
+    {
+        state.kind = ifaddr.kind();
+    }
+}
+
+/// `operational_state` is the range the interface has to be in, if any
+fn is_interface_online_exact(
+    state: Option<InterfaceState>,
+    any: bool,
+    operational_state: Option<OperStateRange>,
+) -> bool {
+    let Some(state) = state else {
+        return operational_state
+            .is_some_and(|range| range.contains(OperState::Missing));
+    };
 
-            (a ^ in_interface, a)
-        });
+    let up = match operational_state {
+        Some(range) => {
+            // At least one entry with a requested address family
+            (state.up || state.down) && range.contains(state.link.operstate())
+        }
+        None if any => state.up,
+        None => state.up && !state.down,
+    };
+    if any {
+        return up;
+    }
+
+    let has_family = if let Some(family_arg) = state.family_type {
+        family_arg.satisfied(state.has_ipv4, state.has_ipv6)
+    } else {
+        // The operational state decides if an address is needed
+        operational_state.is_some() || state.has_ipv4 || state.has_ipv6
+    };
 
-    // Insert interface into the hash map if needed
-    if correct_name {
-        if let Some(map) = map {
-            let current = match ifa_addr_family {
-                Some(AddressFamily::Inet) => (true, false),
-                Some(AddressFamily::Inet6) => (false, true),
-                _ => (false, false),
-            };
-
-            #[allow(clippy::cast_sign_loss, clippy::option_if_let_else)]
-            if let Some(value) = map.get_mut(ifa_name.to_bytes()) {
-                if let Some(prev) = value {
-                    prev.0 |= current.0;
-                    prev.1 |= current.1;
-                } else {
-                    *value = Some(current);
+    up && has_family
+}
+
+impl<'a> InterfacesArgument<'a> {
+    /// Takes only the interfaces of `group` into account, the other
+    /// requirements stay the same
+    fn group(self, group: &'a InterfaceGroup) -> Self {
+        Self {
+            require_or_ignore: Some(
+                InterfacesRequireOrIgnoreArgument::Require(
+                    RequiredInterfaces {
+                        interfaces: &group.interfaces,
+                        kinds: &[],
+                    },
+                ),
+            ),
+            ..self
+        }
+    }
+
+    /// The first `--interface` pattern matching `ifaddr`'s interface
+    ///
+    /// Every pattern matching the interface is marked in `matched`.
+    fn first_match<'i, T>(
+        self,
+        ifaddr: T,
+        matched: &mut [bool],
+    ) -> Option<&'a InterfaceArgument>
+    where
+        T: InterfaceAddress<'i>,
+    {
+        let interfaces = self
+            .require_or_ignore
+            .and_then(InterfacesRequireOrIgnoreArgument::required)?
+            .interfaces;
+
+        let mut first = None;
+        for (i, interface) in interfaces.iter().enumerate() {
+            if interface.pattern.matches_interface(ifaddr) {
+                if let Some(matched) = matched.get_mut(i) {
+                    *matched = true;
                 }
-            } else if action_ignore {
-                // Insert newly found interface into map
-                // except for when the required flag is used
-                // or it's a loopback interface (LOOPBACK shouldn't get here)
-                debug_assert!(
-                    ifa_flags & InterfaceFlags::IFF_LOOPBACK.bits() == 0
-                );
-                _ = map.insert(ifa_name.to_bytes(), Some(current));
+                first = first.or(Some(interface));
             }
         }
-    };
+        first
+    }
 
-    match (correct_family.is_some(), correct_name) {
-        (true, true) => Some(interface_up),
-        _ => None,
+    /// Operational state range for the interface matching `interface`, a
+    /// range given with the pattern takes precedence over
+    /// `--operational-state`
+    fn operational_state(
+        self,
+        interface: Option<&InterfaceArgument>,
+    ) -> Option<OperStateRange> {
+        interface
+            .and_then(|interface| interface.operational_state)
+            .or(self.operational_state)
+    }
+
+    /// Address families for the interface matching `interface`, families
+    /// given with the pattern take precedence over `--ipv4` and `--ipv6`
+    fn family_type(
+        self,
+        interface: Option<&InterfaceArgument>,
+    ) -> Option<InterfacesFamilyTypeArgument> {
+        match interface.and_then(|interface| interface.family) {
+            Some(family) => InterfacesFamilyTypeArgument::from_args(
+                family.ipv4(),
+                family.ipv6(),
+            )
+            .map(|family_type| family_type.scope(self.address_scope)),
+            None => self.family_type,
+        }
     }
-}
 
-impl<'a> InterfacesArgument<'a> {
     fn from_args(args: &'a Args) -> (bool, Option<Self>) {
-        let require_or_ignore = InterfacesRequireOrIgnoreArgument::parse_args(
-            args.interface.as_deref(),
-            args.ignore.as_deref(),
-        );
-        let family_type =
-            InterfacesFamilyTypeArgument::from_args(args.ipv4, args.ipv6);
-
-        match (require_or_ignore, family_type, args.any) {
-            (None, None, _) => (false, None),
-            (Some((interfaces, action)), None, false) => (
-                action == InterfacesActionArgument::Require,
-                Some(InterfacesArgument {
-                    require_or_ignore: Some(
-                        InterfacesRequireOrIgnoreArgument::new(
-                            interfaces, action,
-                        ),
-                    ),
-                    family_type,
-                }),
-            ),
-            (require_or_ignore, family_type, any) => {
-                // Some None true -> true || None.is_some() = true
-                // None Some true -> true || Some.is_some() = true
-                // None Some false -> false || Some.is_some = true
-                // _    _    _ -> true && true && true = true (see lines above)
-                // Just to be sure
-                debug_assert!(any || family_type.is_some());
+        let require_or_ignore =
+            InterfacesRequireOrIgnoreArgument::from_args(args);
+        let family_type = InterfacesFamilyTypeArgument::from_args(
+            args.ipv4 || args.ipv4_or_ipv6,
+            args.ipv6 || args.ipv4_or_ipv6,
+        )
+        .map(|family_type| {
+            family_type
+                .either(args.ipv4_or_ipv6)
+                .scope(args.address_scope)
+        });
+        let (family_type, host_family_type) = match args.family_scope {
+            FamilyScope::Interface => (family_type, None),
+            FamilyScope::Host => (None, family_type),
+        };
+
+        match (
+            require_or_ignore,
+            family_type.or(host_family_type),
+            args.operational_state,
+            args.any,
+        ) {
+            // `--require` needs `--address-scope` for its `ipv4`/`ipv6` terms
+            (None, None, None, _)
+                if args.min_online.is_none()
+                    && args.group.is_empty()
+                    && args.require.is_none() =>
+            {
+                (false, None)
+            }
+            // Ignored interfaces would count towards `--min-online` in lazy
+            // mode
+            (Some(require_or_ignore), None, None, false)
+                if args.min_online.is_none() =>
+            {
+                (
+                    require_or_ignore.action()
+                        == InterfacesActionArgument::Require,
+                    Some(InterfacesArgument {
+                        require_or_ignore: Some(require_or_ignore),
+                        family_type,
+                        host_family_type,
+                        operational_state: None,
+                        address_scope: args.address_scope,
+                        presence_only: args.presence_only,
+                    }),
+                )
+            }
+            (require_or_ignore, any_family_type, operational_state, any) => {
+                // Some None None true -> true || None.is_some() = true
+                // None Some None true -> true || Some.is_some() = true
+                // None Some None false -> false || Some.is_some = true
+                // None None Some _ -> Some.is_some = true
+                // _    _    _    _ -> true && true && true = true (see lines above)
+                // Or `--min-online`, `--group` or `--require` is given, just
+                // to be sure
+                debug_assert!(
+                    any || any_family_type.is_some()
+                        || operational_state.is_some()
+                        || args.min_online.is_some()
+                        || !args.group.is_empty()
+                        || args.require.is_some()
+                );
                 (
                     //any || family_type.is_some(),
                     true,
                     Some(InterfacesArgument {
-                        require_or_ignore: require_or_ignore.map(
-                            |(interfaces, action)| {
-                                InterfacesRequireOrIgnoreArgument::new(
-                                    interfaces, action,
-                                )
-                            },
-                        ),
+                        require_or_ignore,
                         family_type,
+                        host_family_type,
+                        operational_state,
+                        address_scope: args.address_scope,
+                        presence_only: args.presence_only,
                     }),
                 )
             }
@@ -325,7 +1097,32 @@ impl<'a> From<&'a Args> for NetworkArgument<'a> {
         Self {
             interfaces_argument,
             exact,
-            any: args.any,
+            aggregation: match (args.any, args.min_online) {
+                (_, Some(min_online)) => Aggregation::AtLeast(min_online),
+                (true, None) => Aggregation::Any,
+                (false, None) => Aggregation::All,
+            },
+            groups: &args.group,
+            require: args.require.as_ref(),
+        }
+    }
+}
+
+impl<'a> From<&'a Args> for AddressArgument<'a> {
+    fn from(args: &'a Args) -> Self {
+        Self {
+            addresses: &args.address,
+            addresses_in: &args.address_in,
+        }
+    }
+}
+
+impl<'a> From<&'a Args> for RouteArgument<'a> {
+    fn from(args: &'a Args) -> Self {
+        Self {
+            default_route: args.require_default_route,
+            route_to: &args.route_to,
+            gateway_neighbor: args.gateway_neighbor,
         }
     }
 }
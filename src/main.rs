@@ -1,45 +1,225 @@
 use std::{
-    cmp::min,
+    cell::{Cell, RefCell},
     io,
+    path::Path,
     process::ExitCode,
-    thread::sleep,
     time::{Duration, Instant},
 };
 
 use clap::Parser;
 
 use wait_online::{
-    arguments::Args, ifaddrs::getifaddrs, network_online, NetworkArgument,
+    arguments::{Args, Backend},
+    check_dad, dns, gateways_resolved,
+    ifaddrs::{getifaddrs, InterfaceAddress},
+    interface_matches, missing_addresses, neighbor, netlink, network_online,
+    offline_groups,
+    probe::{HttpExpectation, ProbeStatus},
+    requirement_met, routes_online, skipped_virtual_interfaces,
+    unresolved_gateways,
+    wait::Waiter,
+    AddressArgument, NetworkArgument, RouteArgument,
 };
 
-fn main() -> Result<ExitCode, io::Error> {
+fn main() -> ExitCode {
+    match run() {
+        Ok(exit_code) => exit_code,
+        Err(err) => {
+            eprintln!("wait-online: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run() -> Result<ExitCode, io::Error> {
     let start = Instant::now();
 
     let args = Args::parse();
-    let stop = start + Duration::from_secs(args.timeout);
+    args.validate()
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+    for probe in &args.probe {
+        probe.check().map_err(|err| {
+            io::Error::new(err.kind(), format!("{probe}: {err}"))
+        })?;
+    }
+    // A timeout of 0 disables the timeout
+    let stop =
+        (args.timeout != 0).then(|| start + Duration::from_secs(args.timeout));
 
+    let backend = args.selected_backend();
     let network_argument = NetworkArgument::from(&args);
+    let route_argument = RouteArgument::from(&args);
+    let address_argument = AddressArgument::from(&args);
+    let http_expectation = HttpExpectation::from(&args);
+    // Last probe that ran into a captive portal, reported on timeout
+    let captive_portal = Cell::new(None);
+    // Virtual interfaces skipped by `--ignore-virtual` that were reported
+    let reported_skipped = RefCell::new(Vec::new());
+
+    let online = || -> Result<bool, io::Error> {
+        let interfaces_online = match backend {
+            // One snapshot per check, so every requirement sees the same
+            // interfaces
+            Backend::Getifaddrs => {
+                let ifaddrs = getifaddrs()?;
+                if args.ignore_virtual {
+                    report_skipped(
+                        skipped_virtual_interfaces(
+                            ifaddrs.iter(),
+                            network_argument,
+                        ),
+                        &reported_skipped,
+                    );
+                }
+                network_online(ifaddrs.iter(), network_argument)
+                    && requirement_met(ifaddrs.iter(), network_argument)?
+                    && missing_addresses(ifaddrs.iter(), address_argument)
+                        .is_empty()
+            }
+            Backend::Netlink => {
+                let interfaces = netlink::dump()?;
+                if args.ignore_virtual {
+                    report_skipped(
+                        skipped_virtual_interfaces(
+                            interfaces.iter(),
+                            network_argument,
+                        ),
+                        &reported_skipped,
+                    );
+                }
+                check_dad(interfaces.iter(), network_argument).map_err(
+                    |err| io::Error::new(io::ErrorKind::AddrInUse, err),
+                )?;
+                network_online(interfaces.iter(), network_argument)
+                    && requirement_met(interfaces.iter(), network_argument)?
+                    && missing_addresses(interfaces.iter(), address_argument)
+                        .is_empty()
+            }
+        };
+
+        if !interfaces_online {
+            return Ok(false);
+        }
+
+        if !route_argument.is_empty() {
+            let routes = netlink::dump_routes()?;
+            if !routes_online(&routes, route_argument) {
+                return Ok(false);
+            }
+
+            if route_argument.needs_neighbors() {
+                let neighbors = netlink::dump_neighbors()?;
+                if !gateways_resolved(&routes, &neighbors, route_argument) {
+                    if args.solicit_gateway {
+                        for (gateway, index) in unresolved_gateways(
+                            &routes,
+                            &neighbors,
+                            route_argument,
+                        ) {
+                            neighbor::solicit(gateway, index);
+                        }
+                    }
+                    return Ok(false);
+                }
+            }
+        }
+
+        if (args.dns && !dns::has_nameserver(Path::new(dns::RESOLV_CONF))?)
+            || !args.resolve.iter().all(|name| dns::resolves(name, stop))
+        {
+            return Ok(false);
+        }
 
-    if args.interval == 0 {
-        while !network_online(getifaddrs()?, network_argument) {
-            sleep(Duration::from_millis(args.interval));
+        // Probes only run once everything else is online
+        for probe in &args.probe {
+            match probe.run(stop, http_expectation)? {
+                ProbeStatus::Online => {}
+                ProbeStatus::Offline => return Ok(false),
+                ProbeStatus::CaptivePortal => {
+                    captive_portal.set(Some(probe));
+                    return Ok(false);
+                }
+            }
         }
-    } else {
-        while !network_online(getifaddrs()?, network_argument) {
-            // Check if for timeout
-            // If not sleep for interval or untill timeout whichever is faster
-            let time_to_timeout = stop.checked_duration_since(Instant::now());
-            if let Some(time_to_timeout) = time_to_timeout {
-                sleep(min(
-                    Duration::from_millis(args.interval),
-                    time_to_timeout,
-                ));
-            } else {
-                // Timeout
-                return Ok(ExitCode::FAILURE);
+
+        Ok(true)
+    };
+
+    // Subscribe before the first check so no change can be missed
+    let waiter = Waiter::new(
+        args.poll || args.needs_polling(),
+        Duration::from_millis(args.interval),
+    );
+
+    while !online()? {
+        if !waiter.wait(stop)? {
+            // Timeout
+            match backend {
+                Backend::Getifaddrs => {
+                    let ifaddrs = getifaddrs()?;
+                    report_interfaces(
+                        || ifaddrs.iter(),
+                        network_argument,
+                        address_argument,
+                    );
+                }
+                Backend::Netlink => {
+                    let interfaces = netlink::dump()?;
+                    report_interfaces(
+                        || interfaces.iter(),
+                        network_argument,
+                        address_argument,
+                    );
+                }
+            }
+            if let Some(probe) = captive_portal.get() {
+                eprintln!("wait-online: captive portal detected by {probe}");
             }
+            return Ok(ExitCode::FAILURE);
         }
     }
 
     Ok(ExitCode::SUCCESS)
 }
+
+/// Reports which interfaces the `--interface` patterns match, which
+/// `--group`s are offline and which `--address` and `--address-in`
+/// requirements aren't met, all from the same snapshot `ifaddrs` iterates
+fn report_interfaces<'a, F, I, T>(
+    ifaddrs: F,
+    network_argument: NetworkArgument,
+    address_argument: AddressArgument,
+) where
+    F: Fn() -> I,
+    I: Iterator<Item = T>,
+    T: InterfaceAddress<'a>,
+{
+    for matches in interface_matches(ifaddrs(), network_argument) {
+        eprintln!("wait-online: interface pattern {matches}");
+    }
+    for group in offline_groups(ifaddrs(), network_argument) {
+        eprintln!("wait-online: group {group} is offline");
+    }
+    if !address_argument.is_empty() {
+        for address in missing_addresses(ifaddrs(), address_argument) {
+            eprintln!("wait-online: {address} is missing");
+        }
+    }
+}
+
+/// Reports the `skipped` virtual interfaces that haven't been `reported` yet
+fn report_skipped(skipped: Vec<Box<str>>, reported: &RefCell<Vec<Box<str>>>) {
+    let mut reported = reported.borrow_mut();
+    let new: Vec<_> = skipped
+        .into_iter()
+        .filter(|name| !reported.contains(name))
+        .collect();
+
+    if !new.is_empty() {
+        eprintln!(
+            "wait-online: ignoring virtual interfaces {}",
+            new.join(", ")
+        );
+        reported.extend(new);
+    }
+}
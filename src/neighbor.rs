@@ -0,0 +1,25 @@
+use std::net::{IpAddr, SocketAddr, SocketAddrV6, UdpSocket};
+
+/// Port the solicitation datagram is sent to (discard)
+const DISCARD_PORT: u16 = 9;
+
+/// Makes the kernel resolve `address` on the interface `index`, i.e. send an
+/// ARP request or an IPv6 neighbor solicitation
+///
+/// Sending raw ARP or NDP packets needs `CAP_NET_RAW`, instead an empty UDP
+/// datagram is sent to the discard port of `address`, which has the kernel
+/// resolve it first. This is best effort, errors are ignored.
+pub fn solicit(address: IpAddr, index: u32) {
+    let (local, remote): (SocketAddr, SocketAddr) = match address {
+        IpAddr::V4(_) => (([0; 4], 0).into(), (address, DISCARD_PORT).into()),
+        // The scope is needed for link-local gateways
+        IpAddr::V6(address) => (
+            ([0; 16], 0).into(),
+            SocketAddrV6::new(address, DISCARD_PORT, 0, index).into(),
+        ),
+    };
+
+    if let Ok(socket) = UdpSocket::bind(local) {
+        _ = socket.send_to(&[], remote);
+    }
+}
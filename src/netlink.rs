@@ -0,0 +1,599 @@
+use std::{
+    io,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+    os::fd::{AsFd, AsRawFd, OwnedFd},
+    time::Duration,
+};
+
+use nix::{
+    errno::Errno,
+    poll::{poll, PollFd, PollFlags, PollTimeout},
+    sys::socket::{
+        bind, recv, send, socket, AddressFamily, MsgFlags, NetlinkAddr,
+        SockFlag, SockProtocol, SockType,
+    },
+};
+
+use crate::libc;
+
+pub use interface::{Address, Interface, InterfaceEntry, Interfaces, Link};
+pub use neighbor::{Neighbor, Neighbors};
+pub use route::{Route, Routes};
+
+mod interface;
+mod neighbor;
+mod route;
+
+/// Multicast groups a [`Subscription`] listens on
+#[allow(clippy::cast_sign_loss)]
+const GROUPS: u32 = (libc::RTMGRP_LINK
+    | libc::RTMGRP_IPV4_IFADDR
+    | libc::RTMGRP_IPV6_IFADDR
+    | libc::RTMGRP_IPV4_ROUTE
+    | libc::RTMGRP_IPV6_ROUTE
+    | libc::RTMGRP_NEIGH) as u32;
+
+/// Initial size of the buffer used to receive netlink messages
+///
+/// Dump replies grow the buffer when a datagram doesn't fit.
+const BUFFER_SIZE: usize = 32768;
+
+/// Number of times a dump is requested before giving up when it keeps being
+/// interrupted by concurrent changes (`NLM_F_DUMP_INTR`)
+const DUMP_ATTEMPTS: u32 = 3;
+
+/// Size of `struct nlmsghdr`
+const NLMSG_HDRLEN: usize = 16;
+
+/// Size of `struct rtattr`
+const RTA_HDRLEN: usize = 4;
+
+/// Subscription to the kernel's link, address, route and neighbor change
+/// notifications
+///
+/// The content of the notifications is not used, they only signal that the
+/// interfaces have to be checked again.
+#[derive(Debug)]
+pub struct Subscription {
+    fd: OwnedFd,
+}
+
+impl Subscription {
+    /// Subscribe to link (`RTMGRP_LINK`), address (`RTMGRP_IPV4_IFADDR`,
+    /// `RTMGRP_IPV6_IFADDR`), route (`RTMGRP_IPV4_ROUTE`,
+    /// `RTMGRP_IPV6_ROUTE`) and neighbor (`RTMGRP_NEIGH`) changes.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the netlink socket can't be created or bound.
+    pub fn new() -> Result<Self, io::Error> {
+        let fd = open(SockFlag::SOCK_NONBLOCK, GROUPS)?;
+
+        Ok(Self { fd })
+    }
+
+    /// Blocks until the kernel reports a change or `timeout` elapses,
+    /// `None` waits forever.
+    ///
+    /// Returns `true` if a change was reported.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if polling or reading the socket fails.
+    pub fn wait(&self, timeout: Option<Duration>) -> Result<bool, io::Error> {
+        let timeout = timeout.map_or(PollTimeout::NONE, |timeout| {
+            PollTimeout::try_from(timeout).unwrap_or(PollTimeout::MAX)
+        });
+        let mut fds = [PollFd::new(self.fd.as_fd(), PollFlags::POLLIN)];
+
+        match poll(&mut fds, timeout) {
+            Ok(0) | Err(Errno::EINTR) => Ok(false),
+            Ok(_) => self.drain(),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Discards all pending notifications
+    ///
+    /// Returns `true` if at least one notification was pending.
+    fn drain(&self) -> Result<bool, io::Error> {
+        let mut buffer = vec![0u8; BUFFER_SIZE];
+        let mut changed = false;
+
+        loop {
+            match recv(self.fd.as_raw_fd(), &mut buffer, MsgFlags::MSG_DONTWAIT)
+            {
+                Ok(0) | Err(Errno::EAGAIN) => return Ok(changed),
+                // The kernel dropped notifications, something did change
+                Ok(_) | Err(Errno::ENOBUFS) => changed = true,
+                Err(Errno::EINTR) => {}
+                Err(err) => return Err(err.into()),
+            }
+        }
+    }
+}
+
+/// Get all interfaces and their addresses with a `RTM_GETLINK` and
+/// `RTM_GETADDR` dump.
+///
+/// # Errors
+///
+/// Will return `Err` if the netlink socket can't be created or if the kernel
+/// reports an error for one of the dumps.
+pub fn dump() -> Result<Interfaces, io::Error> {
+    let fd = open(SockFlag::empty(), 0)?;
+
+    let links = request_dump(&fd, libc::RTM_GETLINK, interface::IFINFOMSG_LEN)?;
+    let addresses =
+        request_dump(&fd, libc::RTM_GETADDR, interface::IFADDRMSG_LEN)?;
+
+    Ok(Interfaces::parse(&links, &addresses))
+}
+
+/// Get the routes of all routing tables with a `RTM_GETROUTE` dump.
+///
+/// # Errors
+///
+/// Will return `Err` if the netlink socket can't be created or if the kernel
+/// reports an error for the dump.
+pub fn dump_routes() -> Result<Routes, io::Error> {
+    let fd = open(SockFlag::empty(), 0)?;
+
+    let routes = request_dump(&fd, libc::RTM_GETROUTE, route::RTMSG_LEN)?;
+
+    Ok(Routes::parse(&routes))
+}
+
+/// Get the neighbor (ARP and NDP) tables of all interfaces with a
+/// `RTM_GETNEIGH` dump.
+///
+/// # Errors
+///
+/// Will return `Err` if the netlink socket can't be created or if the kernel
+/// reports an error for the dump.
+pub fn dump_neighbors() -> Result<Neighbors, io::Error> {
+    let fd = open(SockFlag::empty(), 0)?;
+
+    let neighbors = request_dump(&fd, libc::RTM_GETNEIGH, neighbor::NDMSG_LEN)?;
+
+    Ok(Neighbors::parse(&neighbors))
+}
+
+/// Opens a `NETLINK_ROUTE` socket bound to the multicast `groups`
+fn open(flags: SockFlag, groups: u32) -> Result<OwnedFd, io::Error> {
+    let fd = socket(
+        AddressFamily::Netlink,
+        SockType::Raw,
+        SockFlag::SOCK_CLOEXEC | flags,
+        SockProtocol::NetlinkRoute,
+    )?;
+    bind(fd.as_raw_fd(), &NetlinkAddr::new(0, groups))?;
+
+    Ok(fd)
+}
+
+/// Sends a dump request of `kind` and returns all messages of the reply.
+///
+/// `header_len` is the size of the family specific header following
+/// `struct nlmsghdr` (e.g. `struct ifinfomsg` for `RTM_GETLINK`), an all zero
+/// header requests every family.
+///
+/// The dump is requested again when the kernel reports that it was
+/// interrupted by a concurrent change, as the reply may be inconsistent.
+fn request_dump(
+    fd: &OwnedFd,
+    kind: u16,
+    header_len: usize,
+) -> Result<Vec<u8>, io::Error> {
+    let mut buffer = vec![0u8; BUFFER_SIZE];
+
+    for seq in 1..=DUMP_ATTEMPTS {
+        let request = dump_request(kind, seq, header_len);
+        _ = send(fd.as_raw_fd(), &request, MsgFlags::empty())?;
+
+        let mut reply = Reply::default();
+        while !reply.done {
+            let len = receive(fd, &mut buffer)?;
+            reply.collect(&buffer[..len], seq)?;
+        }
+
+        if !reply.interrupted {
+            return Ok(reply.messages);
+        }
+    }
+
+    Err(io::Error::new(
+        io::ErrorKind::Other,
+        "netlink dump kept being interrupted by concurrent changes",
+    ))
+}
+
+/// Receives the next datagram into `buffer`, growing it when the datagram
+/// doesn't fit so it is never truncated.
+fn receive(fd: &OwnedFd, buffer: &mut Vec<u8>) -> Result<usize, io::Error> {
+    loop {
+        // `MSG_TRUNC` makes the kernel return the real size of the datagram
+        let len = match recv(
+            fd.as_raw_fd(),
+            &mut [],
+            MsgFlags::MSG_PEEK | MsgFlags::MSG_TRUNC,
+        ) {
+            Ok(len) => len,
+            Err(Errno::EINTR) => continue,
+            Err(err) => return Err(err.into()),
+        };
+        if len > buffer.len() {
+            buffer.resize(len, 0);
+        }
+
+        match recv(fd.as_raw_fd(), buffer, MsgFlags::empty()) {
+            Ok(len) => return Ok(len),
+            Err(Errno::EINTR) => {}
+            Err(err) => return Err(err.into()),
+        }
+    }
+}
+
+/// The messages of a dump reply received so far
+#[derive(Debug, Default)]
+struct Reply {
+    /// The messages, excluding `NLMSG_DONE` and `NLMSG_ERROR`
+    messages: Vec<u8>,
+    /// `NLMSG_DONE` was received
+    done: bool,
+    /// A message had `NLM_F_DUMP_INTR` set
+    interrupted: bool,
+}
+
+impl Reply {
+    /// Adds the messages of a received datagram that belong to the dump
+    /// with sequence number `seq`.
+    ///
+    /// Returns `Err` if the kernel reports an error in a `NLMSG_ERROR` or
+    /// `NLMSG_DONE` message.
+    fn collect(&mut self, buffer: &[u8], seq: u32) -> Result<(), io::Error> {
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        const DUMP_INTR: u16 = libc::NLM_F_DUMP_INTR as u16;
+
+        for message in Messages::new(buffer) {
+            if message.seq != seq {
+                continue;
+            }
+
+            self.interrupted |= message.flags & DUMP_INTR != 0;
+
+            match i32::from(message.kind) {
+                kind @ (libc::NLMSG_DONE | libc::NLMSG_ERROR) => {
+                    let error = read_i32(message.payload, 0).unwrap_or(0);
+                    if error != 0 {
+                        return Err(io::Error::from_raw_os_error(-error));
+                    }
+                    if kind == libc::NLMSG_DONE {
+                        self.done = true;
+                        return Ok(());
+                    }
+                }
+                _ => {
+                    self.messages.extend_from_slice(message.bytes);
+                    self.messages.resize(align(self.messages.len()), 0);
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Builds a `NLM_F_DUMP` request of `kind` with an all zero family specific
+/// header of `header_len` bytes.
+fn dump_request(kind: u16, seq: u32, header_len: usize) -> Vec<u8> {
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    const FLAGS: u16 = (libc::NLM_F_REQUEST | libc::NLM_F_DUMP) as u16;
+
+    let len = NLMSG_HDRLEN + header_len;
+    let mut request = Vec::with_capacity(len);
+
+    #[allow(clippy::cast_possible_truncation)]
+    request.extend_from_slice(&(len as u32).to_ne_bytes());
+    request.extend_from_slice(&kind.to_ne_bytes());
+    request.extend_from_slice(&FLAGS.to_ne_bytes());
+    request.extend_from_slice(&seq.to_ne_bytes());
+    // Port id, 0 addresses the kernel
+    request.extend_from_slice(&0u32.to_ne_bytes());
+    request.resize(len, 0);
+
+    request
+}
+
+/// Rounds `len` up to the netlink alignment of 4 bytes
+const fn align(len: usize) -> usize {
+    (len + 3) & !3
+}
+
+/// A single netlink message
+#[derive(Debug, Clone, Copy)]
+struct Message<'a> {
+    /// `nlmsg_type`
+    kind: u16,
+    /// `nlmsg_flags`
+    flags: u16,
+    /// `nlmsg_seq`
+    seq: u32,
+    /// Data following `struct nlmsghdr`
+    payload: &'a [u8],
+    /// The complete message, including `struct nlmsghdr`
+    bytes: &'a [u8],
+}
+
+/// Iterator over the netlink messages in a buffer
+///
+/// Stops at the first truncated or malformed message.
+#[derive(Debug, Clone)]
+struct Messages<'a> {
+    buffer: &'a [u8],
+}
+
+impl<'a> Messages<'a> {
+    const fn new(buffer: &'a [u8]) -> Self {
+        Self { buffer }
+    }
+}
+
+impl<'a> Iterator for Messages<'a> {
+    type Item = Message<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let len = read_u32(self.buffer, 0)? as usize;
+        if len < NLMSG_HDRLEN || len > self.buffer.len() {
+            self.buffer = &[];
+            return None;
+        }
+
+        let message = Message {
+            kind: read_u16(self.buffer, 4)?,
+            flags: read_u16(self.buffer, 6)?,
+            seq: read_u32(self.buffer, 8)?,
+            payload: &self.buffer[NLMSG_HDRLEN..len],
+            bytes: &self.buffer[..len],
+        };
+        self.buffer = self.buffer.get(align(len)..).unwrap_or_default();
+
+        Some(message)
+    }
+}
+
+/// Iterator over the route attributes (`struct rtattr`) in a buffer
+///
+/// Yields the attribute type, without the `NLA_F_*` flags, and its data.
+/// Stops at the first truncated or malformed attribute.
+#[derive(Debug, Clone)]
+struct Attributes<'a> {
+    buffer: &'a [u8],
+}
+
+impl<'a> Attributes<'a> {
+    const fn new(buffer: &'a [u8]) -> Self {
+        Self { buffer }
+    }
+}
+
+impl<'a> Iterator for Attributes<'a> {
+    type Item = (u16, &'a [u8]);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        const TYPE_MASK: u16 = libc::NLA_TYPE_MASK as u16;
+
+        let len = usize::from(read_u16(self.buffer, 0)?);
+        if len < RTA_HDRLEN || len > self.buffer.len() {
+            self.buffer = &[];
+            return None;
+        }
+
+        let attribute = (
+            read_u16(self.buffer, 2)? & TYPE_MASK,
+            &self.buffer[RTA_HDRLEN..len],
+        );
+        self.buffer = self.buffer.get(align(len)..).unwrap_or_default();
+
+        Some(attribute)
+    }
+}
+
+fn read_u16(buffer: &[u8], offset: usize) -> Option<u16> {
+    buffer
+        .get(offset..offset + 2)
+        .and_then(|bytes| bytes.try_into().ok())
+        .map(u16::from_ne_bytes)
+}
+
+fn read_u32(buffer: &[u8], offset: usize) -> Option<u32> {
+    buffer
+        .get(offset..offset + 4)
+        .and_then(|bytes| bytes.try_into().ok())
+        .map(u32::from_ne_bytes)
+}
+
+fn read_i32(buffer: &[u8], offset: usize) -> Option<i32> {
+    buffer
+        .get(offset..offset + 4)
+        .and_then(|bytes| bytes.try_into().ok())
+        .map(i32::from_ne_bytes)
+}
+
+/// Parses an address attribute of an `AF_INET` or `AF_INET6` `family`
+fn parse_ip(family: u8, data: &[u8]) -> Option<IpAddr> {
+    match i32::from(family) {
+        libc::AF_INET => <[u8; 4]>::try_from(data)
+            .ok()
+            .map(|octets| IpAddr::V4(Ipv4Addr::from(octets))),
+        libc::AF_INET6 => <[u8; 16]>::try_from(data)
+            .ok()
+            .map(|octets| IpAddr::V6(Ipv6Addr::from(octets))),
+        _ => None,
+    }
+}
+
+/// Reads a nul terminated string attribute
+fn read_string(buffer: &[u8]) -> &[u8] {
+    buffer.split(|&byte| byte == 0).next().unwrap_or_default()
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    pub(crate) use super::route::tests::route_message;
+
+    /// Builds a netlink message of `kind` with `header` and `attributes`
+    /// as payload
+    pub(crate) fn message(
+        kind: u16,
+        header: &[u8],
+        attributes: &[u8],
+    ) -> Vec<u8> {
+        let len = NLMSG_HDRLEN + header.len() + attributes.len();
+        let mut message = dump_request(kind, 1, 0);
+        message[..4]
+            .copy_from_slice(&u32::try_from(len).unwrap().to_ne_bytes());
+        message.extend_from_slice(header);
+        message.extend_from_slice(attributes);
+        message.resize(align(len), 0);
+
+        message
+    }
+
+    /// Builds a route attribute of `kind` with `data`
+    pub(crate) fn attribute(kind: u16, data: &[u8]) -> Vec<u8> {
+        let len = RTA_HDRLEN + data.len();
+        let mut attribute = Vec::with_capacity(align(len));
+        attribute.extend_from_slice(&u16::try_from(len).unwrap().to_ne_bytes());
+        attribute.extend_from_slice(&kind.to_ne_bytes());
+        attribute.extend_from_slice(data);
+        attribute.resize(align(len), 0);
+
+        attribute
+    }
+
+    #[test]
+    fn dump_request_header() {
+        let request = dump_request(libc::RTM_GETADDR, 7, 8);
+
+        assert_eq!(request.len(), 24);
+        assert_eq!(read_u32(&request, 0), Some(24));
+        assert_eq!(read_u16(&request, 4), Some(libc::RTM_GETADDR));
+        assert_eq!(read_u16(&request, 6), Some(0x301));
+        assert_eq!(read_u32(&request, 8), Some(7));
+        assert!(request[12..].iter().all(|&byte| byte == 0));
+    }
+
+    #[test]
+    fn messages() {
+        let mut buffer = message(libc::RTM_NEWLINK, &[1, 2, 3], &[]);
+        buffer.extend(message(libc::RTM_NEWADDR, &[4; 8], &[]));
+
+        let messages: Vec<_> = Messages::new(&buffer).collect();
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].kind, libc::RTM_NEWLINK);
+        assert_eq!(messages[0].payload, &[1, 2, 3]);
+        assert_eq!(messages[0].bytes.len(), NLMSG_HDRLEN + 3);
+        assert_eq!(messages[1].kind, libc::RTM_NEWADDR);
+        assert_eq!(messages[1].payload, &[4; 8]);
+    }
+
+    #[test]
+    fn messages_truncated() {
+        let mut buffer = message(libc::RTM_NEWLINK, &[0; 16], &[]);
+        buffer.extend(message(libc::RTM_NEWLINK, &[0; 16], &[]));
+        buffer.truncate(buffer.len() - 1);
+
+        assert_eq!(Messages::new(&buffer).count(), 1);
+        assert_eq!(Messages::new(&buffer[..NLMSG_HDRLEN - 1]).count(), 0);
+    }
+
+    /// Builds a `NLMSG_DONE` or `NLMSG_ERROR` message of `kind` carrying
+    /// `error`
+    fn status(kind: i32, error: i32) -> Vec<u8> {
+        message(u16::try_from(kind).unwrap(), &error.to_ne_bytes(), &[])
+    }
+
+    #[test]
+    fn reply() {
+        let mut reply = Reply::default();
+        let mut buffer = message(libc::RTM_NEWLINK, &[1; 4], &[]);
+        // Reply to another request
+        let mut other = message(libc::RTM_NEWLINK, &[2; 4], &[]);
+        other[8..12].copy_from_slice(&2u32.to_ne_bytes());
+        buffer.extend(&other);
+        buffer.extend(status(libc::NLMSG_ERROR, 0));
+
+        reply.collect(&buffer, 1).unwrap();
+        assert!(!reply.done);
+        assert_eq!(reply.messages, message(libc::RTM_NEWLINK, &[1; 4], &[]));
+
+        reply.collect(&status(libc::NLMSG_DONE, 0), 1).unwrap();
+        assert!(reply.done);
+        assert!(!reply.interrupted);
+    }
+
+    #[test]
+    fn reply_interrupted() {
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        const DUMP_INTR: u16 = libc::NLM_F_DUMP_INTR as u16;
+
+        let mut reply = Reply::default();
+        let mut done = status(libc::NLMSG_DONE, 0);
+        done[6..8].copy_from_slice(&DUMP_INTR.to_ne_bytes());
+
+        reply.collect(&done, 1).unwrap();
+        assert!(reply.done);
+        assert!(reply.interrupted);
+    }
+
+    #[test]
+    fn reply_error() {
+        let error = Reply::default()
+            .collect(&status(libc::NLMSG_ERROR, -libc::EPERM), 1)
+            .unwrap_err();
+        assert_eq!(error.raw_os_error(), Some(libc::EPERM));
+
+        let error = Reply::default()
+            .collect(&status(libc::NLMSG_DONE, -libc::EINTR), 1)
+            .unwrap_err();
+        assert_eq!(error.raw_os_error(), Some(libc::EINTR));
+    }
+
+    #[test]
+    fn attributes() {
+        let mut buffer = attribute(libc::IFLA_IFNAME, b"eth0\0");
+        buffer.extend(attribute(libc::IFLA_MTU, &1500u32.to_ne_bytes()));
+        // Nested flag is masked
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        buffer.extend(attribute(
+            libc::IFLA_LINKINFO | libc::NLA_F_NESTED as u16,
+            &[],
+        ));
+
+        let attributes: Vec<_> = Attributes::new(&buffer).collect();
+        assert_eq!(
+            attributes,
+            vec![
+                (libc::IFLA_IFNAME, &b"eth0\0"[..]),
+                (libc::IFLA_MTU, &1500u32.to_ne_bytes()[..]),
+                (libc::IFLA_LINKINFO, &[][..]),
+            ]
+        );
+        assert_eq!(read_string(attributes[0].1), b"eth0");
+    }
+
+    #[test]
+    fn attributes_malformed() {
+        let mut buffer = attribute(libc::IFLA_IFNAME, b"eth0\0");
+        // Length smaller than the header
+        buffer.extend(2u16.to_ne_bytes());
+        buffer.extend(libc::IFLA_IFNAME.to_ne_bytes());
+        buffer.extend(attribute(libc::IFLA_IFNAME, b"eth1\0"));
+
+        assert_eq!(Attributes::new(&buffer).count(), 1);
+    }
+}
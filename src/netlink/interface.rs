@@ -0,0 +1,882 @@
+use std::{borrow::Cow, cell::OnceCell, collections::HashMap, net::IpAddr};
+
+use super::{parse_ip, read_string, read_u16, read_u32, Attributes, Messages};
+use crate::{
+    ifaddrs::{InterfaceAddress, InterfaceFlags},
+    kind::{self, LinkKind},
+    libc,
+    sockaddr::{AddressFamily, AddressScope, DadState},
+    udev,
+};
+
+/// Size of `struct ifinfomsg`
+pub(super) const IFINFOMSG_LEN: usize = 16;
+
+/// Size of `struct ifaddrmsg`
+pub(super) const IFADDRMSG_LEN: usize = 8;
+
+/// Lifetime of an address that never expires
+const INFINITY_LIFE_TIME: u32 = u32::MAX;
+
+/// A link from a `RTM_NEWLINK` message
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Link {
+    /// Interface index
+    pub index: u32,
+    /// Interface name (`IFLA_IFNAME`)
+    pub name: Box<[u8]>,
+    /// Interface flags (`IFF_*`)
+    pub flags: InterfaceFlags,
+    /// Device type (`ARPHRD_*`)
+    pub link_type: u16,
+    /// RFC 2863 operational state (`IF_OPER_*`)
+    pub operstate: Option<u8>,
+    /// Whether the link has a carrier
+    pub carrier: Option<bool>,
+    /// Index of the bond or bridge master the link is enslaved to
+    pub master: Option<u32>,
+    /// Kind of a virtual link (`IFLA_INFO_KIND`), e.g. `bridge` or `veth`
+    pub kind: Option<Box<str>>,
+    /// Hardware address
+    pub address: Option<Box<[u8]>>,
+    /// Alternative names (`IFLA_ALT_IFNAME`)
+    pub alt_names: Vec<Box<[u8]>>,
+}
+
+/// An address from a `RTM_NEWADDR` message
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Address {
+    /// Index of the interface the address belongs to
+    pub index: u32,
+    /// The local address
+    pub address: IpAddr,
+    /// Prefix length of the address
+    pub prefix_len: u8,
+    /// Address flags (`IFA_F_*`)
+    pub flags: u32,
+    /// Address scope (`RT_SCOPE_*`)
+    pub scope: u8,
+    /// Preferred lifetime in seconds, `None` if the address never expires
+    pub preferred_lifetime: Option<u32>,
+    /// Valid lifetime in seconds, `None` if the address never expires
+    pub valid_lifetime: Option<u32>,
+}
+
+/// A link with all of its addresses
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Interface {
+    pub link: Link,
+    pub addresses: Vec<Address>,
+    /// Predictable names udev generated for the link, read at most once
+    udev_names: OnceCell<Vec<Box<[u8]>>>,
+}
+
+/// All interfaces from a netlink dump, see [`super::dump`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Interfaces {
+    interfaces: Vec<Interface>,
+}
+
+/// Entry of [`Interfaces::iter`]
+///
+/// Mirrors the entries of `getifaddrs`, every link has one entry without an
+/// address (`AF_PACKET`) and one entry for each of its addresses.
+#[derive(Debug, Clone, Copy)]
+pub struct InterfaceEntry<'a> {
+    pub link: &'a Link,
+    pub address: Option<&'a Address>,
+    udev_names: &'a OnceCell<Vec<Box<[u8]>>>,
+}
+
+impl Link {
+    /// Parses the payload of a `RTM_NEWLINK` message
+    ///
+    /// Returns `None` if the message is truncated or has no name.
+    #[must_use]
+    pub fn parse(payload: &[u8]) -> Option<Self> {
+        let link_type = read_u16(payload, 2)?;
+        let index = read_u32(payload, 4)?;
+        #[allow(clippy::cast_possible_wrap)]
+        let flags = InterfaceFlags::from_bits_truncate(
+            read_u32(payload, 8)? as libc::c_int
+        );
+
+        let mut name = None;
+        let mut operstate = None;
+        let mut carrier = None;
+        let mut master = None;
+        let mut kind = None;
+        let mut address = None;
+        let mut alt_names = Vec::new();
+        for (attribute, data) in Attributes::new(payload.get(IFINFOMSG_LEN..)?)
+        {
+            match attribute {
+                libc::IFLA_IFNAME => name = Some(read_string(data).into()),
+                libc::IFLA_OPERSTATE => operstate = data.first().copied(),
+                libc::IFLA_CARRIER => {
+                    carrier = data.first().map(|&carrier| carrier != 0);
+                }
+                libc::IFLA_MASTER => master = read_u32(data, 0),
+                libc::IFLA_ADDRESS => address = Some(data.into()),
+                libc::IFLA_LINKINFO => {
+                    kind = Attributes::new(data)
+                        .find(|(attribute, _)| {
+                            *attribute == libc::IFLA_INFO_KIND
+                        })
+                        .map(|(_, data)| {
+                            String::from_utf8_lossy(read_string(data)).into()
+                        });
+                }
+                libc::IFLA_PROP_LIST => {
+                    alt_names = Attributes::new(data)
+                        .filter(|(attribute, _)| {
+                            *attribute == libc::IFLA_ALT_IFNAME
+                        })
+                        .map(|(_, data)| read_string(data).into())
+                        .collect();
+                }
+                _ => {}
+            }
+        }
+
+        Some(Self {
+            index,
+            name: name?,
+            flags,
+            link_type,
+            operstate,
+            carrier,
+            master,
+            kind,
+            address,
+            alt_names,
+        })
+    }
+}
+
+impl Address {
+    /// Parses the payload of a `RTM_NEWADDR` message
+    ///
+    /// Returns `None` if the message is truncated or isn't an IPv4 or IPv6
+    /// address.
+    #[must_use]
+    pub fn parse(payload: &[u8]) -> Option<Self> {
+        let (&family, header) = payload.split_first()?;
+        let (&prefix_len, header) = header.split_first()?;
+        let (&flags, header) = header.split_first()?;
+        let (&scope, _) = header.split_first()?;
+        let index = read_u32(payload, 4)?;
+
+        let mut flags = u32::from(flags);
+        let mut local = None;
+        let mut address = None;
+        let mut preferred_lifetime = None;
+        let mut valid_lifetime = None;
+        for (attribute, data) in Attributes::new(payload.get(IFADDRMSG_LEN..)?)
+        {
+            match attribute {
+                libc::IFA_LOCAL => local = parse_ip(family, data),
+                libc::IFA_ADDRESS => address = parse_ip(family, data),
+                // Replaces the 8 bit flags of `struct ifaddrmsg`
+                libc::IFA_FLAGS => flags = read_u32(data, 0).unwrap_or(flags),
+                libc::IFA_CACHEINFO => {
+                    let lifetime = |offset| {
+                        read_u32(data, offset)
+                            .filter(|&lifetime| lifetime != INFINITY_LIFE_TIME)
+                    };
+                    preferred_lifetime = lifetime(0);
+                    valid_lifetime = lifetime(4);
+                }
+                _ => {}
+            }
+        }
+
+        Some(Self {
+            index,
+            // `IFA_ADDRESS` is the peer address for point-to-point links
+            address: local.or(address)?,
+            prefix_len,
+            flags,
+            scope,
+            preferred_lifetime,
+            valid_lifetime,
+        })
+    }
+
+    /// Address family of the address
+    #[must_use]
+    pub const fn family(&self) -> AddressFamily {
+        match self.address {
+            IpAddr::V4(_) => AddressFamily::Inet,
+            IpAddr::V6(_) => AddressFamily::Inet6,
+        }
+    }
+}
+
+impl Interfaces {
+    /// Builds the interfaces from the messages of a `RTM_GETLINK` dump
+    /// (`links`) and a `RTM_GETADDR` dump (`addresses`).
+    ///
+    /// Messages that can't be parsed and addresses of unknown links are
+    /// skipped.
+    #[must_use]
+    pub fn parse(links: &[u8], addresses: &[u8]) -> Self {
+        let mut interfaces: Vec<Interface> = Messages::new(links)
+            .filter(|message| message.kind == libc::RTM_NEWLINK)
+            .filter_map(|message| Link::parse(message.payload))
+            .map(|link| Interface {
+                link,
+                addresses: Vec::new(),
+                udev_names: OnceCell::new(),
+            })
+            .collect();
+
+        let indexes: HashMap<u32, usize> = interfaces
+            .iter()
+            .enumerate()
+            .map(|(position, interface)| (interface.link.index, position))
+            .collect();
+
+        Messages::new(addresses)
+            .filter(|message| message.kind == libc::RTM_NEWADDR)
+            .filter_map(|message| Address::parse(message.payload))
+            .for_each(|address| {
+                if let Some(&position) = indexes.get(&address.index) {
+                    interfaces[position].addresses.push(address);
+                }
+            });
+
+        Self { interfaces }
+    }
+
+    /// Iterates over the interfaces like [`crate::ifaddrs::getifaddrs`]
+    pub fn iter(&self) -> impl Iterator<Item = InterfaceEntry<'_>> {
+        self.interfaces.iter().flat_map(|interface| {
+            let link = &interface.link;
+            let udev_names = &interface.udev_names;
+            std::iter::once(InterfaceEntry {
+                link,
+                address: None,
+                udev_names,
+            })
+            .chain(interface.addresses.iter().map(
+                move |address| InterfaceEntry {
+                    link,
+                    address: Some(address),
+                    udev_names,
+                },
+            ))
+        })
+    }
+
+    /// The interfaces with their addresses
+    #[must_use]
+    pub fn interfaces(&self) -> &[Interface] {
+        &self.interfaces
+    }
+}
+
+impl<'a> InterfaceAddress<'a> for InterfaceEntry<'a> {
+    fn name(&self) -> &'a [u8] {
+        &self.link.name
+    }
+
+    fn flags(&self) -> InterfaceFlags {
+        self.link.flags
+    }
+
+    fn family(&self) -> Option<AddressFamily> {
+        Some(self.address.map_or(AddressFamily::Packet, Address::family))
+    }
+
+    fn address(&self) -> Option<IpAddr> {
+        self.address.map(|address| address.address)
+    }
+
+    fn scope(&self) -> Option<AddressScope> {
+        self.address.map(|address| {
+            // The kernel gives unique local addresses a global scope
+            AddressScope::from_rt_scope(address.scope)
+                .min(AddressScope::from_ip(address.address))
+        })
+    }
+
+    fn dad_state(&self) -> Option<DadState> {
+        self.address
+            .map(|address| DadState::from_ifa_flags(address.flags))
+    }
+
+    fn oper_status(&self) -> Option<u8> {
+        self.link.operstate
+    }
+
+    fn carrier(&self) -> Option<bool> {
+        self.link.carrier
+    }
+
+    fn master(&self) -> Option<u32> {
+        self.link.master
+    }
+
+    fn kind(&self) -> Option<LinkKind> {
+        LinkKind::from_link(
+            &self.link.name,
+            self.link.kind.as_deref(),
+            self.link.link_type,
+        )
+    }
+
+    /// Every link with an `IFLA_INFO_KIND` is virtual, e.g. also `dummy` or
+    /// `macvlan` links
+    fn is_virtual(&self) -> bool {
+        self.link.kind.is_some() || !kind::has_device(&self.link.name)
+    }
+
+    fn index(&self) -> Option<u32> {
+        Some(self.link.index)
+    }
+
+    fn hardware_address(&self) -> Option<Cow<'a, [u8]>> {
+        self.link.address.as_deref().map(Cow::Borrowed)
+    }
+
+    fn alt_names(&self) -> &'a [Box<[u8]>] {
+        &self.link.alt_names
+    }
+
+    fn udev_names(&self) -> Cow<'a, [Box<[u8]>]> {
+        Cow::Borrowed(
+            self.udev_names
+                .get_or_init(|| udev::net_names(self.link.index)),
+        )
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    use std::net::{Ipv4Addr, Ipv6Addr};
+
+    use crate::{
+        arguments::{Args, InterfaceArgument},
+        check_dad,
+        netlink::tests::{attribute, message},
+        network_online,
+        operstate::{OperState, OperStateRange},
+        skipped_virtual_interfaces, DadFailedError,
+    };
+
+    #[allow(clippy::cast_sign_loss)]
+    const FLAGS_UP: u32 = (InterfaceFlags::IFF_UP.bits()
+        | InterfaceFlags::IFF_BROADCAST.bits()
+        | InterfaceFlags::IFF_RUNNING.bits()
+        | InterfaceFlags::IFF_MULTICAST.bits()
+        | InterfaceFlags::IFF_LOWER_UP.bits()) as u32;
+
+    #[allow(clippy::cast_sign_loss)]
+    const FLAGS_LOWER_LAYER_DOWN: u32 = (InterfaceFlags::IFF_UP.bits()
+        | InterfaceFlags::IFF_BROADCAST.bits()
+        | InterfaceFlags::IFF_MULTICAST.bits())
+        as u32;
+
+    /// `RTM_NEWADDR` for `192.0.2.2/24` on interface 4 as sent by the kernel
+    #[cfg(target_endian = "little")]
+    const CANNED_NEWADDR: [u8; 88] = [
+        0x58, 0x00, 0x00, 0x00, 0x14, 0x00, 0x02, 0x00, 0x01, 0x00, 0x00, 0x00,
+        0xff, 0x13, 0x00, 0x00, 0x02, 0x18, 0x80, 0x00, 0x04, 0x00, 0x00, 0x00,
+        0x08, 0x00, 0x01, 0x00, 0xc0, 0x00, 0x02, 0x02, 0x08, 0x00, 0x02, 0x00,
+        0xc0, 0x00, 0x02, 0x02, 0x08, 0x00, 0x04, 0x00, 0xc0, 0x00, 0x02, 0xff,
+        0x09, 0x00, 0x03, 0x00, 0x65, 0x74, 0x68, 0x30, 0x00, 0x00, 0x00, 0x00,
+        0x08, 0x00, 0x08, 0x00, 0x80, 0x00, 0x00, 0x00, 0x14, 0x00, 0x06, 0x00,
+        0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x10, 0x00, 0x00, 0x00,
+        0x10, 0x00, 0x00, 0x00,
+    ];
+
+    /// Builds a `RTM_NEWLINK` message
+    pub(crate) fn link_message(
+        index: u32,
+        name: &str,
+        flags: u32,
+        extra: &[u8],
+    ) -> Vec<u8> {
+        let mut header = Vec::with_capacity(IFINFOMSG_LEN);
+        header.extend([0, 0]);
+        header.extend(libc::ARPHRD_ETHER.to_ne_bytes());
+        header.extend(index.to_ne_bytes());
+        header.extend(flags.to_ne_bytes());
+        header.extend(u32::MAX.to_ne_bytes());
+
+        let mut name = name.as_bytes().to_vec();
+        name.push(0);
+        let mut attributes = attribute(libc::IFLA_IFNAME, &name);
+        attributes.extend_from_slice(extra);
+
+        message(libc::RTM_NEWLINK, &header, &attributes)
+    }
+
+    /// Builds a `RTM_NEWADDR` message
+    pub(crate) fn address_message(
+        index: u32,
+        address: IpAddr,
+        prefix_len: u8,
+        scope: u8,
+        flags: u32,
+    ) -> Vec<u8> {
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        let (family, octets) = match address {
+            IpAddr::V4(address) => {
+                (libc::AF_INET as u8, address.octets().to_vec())
+            }
+            IpAddr::V6(address) => {
+                (libc::AF_INET6 as u8, address.octets().to_vec())
+            }
+        };
+
+        let mut header =
+            vec![family, prefix_len, flags.to_ne_bytes()[0], scope];
+        header.extend(index.to_ne_bytes());
+
+        let mut attributes = attribute(libc::IFA_ADDRESS, &octets);
+        attributes.extend(attribute(libc::IFA_FLAGS, &flags.to_ne_bytes()));
+
+        message(libc::RTM_NEWADDR, &header, &attributes)
+    }
+
+    #[test]
+    fn parse_link() {
+        let mut linkinfo = attribute(libc::IFLA_INFO_KIND, b"bridge\0");
+        linkinfo.extend(attribute(libc::IFLA_INFO_DATA, &[0; 8]));
+        let mut extra = attribute(libc::IFLA_OPERSTATE, &[6]);
+        extra.extend(attribute(libc::IFLA_CARRIER, &[1]));
+        extra.extend(attribute(libc::IFLA_MASTER, &7u32.to_ne_bytes()));
+        extra.extend(attribute(libc::IFLA_ADDRESS, &[2, 0, 0, 0, 0, 1]));
+        extra.extend(attribute(libc::IFLA_LINKINFO, &linkinfo));
+        let mut alt_names = attribute(libc::IFLA_ALT_IFNAME, b"bridge0\0");
+        alt_names.extend(attribute(libc::IFLA_ALT_IFNAME, b"lan\0"));
+        extra.extend(attribute(libc::IFLA_PROP_LIST, &alt_names));
+        let buffer = link_message(3, "br0", FLAGS_UP, &extra);
+
+        let interfaces = Interfaces::parse(&buffer, &[]);
+        let link = &interfaces.interfaces()[0].link;
+
+        assert_eq!(link.index, 3);
+        assert_eq!(&*link.name, b"br0");
+        assert!(link.flags.contains(InterfaceFlags::IFF_LOWER_UP));
+        assert_eq!(link.link_type, libc::ARPHRD_ETHER);
+        assert_eq!(link.operstate, Some(6));
+        assert_eq!(link.carrier, Some(true));
+        assert_eq!(link.master, Some(7));
+        assert_eq!(link.kind.as_deref(), Some("bridge"));
+        assert_eq!(link.address.as_deref(), Some(&[2, 0, 0, 0, 0, 1][..]));
+        assert_eq!(
+            link.alt_names,
+            [&b"bridge0"[..], &b"lan"[..]].map(Box::from)
+        );
+    }
+
+    #[test]
+    fn parse_link_minimal() {
+        let buffer = link_message(1, "eth0", FLAGS_LOWER_LAYER_DOWN, &[]);
+        let link = Link::parse(&buffer[16..]).unwrap();
+
+        assert_eq!(&*link.name, b"eth0");
+        assert!(!link.flags.contains(InterfaceFlags::IFF_LOWER_UP));
+        assert_eq!(link.operstate, None);
+        assert_eq!(link.carrier, None);
+        assert_eq!(link.kind, None);
+        assert!(link.alt_names.is_empty());
+
+        // Truncated `struct ifinfomsg`
+        assert_eq!(Link::parse(&buffer[16..24]), None);
+        // No name
+        assert_eq!(Link::parse(&buffer[16..32]), None);
+    }
+
+    #[cfg(target_endian = "little")]
+    #[test]
+    fn parse_canned_address() {
+        let address = Address::parse(&CANNED_NEWADDR[16..]).unwrap();
+
+        assert_eq!(
+            address,
+            Address {
+                index: 4,
+                address: IpAddr::V4(Ipv4Addr::new(192, 0, 2, 2)),
+                prefix_len: 24,
+                flags: libc::IFA_F_PERMANENT,
+                scope: libc::RT_SCOPE_UNIVERSE,
+                preferred_lifetime: None,
+                valid_lifetime: None,
+            }
+        );
+        assert_eq!(address.family(), AddressFamily::Inet);
+    }
+
+    #[test]
+    fn parse_address() {
+        let ip = IpAddr::V6(Ipv6Addr::new(0xfe80, 0, 0, 0, 0, 0, 0, 1));
+        let flags = libc::IFA_F_TENTATIVE | libc::IFA_F_STABLE_PRIVACY;
+        let buffer = address_message(2, ip, 64, libc::RT_SCOPE_LINK, flags);
+
+        let address = Address::parse(&buffer[16..]).unwrap();
+        assert_eq!(address.address, ip);
+        assert_eq!(address.prefix_len, 64);
+        // Flags from `IFA_FLAGS`, not the 8 bit header flags
+        assert_eq!(address.flags, flags);
+        assert_eq!(address.scope, libc::RT_SCOPE_LINK);
+        assert_eq!(address.family(), AddressFamily::Inet6);
+    }
+
+    #[test]
+    fn parse_address_lifetimes() {
+        let mut header = vec![2, 24, 0, 0];
+        header.extend(1u32.to_ne_bytes());
+        let mut cacheinfo = Vec::new();
+        cacheinfo.extend(300u32.to_ne_bytes());
+        cacheinfo.extend(600u32.to_ne_bytes());
+        cacheinfo.extend([0; 8]);
+        let mut attributes = attribute(libc::IFA_ADDRESS, &[10, 0, 0, 2]);
+        attributes.extend(attribute(libc::IFA_LOCAL, &[10, 0, 0, 1]));
+        attributes.extend(attribute(libc::IFA_CACHEINFO, &cacheinfo));
+        let buffer = message(libc::RTM_NEWADDR, &header, &attributes);
+
+        let address = Address::parse(&buffer[16..]).unwrap();
+        // `IFA_LOCAL` takes precedence over the peer address
+        assert_eq!(address.address, IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)));
+        assert_eq!(address.preferred_lifetime, Some(300));
+        assert_eq!(address.valid_lifetime, Some(600));
+    }
+
+    #[test]
+    fn parse_address_unknown_family() {
+        let mut header = vec![0, 0, 0, 0];
+        header.extend(1u32.to_ne_bytes());
+        let attributes = attribute(libc::IFA_ADDRESS, &[10, 0, 0, 1]);
+        let buffer = message(libc::RTM_NEWADDR, &header, &attributes);
+
+        assert_eq!(Address::parse(&buffer[16..]), None);
+    }
+
+    #[test]
+    fn entries() {
+        let mut links = link_message(1, "lo", FLAGS_UP, &[]);
+        links.extend(link_message(2, "eth0", FLAGS_UP, &[]));
+        let mut addresses = address_message(
+            2,
+            IpAddr::V4(Ipv4Addr::new(192, 0, 2, 2)),
+            24,
+            libc::RT_SCOPE_UNIVERSE,
+            0,
+        );
+        // Unknown interface
+        addresses.extend(address_message(
+            9,
+            IpAddr::V4(Ipv4Addr::new(192, 0, 2, 3)),
+            24,
+            libc::RT_SCOPE_UNIVERSE,
+            0,
+        ));
+
+        let interfaces = Interfaces::parse(&links, &addresses);
+        let entries: Vec<_> = interfaces
+            .iter()
+            .map(|entry| (entry.name(), entry.family()))
+            .collect();
+
+        assert_eq!(
+            entries,
+            vec![
+                (&b"lo"[..], Some(AddressFamily::Packet)),
+                (&b"eth0"[..], Some(AddressFamily::Packet)),
+                (&b"eth0"[..], Some(AddressFamily::Inet)),
+            ]
+        );
+    }
+
+    #[test]
+    fn entries_share_udev_names() {
+        let links = link_message(2, "eth0", FLAGS_UP, &[]);
+        let addresses = address_message(
+            2,
+            IpAddr::V4(Ipv4Addr::new(192, 0, 2, 2)),
+            24,
+            libc::RT_SCOPE_UNIVERSE,
+            0,
+        );
+
+        let interfaces = Interfaces::parse(&links, &addresses);
+        let entries: Vec<_> = interfaces.iter().collect();
+        assert_eq!(entries.len(), 2);
+
+        assert!(matches!(entries[0].udev_names(), Cow::Borrowed(_)));
+        // Read once for both entries
+        assert!(std::ptr::eq(entries[0].udev_names, entries[1].udev_names));
+        assert!(entries[1].udev_names.get().is_some());
+    }
+
+    #[test]
+    fn online() {
+        let mut links = link_message(2, "eth0", FLAGS_UP, &[]);
+        links.extend(link_message(3, "eth1", FLAGS_LOWER_LAYER_DOWN, &[]));
+        let addresses = address_message(
+            2,
+            IpAddr::V6(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1)),
+            64,
+            libc::RT_SCOPE_UNIVERSE,
+            0,
+        );
+        let interfaces = Interfaces::parse(&links, &addresses);
+
+        let args = Args::new();
+        assert!(!network_online(interfaces.iter(), (&args).into()));
+
+        let args = Args::new().ignore(vec!["eth1".into()]);
+        assert!(network_online(interfaces.iter(), (&args).into()));
+
+        let args = Args::new().interface(vec!["eth0".into()]).ipv6(true);
+        assert!(network_online(interfaces.iter(), (&args).into()));
+
+        let args = args.ipv4(true).ipv6(false);
+        assert!(!network_online(interfaces.iter(), (&args).into()));
+
+        let args = args.any(true).interface(vec!["eth1".into()]);
+        assert!(!network_online(interfaces.iter(), (&args).into()));
+    }
+    #[test]
+    fn dad() {
+        let links = link_message(2, "eth0", FLAGS_UP, &[]);
+        let global = IpAddr::V6(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1));
+        let address = |flags| {
+            address_message(2, global, 64, libc::RT_SCOPE_UNIVERSE, flags)
+        };
+
+        let args = Args::new().ipv6(true);
+        for flags in [libc::IFA_F_TENTATIVE, libc::IFA_F_OPTIMISTIC] {
+            let interfaces = Interfaces::parse(&links, &address(flags));
+            assert!(!network_online(interfaces.iter(), (&args).into()));
+            assert_eq!(check_dad(interfaces.iter(), (&args).into()), Ok(()));
+        }
+
+        let interfaces =
+            Interfaces::parse(&links, &address(libc::IFA_F_PERMANENT));
+        assert!(network_online(interfaces.iter(), (&args).into()));
+
+        let flags = libc::IFA_F_TENTATIVE | libc::IFA_F_DADFAILED;
+        let interfaces = Interfaces::parse(&links, &address(flags));
+        assert!(!network_online(interfaces.iter(), (&args).into()));
+        assert_eq!(
+            check_dad(interfaces.iter(), (&args).into()),
+            Err(DadFailedError {
+                interface: "eth0".into(),
+                address: global,
+            })
+        );
+
+        // Only addresses that are taken into account fail
+        let args = Args::new().ipv4(true);
+        assert_eq!(check_dad(interfaces.iter(), (&args).into()), Ok(()));
+        let args = Args::new().ignore(vec!["eth0".into()]);
+        assert_eq!(check_dad(interfaces.iter(), (&args).into()), Ok(()));
+        let args = Args::new().interface(vec!["eth0".into()]);
+        assert!(check_dad(interfaces.iter(), (&args).into()).is_err());
+
+        // Just like addresses outside of `--address-scope`
+        let link_local = IpAddr::V6(Ipv6Addr::new(0xfe80, 0, 0, 0, 0, 0, 0, 1));
+        let interfaces = Interfaces::parse(
+            &links,
+            &address_message(2, link_local, 64, libc::RT_SCOPE_LINK, flags),
+        );
+        assert_eq!(check_dad(interfaces.iter(), (&args).into()), Ok(()));
+        let args = Args::new().ipv6(true);
+        assert_eq!(check_dad(interfaces.iter(), (&args).into()), Ok(()));
+        let args = args.address_scope(AddressScope::Link);
+        assert!(check_dad(interfaces.iter(), (&args).into()).is_err());
+        let args = Args::new().address_scope(AddressScope::Link);
+        assert_eq!(check_dad(interfaces.iter(), (&args).into()), Ok(()));
+    }
+
+    #[test]
+    fn operational_state() {
+        let global = IpAddr::V6(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1));
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        let link = |operstate: libc::c_int| {
+            link_message(
+                2,
+                "wlan0",
+                FLAGS_UP,
+                &attribute(libc::IFLA_OPERSTATE, &[operstate as u8]),
+            )
+        };
+        let address = |flags| {
+            address_message(2, global, 64, libc::RT_SCOPE_UNIVERSE, flags)
+        };
+        let args = Args::new().operational_state(Some(
+            OperStateRange::at_least(OperState::Routable),
+        ));
+
+        let interfaces = Interfaces::parse(
+            &link(libc::IF_OPER_UP),
+            &address(libc::IFA_F_PERMANENT),
+        );
+        assert!(network_online(interfaces.iter(), (&args).into()));
+
+        // The kernel knows better than the flags
+        let interfaces = Interfaces::parse(
+            &link(libc::IF_OPER_DORMANT),
+            &address(libc::IFA_F_PERMANENT),
+        );
+        assert!(!network_online(interfaces.iter(), (&args).into()));
+        let dormant = Args::new().operational_state(Some(OperStateRange::new(
+            OperState::Dormant,
+            OperState::Dormant,
+        )));
+        assert!(network_online(interfaces.iter(), (&dormant).into()));
+
+        // Addresses only count once duplicate address detection finished
+        let interfaces = Interfaces::parse(
+            &link(libc::IF_OPER_UP),
+            &address(libc::IFA_F_TENTATIVE),
+        );
+        assert!(!network_online(interfaces.iter(), (&args).into()));
+        let carrier = Args::new().operational_state(Some(OperStateRange::new(
+            OperState::Carrier,
+            OperState::Carrier,
+        )));
+        assert!(network_online(interfaces.iter(), (&carrier).into()));
+    }
+
+    #[test]
+    fn kinds() {
+        let info_kind = |kind: &str| {
+            let mut kind = kind.as_bytes().to_vec();
+            kind.push(0);
+            attribute(
+                libc::IFLA_LINKINFO,
+                &attribute(libc::IFLA_INFO_KIND, &kind),
+            )
+        };
+        // Names that don't exist in sysfs
+        let mut links =
+            link_message(2, "wo-vlan0", FLAGS_UP, &info_kind("vlan"));
+        links.extend(link_message(
+            3,
+            "wo-veth0",
+            FLAGS_LOWER_LAYER_DOWN,
+            &info_kind("veth"),
+        ));
+        links.extend(link_message(4, "wo-br0", FLAGS_UP, &info_kind("bridge")));
+        let address = |index| {
+            address_message(
+                index,
+                IpAddr::V6(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1)),
+                64,
+                libc::RT_SCOPE_UNIVERSE,
+                0,
+            )
+        };
+        let mut addresses = address(2);
+        addresses.extend(address(4));
+        let interfaces = Interfaces::parse(&links, &addresses);
+
+        let kinds: Vec<_> = interfaces
+            .iter()
+            .filter(|entry| entry.family() == Some(AddressFamily::Packet))
+            .map(|entry| entry.kind())
+            .collect();
+        assert_eq!(
+            kinds,
+            [
+                Some(LinkKind::Vlan),
+                Some(LinkKind::Veth),
+                Some(LinkKind::Bridge)
+            ]
+        );
+
+        let args = Args::new();
+        assert!(!network_online(interfaces.iter(), (&args).into()));
+        let args = Args::new().ignore_kind(vec![LinkKind::Veth]);
+        assert!(network_online(interfaces.iter(), (&args).into()));
+
+        let args = Args::new().interface_kind(vec![LinkKind::Vlan]);
+        assert!(network_online(interfaces.iter(), (&args).into()));
+        let args =
+            Args::new().interface_kind(vec![LinkKind::Vlan, LinkKind::Veth]);
+        assert!(!network_online(interfaces.iter(), (&args).into()));
+
+        // At least one interface of each kind has to exist
+        let args =
+            Args::new().interface_kind(vec![LinkKind::Vlan, LinkKind::Wlan]);
+        assert!(!network_online(interfaces.iter(), (&args).into()));
+        let args = args.any(true);
+        assert!(network_online(interfaces.iter(), (&args).into()));
+
+        // Combined with names
+        let args = Args::new()
+            .interface_kind(vec![LinkKind::Bridge])
+            .interface(vec!["wo-vlan0".into()]);
+        assert!(network_online(interfaces.iter(), (&args).into()));
+    }
+
+    #[test]
+    fn selectors() {
+        let mut extra = attribute(libc::IFLA_ADDRESS, &[2, 0xfc, 0, 0, 0, 1]);
+        extra.extend(attribute(
+            libc::IFLA_PROP_LIST,
+            &attribute(libc::IFLA_ALT_IFNAME, b"enp3s0\0"),
+        ));
+        let mut links = link_message(2, "wo-eth0", FLAGS_UP, &extra);
+        links.extend(link_message(3, "wo-eth1", FLAGS_LOWER_LAYER_DOWN, &[]));
+        let addresses = address_message(
+            2,
+            IpAddr::V4(Ipv4Addr::new(192, 0, 2, 2)),
+            24,
+            libc::RT_SCOPE_UNIVERSE,
+            0,
+        );
+        let interfaces = Interfaces::parse(&links, &addresses);
+        let online = |pattern: &str| {
+            let args = Args::new().interface(vec![InterfaceArgument::new(
+                pattern.parse().unwrap(),
+                None,
+            )]);
+            network_online(interfaces.iter(), (&args).into())
+        };
+
+        assert!(online("enp3s0"));
+        assert!(online("enp*"));
+        assert!(online("mac:02:fc:00:00:00:01"));
+        assert!(online("index:2"));
+        assert!(!online("index:3"));
+        assert!(!online("mac:02:fc:00:00:00:02"));
+        assert!(!online("index:4"));
+
+        let args = Args::new().ignore(vec!["index:3".parse().unwrap()]);
+        assert!(network_online(interfaces.iter(), (&args).into()));
+    }
+
+    #[test]
+    fn virtual_interfaces() {
+        let bridge = attribute(
+            libc::IFLA_LINKINFO,
+            &attribute(libc::IFLA_INFO_KIND, b"bridge\0"),
+        );
+        let mut links =
+            link_message(2, "wo-veth0", FLAGS_LOWER_LAYER_DOWN, &[]);
+        links.extend(link_message(3, "wo-docker0", FLAGS_UP, &bridge));
+        let interfaces = Interfaces::parse(&links, &[]);
+
+        assert!(interfaces.iter().all(|entry| entry.is_virtual()));
+
+        let args = Args::new().ignore_virtual(true);
+        assert_eq!(
+            skipped_virtual_interfaces(interfaces.iter(), (&args).into()),
+            ["wo-docker0", "wo-veth0"].map(Box::from)
+        );
+        let args = Args::new().ignore(vec!["wo-veth0".into()]);
+        assert!(
+            skipped_virtual_interfaces(interfaces.iter(), (&args).into())
+                .is_empty()
+        );
+    }
+}
@@ -0,0 +1,267 @@
+use std::net::IpAddr;
+
+use super::{parse_ip, read_u32, Attributes, Messages};
+use crate::libc;
+
+/// Size of `struct ndmsg`
+pub(super) const NDMSG_LEN: usize = 12;
+
+/// States of an entry with a link-layer address that can be used right away
+/// (`NUD_VALID` without `NUD_INCOMPLETE`)
+///
+/// `NUD_DELAY` and `NUD_PROBE` are stale entries that are being confirmed
+/// again.
+const NUD_USABLE: u16 = libc::NUD_REACHABLE
+    | libc::NUD_STALE
+    | libc::NUD_DELAY
+    | libc::NUD_PROBE
+    | libc::NUD_PERMANENT
+    | libc::NUD_NOARP;
+
+/// An entry of the neighbor table (ARP or NDP) from a `RTM_NEWNEIGH` message
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Neighbor {
+    /// Network layer address of the neighbor
+    pub address: IpAddr,
+    /// Index of the interface the neighbor is reachable on
+    pub index: u32,
+    /// State of the entry (`NUD_*`)
+    pub state: u16,
+}
+
+/// All neighbors from a netlink dump, see [`super::dump_neighbors`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Neighbors {
+    neighbors: Vec<Neighbor>,
+}
+
+impl Neighbor {
+    /// Parses the payload of a `RTM_NEWNEIGH` message
+    ///
+    /// Returns `None` if the message is truncated or isn't an IPv4 or IPv6
+    /// neighbor.
+    #[must_use]
+    pub fn parse(payload: &[u8]) -> Option<Self> {
+        let &family = payload.first()?;
+        let index = read_u32(payload, 4)?;
+        let state = u16::from_ne_bytes(payload.get(8..10)?.try_into().ok()?);
+
+        let address = Attributes::new(payload.get(NDMSG_LEN..)?)
+            .find(|(attribute, _)| *attribute == libc::NDA_DST)
+            .and_then(|(_, data)| parse_ip(family, data))?;
+
+        Some(Self {
+            address,
+            index,
+            state,
+        })
+    }
+
+    /// Checks if the link-layer address of the neighbor is known
+    ///
+    /// This is the case for `REACHABLE` and `STALE` entries, but not while
+    /// the neighbor is still being resolved (`INCOMPLETE`) or after
+    /// resolving it failed (`FAILED`).
+    #[must_use]
+    pub const fn is_resolved(&self) -> bool {
+        self.state & NUD_USABLE != 0
+    }
+}
+
+impl Neighbors {
+    /// Builds the neighbors from the messages of a `RTM_GETNEIGH` dump
+    ///
+    /// Messages that can't be parsed are skipped.
+    #[must_use]
+    pub fn parse(neighbors: &[u8]) -> Self {
+        let neighbors = Messages::new(neighbors)
+            .filter(|message| message.kind == libc::RTM_NEWNEIGH)
+            .filter_map(|message| Neighbor::parse(message.payload))
+            .collect();
+
+        Self { neighbors }
+    }
+
+    /// All neighbors of all interfaces
+    #[must_use]
+    pub fn neighbors(&self) -> &[Neighbor] {
+        &self.neighbors
+    }
+
+    /// The entry of `address` on the interface `index`
+    #[must_use]
+    pub fn get(&self, address: IpAddr, index: u32) -> Option<&Neighbor> {
+        self.neighbors.iter().find(|neighbor| {
+            neighbor.address == address && neighbor.index == index
+        })
+    }
+
+    /// Checks if `address` is resolved on the interface `index`
+    #[must_use]
+    pub fn is_resolved(&self, address: IpAddr, index: u32) -> bool {
+        self.get(address, index).is_some_and(Neighbor::is_resolved)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    use crate::{
+        arguments::{Args, RouteFamily},
+        gateways_resolved,
+        netlink::{
+            route::tests::route_message,
+            tests::{attribute, message},
+            Routes,
+        },
+        unresolved_gateways,
+    };
+
+    /// Builds a `RTM_NEWNEIGH` message
+    pub(crate) fn neighbor_message(
+        address: IpAddr,
+        index: u32,
+        state: u16,
+    ) -> Vec<u8> {
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        let (family, octets) = match address {
+            IpAddr::V4(address) => {
+                (libc::AF_INET as u8, address.octets().to_vec())
+            }
+            IpAddr::V6(address) => {
+                (libc::AF_INET6 as u8, address.octets().to_vec())
+            }
+        };
+
+        let mut header = vec![family, 0, 0, 0];
+        header.extend(index.to_ne_bytes());
+        header.extend(state.to_ne_bytes());
+        // Flags and type
+        header.extend([0, 1]);
+
+        let mut attributes = attribute(libc::NDA_DST, &octets);
+        attributes.extend(attribute(libc::NDA_LLADDR, &[2, 0, 0, 0, 0, 1]));
+
+        message(libc::RTM_NEWNEIGH, &header, &attributes)
+    }
+
+    #[test]
+    fn parse_neighbors() {
+        let gateway: IpAddr = "192.0.2.1".parse().unwrap();
+        let router: IpAddr = "fe80::1".parse().unwrap();
+
+        let mut bytes = neighbor_message(gateway, 2, libc::NUD_STALE);
+        bytes.extend(neighbor_message(router, 2, libc::NUD_INCOMPLETE));
+        bytes.extend(neighbor_message(gateway, 3, libc::NUD_FAILED));
+        let neighbors = Neighbors::parse(&bytes);
+
+        assert_eq!(
+            neighbors.neighbors()[0],
+            Neighbor {
+                address: gateway,
+                index: 2,
+                state: libc::NUD_STALE,
+            }
+        );
+        assert_eq!(neighbors.neighbors().len(), 3);
+
+        assert!(neighbors.is_resolved(gateway, 2));
+        assert!(!neighbors.is_resolved(gateway, 3));
+        assert!(!neighbors.is_resolved(gateway, 4));
+        assert!(!neighbors.is_resolved(router, 2));
+        assert!(neighbors.get(router, 2).is_some());
+
+        // Truncated `struct ndmsg`
+        assert_eq!(Neighbor::parse(&[libc::AF_INET as u8, 0, 0, 0]), None);
+    }
+
+    #[test]
+    fn resolved() {
+        let neighbor = |state| Neighbor {
+            address: "192.0.2.1".parse().unwrap(),
+            index: 2,
+            state,
+        };
+
+        for state in [
+            libc::NUD_REACHABLE,
+            libc::NUD_STALE,
+            libc::NUD_DELAY,
+            libc::NUD_PROBE,
+            libc::NUD_PERMANENT,
+        ] {
+            assert!(neighbor(state).is_resolved());
+        }
+        for state in [libc::NUD_NONE, libc::NUD_INCOMPLETE, libc::NUD_FAILED] {
+            assert!(!neighbor(state).is_resolved());
+        }
+    }
+
+    #[test]
+    fn gateways() {
+        let gateway: IpAddr = "192.0.2.1".parse().unwrap();
+        let router: IpAddr = "fe80::1".parse().unwrap();
+        let mut bytes = route_message(
+            "0.0.0.0/0".parse().unwrap(),
+            Some(gateway),
+            2,
+            libc::RTN_UNICAST,
+            0,
+        );
+        bytes.extend(route_message(
+            "::/0".parse().unwrap(),
+            Some(router),
+            2,
+            libc::RTN_UNICAST,
+            0,
+        ));
+        let routes = Routes::parse(&bytes);
+        let neighbors = Neighbors::parse(
+            &[
+                neighbor_message(gateway, 2, libc::NUD_REACHABLE),
+                neighbor_message(router, 2, libc::NUD_FAILED),
+            ]
+            .concat(),
+        );
+
+        let args = Args::new();
+        assert!(gateways_resolved(&routes, &neighbors, (&args).into()));
+        assert_eq!(
+            unresolved_gateways(&routes, &neighbors, (&args).into()).next(),
+            None
+        );
+
+        let args = Args::new().gateway_neighbor(Some(RouteFamily::Any));
+        assert!(gateways_resolved(&routes, &neighbors, (&args).into()));
+        assert_eq!(
+            unresolved_gateways(&routes, &neighbors, (&args).into())
+                .collect::<Vec<_>>(),
+            [(router, 2)]
+        );
+
+        let args = args.gateway_neighbor(Some(RouteFamily::Ipv4));
+        assert!(gateways_resolved(&routes, &neighbors, (&args).into()));
+        let args = args.gateway_neighbor(Some(RouteFamily::Ipv6));
+        assert!(!gateways_resolved(&routes, &neighbors, (&args).into()));
+        assert!(!gateways_resolved(
+            &routes,
+            &Neighbors::default(),
+            (&Args::new().gateway_neighbor(Some(RouteFamily::Any))).into()
+        ));
+
+        // A default route without gateway
+        let routes = Routes::parse(&route_message(
+            "0.0.0.0/0".parse().unwrap(),
+            None,
+            3,
+            libc::RTN_UNICAST,
+            0,
+        ));
+        assert!(gateways_resolved(
+            &routes,
+            &Neighbors::default(),
+            (&args.gateway_neighbor(Some(RouteFamily::Ipv4))).into()
+        ));
+    }
+}
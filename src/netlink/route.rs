@@ -0,0 +1,424 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use super::{parse_ip, read_u32, Attributes, Messages};
+use crate::{
+    libc,
+    sockaddr::{AddressFamily, Cidr},
+};
+
+/// Size of `struct rtmsg`
+pub(super) const RTMSG_LEN: usize = 12;
+
+/// The next hop is dead (`RTNH_F_DEAD`)
+const RTNH_F_DEAD: u32 = 1;
+
+/// The link of the next hop is down (`RTNH_F_LINKDOWN`)
+const RTNH_F_LINKDOWN: u32 = 16;
+
+/// A route from a `RTM_NEWROUTE` message
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Route {
+    /// Destination prefix, `0.0.0.0/0` or `::/0` for a default route
+    pub destination: Cidr,
+    /// Address of the gateway, `None` for directly connected destinations
+    /// and multipath routes
+    pub gateway: Option<IpAddr>,
+    /// Index of the outgoing interface
+    pub oif: Option<u32>,
+    /// Routing table (`RT_TABLE_*`)
+    pub table: u32,
+    /// Route type (`RTN_*`)
+    pub kind: u8,
+    /// Route flags (`RTM_F_*` and `RTNH_F_*`)
+    pub flags: u32,
+    /// Metric of the route
+    pub priority: Option<u32>,
+}
+
+/// All routes from a netlink dump, see [`super::dump_routes`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Routes {
+    routes: Vec<Route>,
+}
+
+impl Route {
+    /// Parses the payload of a `RTM_NEWROUTE` message
+    ///
+    /// Returns `None` if the message is truncated or isn't an IPv4 or IPv6
+    /// route.
+    #[must_use]
+    pub fn parse(payload: &[u8]) -> Option<Self> {
+        let (&family, header) = payload.split_first()?;
+        let (&dst_len, header) = header.split_first()?;
+        let &[_src_len, _tos, table, _protocol, _scope, kind, ..] = header
+        else {
+            return None;
+        };
+        let flags = read_u32(payload, 8)?;
+
+        let mut destination = None;
+        let mut gateway = None;
+        let mut oif = None;
+        let mut table = u32::from(table);
+        let mut priority = None;
+        for (attribute, data) in Attributes::new(payload.get(RTMSG_LEN..)?) {
+            match attribute {
+                libc::RTA_DST => destination = parse_ip(family, data),
+                libc::RTA_GATEWAY => gateway = parse_ip(family, data),
+                libc::RTA_OIF => oif = read_u32(data, 0),
+                // Replaces the 8 bit table of `struct rtmsg`
+                libc::RTA_TABLE => table = read_u32(data, 0).unwrap_or(table),
+                libc::RTA_PRIORITY => priority = read_u32(data, 0),
+                _ => {}
+            }
+        }
+
+        // Default routes have no `RTA_DST`
+        let destination = match (destination, i32::from(family)) {
+            (Some(destination), _) => destination,
+            (None, libc::AF_INET) => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+            (None, libc::AF_INET6) => IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+            (None, _) => return None,
+        };
+
+        Some(Self {
+            destination: Cidr::new(destination, dst_len)?,
+            gateway,
+            oif,
+            table,
+            kind,
+            flags,
+            priority,
+        })
+    }
+
+    /// Address family of the route
+    #[must_use]
+    pub const fn family(&self) -> AddressFamily {
+        match self.destination.address() {
+            IpAddr::V4(_) => AddressFamily::Inet,
+            IpAddr::V6(_) => AddressFamily::Inet6,
+        }
+    }
+
+    /// Checks if the route is a default route (`0.0.0.0/0` or `::/0`)
+    #[must_use]
+    pub const fn is_default(&self) -> bool {
+        self.destination.prefix_len() == 0
+    }
+
+    /// Checks if traffic can be sent over the route
+    ///
+    /// Only unicast routes are usable, not e.g. `unreachable` or `blackhole`
+    /// routes, and only while their next hop is alive.
+    #[must_use]
+    pub const fn is_usable(&self) -> bool {
+        self.kind == libc::RTN_UNICAST
+            && self.flags & (RTNH_F_DEAD | RTNH_F_LINKDOWN) == 0
+    }
+}
+
+impl Routes {
+    /// Builds the routes from the messages of a `RTM_GETROUTE` dump
+    ///
+    /// Messages that can't be parsed are skipped.
+    #[must_use]
+    pub fn parse(routes: &[u8]) -> Self {
+        let routes = Messages::new(routes)
+            .filter(|message| message.kind == libc::RTM_NEWROUTE)
+            .filter_map(|message| Route::parse(message.payload))
+            .collect();
+
+        Self { routes }
+    }
+
+    /// All routes of all routing tables
+    #[must_use]
+    pub fn routes(&self) -> &[Route] {
+        &self.routes
+    }
+
+    /// A usable default route of `family`, `None` matches both IPv4 and IPv6
+    #[must_use]
+    pub fn default_route(
+        &self,
+        family: Option<AddressFamily>,
+    ) -> Option<&Route> {
+        self.default_routes(family).next()
+    }
+
+    /// All usable default routes of `family`, `None` matches both IPv4 and
+    /// IPv6
+    pub fn default_routes(
+        &self,
+        family: Option<AddressFamily>,
+    ) -> impl Iterator<Item = &Route> {
+        self.routes.iter().filter(move |route| {
+            route.is_usable()
+                && route.is_default()
+                && family.map_or(true, |family| route.family() == family)
+        })
+    }
+
+    /// The most specific route that covers all of `destination`, this
+    /// includes default routes, if it is usable
+    ///
+    /// Like the kernel the longest prefix wins, so e.g. an `unreachable` route
+    /// hides a default route. Policy routing rules aren't taken into account,
+    /// the routes of the local table are ignored.
+    #[must_use]
+    pub fn route_to(&self, destination: &Cidr) -> Option<&Route> {
+        self.routes
+            .iter()
+            .filter(|route| {
+                route.table != u32::from(libc::RT_TABLE_LOCAL)
+                    && route.destination.contains(destination)
+            })
+            .min_by_key(|route| {
+                (
+                    std::cmp::Reverse(route.destination.prefix_len()),
+                    route.priority.unwrap_or(0),
+                )
+            })
+            .filter(|route| route.is_usable())
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    use crate::{
+        arguments::{Args, RouteFamily},
+        netlink::tests::{attribute, message},
+        routes_online,
+    };
+
+    /// A `RTM_GETROUTE` dump of `default via 192.0.2.1 dev eth0` and
+    /// `192.0.2.0/24 dev eth0 proto kernel scope link src 192.0.2.2`, as
+    /// received from the kernel
+    #[cfg(target_endian = "little")]
+    const CANNED_ROUTES: [u8; 112] = [
+        0x34, 0x00, 0x00, 0x00, 0x18, 0x00, 0x02, 0x00, 0x01, 0x00, 0x00, 0x00,
+        0x55, 0x33, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0xfe, 0x03, 0x00, 0x01,
+        0x00, 0x00, 0x00, 0x00, 0x08, 0x00, 0x0f, 0x00, 0xfe, 0x00, 0x00, 0x00,
+        0x08, 0x00, 0x05, 0x00, 0xc0, 0x00, 0x02, 0x01, 0x08, 0x00, 0x04, 0x00,
+        0x04, 0x00, 0x00, 0x00, 0x3c, 0x00, 0x00, 0x00, 0x18, 0x00, 0x02, 0x00,
+        0x01, 0x00, 0x00, 0x00, 0x55, 0x33, 0x00, 0x00, 0x02, 0x18, 0x00, 0x00,
+        0xfe, 0x02, 0xfd, 0x01, 0x00, 0x00, 0x00, 0x00, 0x08, 0x00, 0x0f, 0x00,
+        0xfe, 0x00, 0x00, 0x00, 0x08, 0x00, 0x01, 0x00, 0xc0, 0x00, 0x02, 0x00,
+        0x08, 0x00, 0x07, 0x00, 0xc0, 0x00, 0x02, 0x02, 0x08, 0x00, 0x04, 0x00,
+        0x04, 0x00, 0x00, 0x00,
+    ];
+
+    /// Builds a `RTM_NEWROUTE` message in the main table
+    pub(crate) fn route_message(
+        destination: Cidr,
+        gateway: Option<IpAddr>,
+        oif: u32,
+        kind: u8,
+        flags: u32,
+    ) -> Vec<u8> {
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        let family = match destination.address() {
+            IpAddr::V4(_) => libc::AF_INET as u8,
+            IpAddr::V6(_) => libc::AF_INET6 as u8,
+        };
+        let octets = |address: IpAddr| match address {
+            IpAddr::V4(address) => address.octets().to_vec(),
+            IpAddr::V6(address) => address.octets().to_vec(),
+        };
+
+        let mut header = vec![
+            family,
+            destination.prefix_len(),
+            0,
+            0,
+            libc::RT_TABLE_MAIN,
+            libc::RTPROT_BOOT,
+            libc::RT_SCOPE_UNIVERSE,
+            kind,
+        ];
+        header.extend(flags.to_ne_bytes());
+
+        let mut attributes = attribute(
+            libc::RTA_TABLE,
+            &u32::from(libc::RT_TABLE_MAIN).to_ne_bytes(),
+        );
+        if destination.prefix_len() != 0 {
+            attributes.extend(attribute(
+                libc::RTA_DST,
+                &octets(destination.address()),
+            ));
+        }
+        if let Some(gateway) = gateway {
+            attributes.extend(attribute(libc::RTA_GATEWAY, &octets(gateway)));
+        }
+        attributes.extend(attribute(libc::RTA_OIF, &oif.to_ne_bytes()));
+
+        message(libc::RTM_NEWROUTE, &header, &attributes)
+    }
+
+    fn cidr(cidr: &str) -> Cidr {
+        cidr.parse().unwrap()
+    }
+
+    #[test]
+    #[cfg(target_endian = "little")]
+    fn parse_canned_routes() {
+        let routes = Routes::parse(&CANNED_ROUTES);
+
+        assert_eq!(
+            routes.routes(),
+            [
+                Route {
+                    destination: cidr("0.0.0.0/0"),
+                    gateway: Some(IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1))),
+                    oif: Some(4),
+                    table: u32::from(libc::RT_TABLE_MAIN),
+                    kind: libc::RTN_UNICAST,
+                    flags: 0,
+                    priority: None,
+                },
+                Route {
+                    destination: cidr("192.0.2.0/24"),
+                    gateway: None,
+                    oif: Some(4),
+                    table: u32::from(libc::RT_TABLE_MAIN),
+                    kind: libc::RTN_UNICAST,
+                    flags: 0,
+                    priority: None,
+                },
+            ]
+        );
+        assert!(routes.routes()[0].is_default());
+        assert!(!routes.routes()[1].is_default());
+    }
+
+    #[test]
+    fn parse_route() {
+        let gateway = "2001:db8::1".parse().ok();
+        let mut bytes = route_message(
+            cidr("2001:db8:1::/48"),
+            gateway,
+            2,
+            libc::RTN_UNICAST,
+            0,
+        );
+        bytes.extend(attribute(libc::RTA_PRIORITY, &1024u32.to_ne_bytes()));
+        // Fix up the length for the appended attribute
+        #[allow(clippy::cast_possible_truncation)]
+        let len = bytes.len() as u32;
+        bytes[..4].copy_from_slice(&len.to_ne_bytes());
+
+        let routes = Routes::parse(&bytes);
+        let route = &routes.routes()[0];
+        assert_eq!(route.destination, cidr("2001:db8:1::/48"));
+        assert_eq!(route.gateway, gateway);
+        assert_eq!(route.oif, Some(2));
+        assert_eq!(route.priority, Some(1024));
+        assert_eq!(route.family(), AddressFamily::Inet6);
+        assert!(route.is_usable());
+
+        // Truncated `struct rtmsg`
+        assert_eq!(Route::parse(&[libc::AF_INET as u8, 0, 0]), None);
+    }
+
+    #[test]
+    fn default_route() {
+        let gateway = "192.0.2.1".parse().ok();
+        let mut bytes =
+            route_message(cidr("192.0.2.0/24"), None, 2, libc::RTN_UNICAST, 0);
+        let routes = Routes::parse(&bytes);
+        assert_eq!(routes.default_route(None), None);
+
+        bytes.extend(route_message(
+            cidr("::/0"),
+            None,
+            2,
+            libc::RTN_UNREACHABLE,
+            0,
+        ));
+        bytes.extend(route_message(
+            cidr("0.0.0.0/0"),
+            gateway,
+            3,
+            libc::RTN_UNICAST,
+            RTNH_F_LINKDOWN,
+        ));
+        let routes = Routes::parse(&bytes);
+        assert_eq!(routes.default_route(None), None);
+
+        bytes.extend(route_message(
+            cidr("0.0.0.0/0"),
+            gateway,
+            2,
+            libc::RTN_UNICAST,
+            0,
+        ));
+        let routes = Routes::parse(&bytes);
+        assert_eq!(
+            routes.default_route(None).and_then(|route| route.oif),
+            Some(2)
+        );
+        assert!(routes.default_route(Some(AddressFamily::Inet)).is_some());
+        assert!(routes.default_route(Some(AddressFamily::Inet6)).is_none());
+    }
+
+    #[test]
+    fn route_to() {
+        let bytes =
+            route_message(cidr("10.0.0.0/8"), None, 2, libc::RTN_UNICAST, 0);
+        let routes = Routes::parse(&bytes);
+
+        assert!(routes.route_to(&cidr("10.1.0.0/16")).is_some());
+        assert!(routes.route_to(&cidr("10.1.2.3")).is_some());
+        assert!(routes.route_to(&cidr("10.0.0.0/7")).is_none());
+        assert!(routes.route_to(&cidr("192.0.2.1")).is_none());
+        assert!(routes.route_to(&cidr("::/0")).is_none());
+
+        let mut bytes = route_message(
+            cidr("0.0.0.0/0"),
+            "192.0.2.1".parse().ok(),
+            2,
+            libc::RTN_UNICAST,
+            0,
+        );
+        bytes.extend(route_message(
+            cidr("10.0.0.0/8"),
+            None,
+            0,
+            libc::RTN_UNREACHABLE,
+            0,
+        ));
+        let routes = Routes::parse(&bytes);
+        assert!(routes.route_to(&cidr("10.1.2.3")).is_none());
+        assert!(routes.route_to(&cidr("0.0.0.0/0")).is_some());
+        assert!(routes.route_to(&cidr("192.0.2.1")).is_some());
+    }
+
+    #[test]
+    #[cfg(target_endian = "little")]
+    fn online() {
+        let routes = Routes::parse(&CANNED_ROUTES);
+
+        let args = Args::new();
+        assert!(routes_online(&routes, (&args).into()));
+
+        let args = Args::new().require_default_route(Some(RouteFamily::Any));
+        assert!(routes_online(&routes, (&args).into()));
+        let args = args.require_default_route(Some(RouteFamily::Ipv4));
+        assert!(routes_online(&routes, (&args).into()));
+        let args = args.require_default_route(Some(RouteFamily::Ipv6));
+        assert!(!routes_online(&routes, (&args).into()));
+
+        let args = Args::new().route_to(vec![cidr("192.0.2.128/25")]);
+        assert!(routes_online(&routes, (&args).into()));
+        let args = args.route_to(vec![cidr("198.51.100.0/24")]);
+        assert!(routes_online(&routes, (&args).into()));
+        let args =
+            args.route_to(vec![cidr("198.51.100.1"), cidr("2001:db8::1")]);
+        assert!(!routes_online(&routes, (&args).into()));
+        assert!(!routes_online(&Routes::default(), (&args).into()));
+    }
+}
@@ -1,8 +1,20 @@
+use std::{fmt, str::FromStr};
+
+use crate::{
+    ifaddrs::{InterfaceAddress, InterfaceFlags},
+    libc,
+    sockaddr::{AddressScope, DadState},
+};
+
 /// Systemd opersational states
 ///
 /// (See
 /// [`netowrkctl(1)`](https://man7.org/linux/man-pages/man1/networkctl.1.html)
 /// for more info)
+///
+/// The states are ordered from [`OperState::Missing`] to
+/// [`OperState::Routable`], like in `systemd-networkd-wait-online`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum OperState {
     /// the device is missing
     Missing,
@@ -38,3 +50,395 @@ pub enum OperState {
     /// have carrier, but at least one must.
     Routable,
 }
+
+impl OperState {
+    const ALL: [Self; 9] = [
+        Self::Missing,
+        Self::Off,
+        Self::NoCarrier,
+        Self::Dormant,
+        Self::DegradedCarrier,
+        Self::Carrier,
+        Self::Degraded,
+        Self::Enslaved,
+        Self::Routable,
+    ];
+
+    /// Name of the state as used by `networkctl`
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Missing => "missing",
+            Self::Off => "off",
+            Self::NoCarrier => "no-carrier",
+            Self::Dormant => "dormant",
+            Self::DegradedCarrier => "degraded-carrier",
+            Self::Carrier => "carrier",
+            Self::Degraded => "degraded",
+            Self::Enslaved => "enslaved",
+            Self::Routable => "routable",
+        }
+    }
+}
+
+impl fmt::Display for OperState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for OperState {
+    type Err = ParseOperStateError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|state| state.as_str() == s)
+            .ok_or_else(|| ParseOperStateError(s.into()))
+    }
+}
+
+/// Error returned when parsing an unknown [`OperState`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseOperStateError(Box<str>);
+
+impl fmt::Display for ParseOperStateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "unknown operational state `{}`, expected one of ",
+            self.0
+        )?;
+        for (i, state) in OperState::ALL.into_iter().enumerate() {
+            if i != 0 {
+                f.write_str(", ")?;
+            }
+            f.write_str(state.as_str())?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ParseOperStateError {}
+
+/// Inclusive range of accepted [`OperState`]s, parsed from `MIN[:MAX]`
+///
+/// `MAX` defaults to [`OperState::Routable`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OperStateRange {
+    pub min: OperState,
+    pub max: OperState,
+}
+
+impl OperStateRange {
+    #[must_use]
+    pub const fn new(min: OperState, max: OperState) -> Self {
+        Self { min, max }
+    }
+
+    /// Range from `min` up to [`OperState::Routable`]
+    #[must_use]
+    pub const fn at_least(min: OperState) -> Self {
+        Self::new(min, OperState::Routable)
+    }
+
+    #[must_use]
+    pub fn contains(self, state: OperState) -> bool {
+        self.min <= state && state <= self.max
+    }
+}
+
+impl fmt::Display for OperStateRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.min, self.max)
+    }
+}
+
+impl FromStr for OperStateRange {
+    type Err = ParseOperStateError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (min, max) =
+            s.split_once(':').map_or(Ok((s, None)), |(min, max)| {
+                Ok((min, Some(max.parse()?)))
+            })?;
+        let min = min.parse()?;
+
+        Ok(Self::new(min, max.unwrap_or(OperState::Routable)))
+    }
+}
+
+/// Flags and addresses of an interface, collected from all of its entries to
+/// determine its [`OperState`]
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct LinkState {
+    flags: InterfaceFlags,
+    /// Operational state reported by the kernel (`IF_OPER_*`), if known
+    oper_status: Option<u8>,
+    /// Carrier reported by the kernel, if known
+    carrier: Option<bool>,
+    enslaved: bool,
+    /// Widest scope of all addresses that passed duplicate address detection
+    scope: Option<AddressScope>,
+}
+
+impl LinkState {
+    pub(crate) const fn new() -> Self {
+        Self {
+            flags: InterfaceFlags::empty(),
+            oper_status: None,
+            carrier: None,
+            enslaved: false,
+            scope: None,
+        }
+    }
+
+    pub(crate) fn update<'a, T>(&mut self, ifaddr: T)
+    where
+        T: InterfaceAddress<'a>,
+    {
+        self.flags |= ifaddr.flags();
+        self.oper_status = self.oper_status.or(ifaddr.oper_status());
+        self.carrier = self.carrier.or(ifaddr.carrier());
+        self.enslaved |= ifaddr.master().is_some();
+        // Tentative addresses can't be used yet, just like networkd
+        if ifaddr.dad_state().map_or(true, |dad| dad == DadState::Done) {
+            self.scope = self.scope.max(ifaddr.scope());
+        }
+    }
+
+    /// Classifies the interface like `systemd-networkd`
+    ///
+    /// [`OperState::Missing`] is never returned, the interface exists, and
+    /// neither is [`OperState::DegradedCarrier`] since the state of the slave
+    /// interfaces isn't taken into account.
+    ///
+    /// The operational state and carrier reported by the kernel take
+    /// precedence over the `IFF_DORMANT` and `IFF_LOWER_UP` flags.
+    pub(crate) fn operstate(&self) -> OperState {
+        if !self.flags.contains(InterfaceFlags::IFF_UP) {
+            return OperState::Off;
+        }
+        let carrier = match self.oper_status.map(libc::c_int::from) {
+            Some(libc::IF_OPER_UP) => true,
+            Some(libc::IF_OPER_DORMANT) => return OperState::Dormant,
+            Some(
+                libc::IF_OPER_DOWN
+                | libc::IF_OPER_LOWERLAYERDOWN
+                | libc::IF_OPER_NOTPRESENT,
+            ) => false,
+            // `IF_OPER_UNKNOWN` (e.g. for tun devices), `IF_OPER_TESTING` or
+            // no operational state at all
+            _ => {
+                if self.flags.contains(InterfaceFlags::IFF_DORMANT) {
+                    return OperState::Dormant;
+                }
+                self.carrier.unwrap_or_else(|| {
+                    self.flags.contains(InterfaceFlags::IFF_LOWER_UP)
+                })
+            }
+        };
+        if !carrier {
+            return OperState::NoCarrier;
+        }
+        if self.enslaved || self.flags.contains(InterfaceFlags::IFF_SLAVE) {
+            return OperState::Enslaved;
+        }
+
+        match self.scope {
+            Some(AddressScope::Global | AddressScope::Site) => {
+                OperState::Routable
+            }
+            Some(AddressScope::Link) => OperState::Degraded,
+            Some(AddressScope::Host) | None => OperState::Carrier,
+        }
+    }
+}
+
+impl Default for LinkState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn order() {
+        assert!(OperState::ALL.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+
+    #[test]
+    fn parse() {
+        for state in OperState::ALL {
+            assert_eq!(state.as_str().parse(), Ok(state));
+            assert_eq!(state.to_string(), state.as_str());
+        }
+        assert!("up".parse::<OperState>().is_err());
+        assert!("Routable".parse::<OperState>().is_err());
+    }
+
+    #[test]
+    fn parse_range() {
+        use OperState::{Carrier, Degraded, NoCarrier, Routable};
+
+        assert_eq!(
+            "degraded".parse(),
+            Ok(OperStateRange::new(Degraded, Routable))
+        );
+        assert_eq!(
+            "no-carrier:carrier".parse(),
+            Ok(OperStateRange::new(NoCarrier, Carrier))
+        );
+        assert!("degraded:".parse::<OperStateRange>().is_err());
+        assert!(":degraded".parse::<OperStateRange>().is_err());
+        assert!("degraded:routable:routable"
+            .parse::<OperStateRange>()
+            .is_err());
+        assert_eq!(
+            "carrier:routable"
+                .parse::<OperStateRange>()
+                .map(|r| r.to_string()),
+            Ok("carrier:routable".into())
+        );
+    }
+
+    #[test]
+    fn range_contains() {
+        let range =
+            OperStateRange::new(OperState::Carrier, OperState::Degraded);
+
+        assert!(!range.contains(OperState::NoCarrier));
+        assert!(range.contains(OperState::Carrier));
+        assert!(range.contains(OperState::Degraded));
+        assert!(!range.contains(OperState::Routable));
+        assert!(OperStateRange::at_least(OperState::Off)
+            .contains(OperState::Routable));
+    }
+
+    fn link_state(
+        flags: InterfaceFlags,
+        enslaved: bool,
+        scope: Option<AddressScope>,
+    ) -> OperState {
+        LinkState {
+            flags,
+            enslaved,
+            scope,
+            ..LinkState::new()
+        }
+        .operstate()
+    }
+
+    fn kernel_link_state(
+        flags: InterfaceFlags,
+        oper_status: libc::c_int,
+        carrier: Option<bool>,
+    ) -> OperState {
+        LinkState {
+            flags,
+            oper_status: u8::try_from(oper_status).ok(),
+            carrier,
+            scope: Some(AddressScope::Global),
+            ..LinkState::new()
+        }
+        .operstate()
+    }
+
+    #[test]
+    fn classify() {
+        let up = InterfaceFlags::IFF_UP;
+        let carrier = up | InterfaceFlags::IFF_LOWER_UP;
+
+        assert_eq!(
+            link_state(InterfaceFlags::empty(), false, None),
+            OperState::Off
+        );
+        assert_eq!(
+            link_state(InterfaceFlags::IFF_LOWER_UP, false, None),
+            OperState::Off
+        );
+        assert_eq!(link_state(up, false, None), OperState::NoCarrier);
+        assert_eq!(
+            link_state(up, false, Some(AddressScope::Global)),
+            OperState::NoCarrier
+        );
+        assert_eq!(
+            link_state(carrier | InterfaceFlags::IFF_DORMANT, false, None),
+            OperState::Dormant
+        );
+        assert_eq!(link_state(carrier, false, None), OperState::Carrier);
+        assert_eq!(
+            link_state(carrier, false, Some(AddressScope::Host)),
+            OperState::Carrier
+        );
+        assert_eq!(
+            link_state(carrier, false, Some(AddressScope::Link)),
+            OperState::Degraded
+        );
+        assert_eq!(
+            link_state(carrier, false, Some(AddressScope::Site)),
+            OperState::Routable
+        );
+        assert_eq!(
+            link_state(carrier, false, Some(AddressScope::Global)),
+            OperState::Routable
+        );
+        assert_eq!(
+            link_state(carrier, true, Some(AddressScope::Global)),
+            OperState::Enslaved
+        );
+        assert_eq!(
+            link_state(carrier | InterfaceFlags::IFF_SLAVE, false, None),
+            OperState::Enslaved
+        );
+    }
+
+    #[test]
+    fn classify_kernel_state() {
+        let up = InterfaceFlags::IFF_UP;
+        let carrier = up | InterfaceFlags::IFF_LOWER_UP;
+
+        // The kernel state wins over the flags
+        assert_eq!(
+            kernel_link_state(carrier, libc::IF_OPER_DORMANT, Some(true)),
+            OperState::Dormant
+        );
+        assert_eq!(
+            kernel_link_state(
+                carrier,
+                libc::IF_OPER_LOWERLAYERDOWN,
+                Some(true)
+            ),
+            OperState::NoCarrier
+        );
+        assert_eq!(
+            kernel_link_state(up, libc::IF_OPER_UP, None),
+            OperState::Routable
+        );
+        assert_eq!(
+            kernel_link_state(
+                InterfaceFlags::empty(),
+                libc::IF_OPER_UP,
+                Some(true)
+            ),
+            OperState::Off
+        );
+
+        // Without a known state the carrier decides
+        assert_eq!(
+            kernel_link_state(carrier, libc::IF_OPER_UNKNOWN, Some(false)),
+            OperState::NoCarrier
+        );
+        assert_eq!(
+            kernel_link_state(up, libc::IF_OPER_UNKNOWN, Some(true)),
+            OperState::Routable
+        );
+        assert_eq!(
+            kernel_link_state(up, libc::IF_OPER_UNKNOWN, None),
+            OperState::NoCarrier
+        );
+    }
+}
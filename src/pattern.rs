@@ -0,0 +1,414 @@
+use std::{
+    ffi::{CStr, CString},
+    fmt, ptr,
+    str::FromStr,
+    sync::Arc,
+};
+
+use crate::{ifaddrs::InterfaceAddress, libc};
+
+/// Interface pattern of `--interface` and `--ignore`
+///
+/// A pattern containing `*`, `?` or `[` is a shell glob, a `re:` prefix makes
+/// it a POSIX extended regex. Globs and regexes have to match the whole name.
+/// Names, globs and regexes also match alternative names and the predictable
+/// names of udev. `mac:` and `index:` select an interface by its hardware
+/// address or its index instead.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InterfacePattern {
+    /// Matches exactly this name
+    Name(Box<str>),
+    /// Shell glob (`*`, `?`, `[...]`), see `glob(7)`
+    Glob(Box<str>),
+    Regex(Regex),
+    /// Hardware address, e.g. `mac:02:fc:00:00:00:01`
+    Mac(Box<[u8]>),
+    /// Interface index, e.g. `index:3`
+    Index(u32),
+}
+
+impl InterfacePattern {
+    /// Checks if the interface `name` matches the pattern, a
+    /// [`InterfacePattern::Mac`] or [`InterfacePattern::Index`] never matches
+    #[must_use]
+    pub fn matches(&self, name: &[u8]) -> bool {
+        match self {
+            Self::Name(pattern) => pattern.as_bytes() == name,
+            Self::Glob(pattern) => glob_match(pattern.as_bytes(), name),
+            Self::Regex(regex) => regex.is_match(name),
+            Self::Mac(_) | Self::Index(_) => false,
+        }
+    }
+
+    /// Checks if `ifaddr`'s interface matches the pattern
+    ///
+    /// Besides the name, its alternative names and the names udev generated
+    /// for it are tried. The udev database is only read if needed and at most
+    /// once per interface of a [`crate::ifaddrs::getifaddrs`] or
+    /// [`crate::netlink::dump`] snapshot.
+    #[must_use]
+    pub fn matches_interface<'a, T>(&self, ifaddr: T) -> bool
+    where
+        T: InterfaceAddress<'a>,
+    {
+        match self {
+            Self::Mac(address) => ifaddr
+                .hardware_address()
+                .is_some_and(|hardware_address| *hardware_address == **address),
+            Self::Index(index) => ifaddr.index() == Some(*index),
+            _ => {
+                self.matches(ifaddr.name())
+                    || ifaddr.alt_names().iter().any(|name| self.matches(name))
+                    || ifaddr.udev_names().iter().any(|name| self.matches(name))
+            }
+        }
+    }
+
+    /// The name of a [`InterfacePattern::Name`] pattern, which matches at
+    /// most one interface
+    #[must_use]
+    pub fn name(&self) -> Option<&str> {
+        match self {
+            Self::Name(name) => Some(name),
+            _ => None,
+        }
+    }
+}
+
+/// A name without glob characters, used as is
+impl From<&str> for InterfacePattern {
+    fn from(name: &str) -> Self {
+        Self::Name(name.into())
+    }
+}
+
+impl FromStr for InterfacePattern {
+    type Err = ParseInterfacePatternError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(regex) = s.strip_prefix("re:") {
+            return Ok(Self::Regex(Regex::new(regex)?));
+        }
+        if let Some(address) = s.strip_prefix("mac:") {
+            return parse_hardware_address(address)
+                .map(|address| Self::Mac(address.into()))
+                .ok_or(ParseInterfacePatternError::InvalidMac);
+        }
+        if let Some(index) = s.strip_prefix("index:") {
+            return match index.parse() {
+                Ok(index) if index != 0 => Ok(Self::Index(index)),
+                _ => Err(ParseInterfacePatternError::InvalidIndex),
+            };
+        }
+
+        if s.is_empty() {
+            Err(ParseInterfacePatternError::Empty)
+        } else if s.contains(['*', '?', '[']) {
+            Ok(Self::Glob(s.into()))
+        } else {
+            Ok(Self::Name(s.into()))
+        }
+    }
+}
+
+impl fmt::Display for InterfacePattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Name(pattern) | Self::Glob(pattern) => f.write_str(pattern),
+            Self::Regex(regex) => write!(f, "re:{}", regex.source),
+            Self::Mac(address) => {
+                f.write_str("mac:")?;
+                for (i, byte) in address.iter().enumerate() {
+                    if i != 0 {
+                        f.write_str(":")?;
+                    }
+                    write!(f, "{byte:02x}")?;
+                }
+                Ok(())
+            }
+            Self::Index(index) => write!(f, "index:{index}"),
+        }
+    }
+}
+
+/// Parses a hardware address of hexadecimal bytes separated by `:` or `-`,
+/// e.g. `02:fc:00:00:00:01`
+pub(crate) fn parse_hardware_address(s: &str) -> Option<Vec<u8>> {
+    s.split([':', '-'])
+        .map(|byte| match byte.len() {
+            1 | 2 if byte.bytes().all(|c| c.is_ascii_hexdigit()) => {
+                u8::from_str_radix(byte, 16).ok()
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// Error returned when parsing an invalid [`InterfacePattern`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseInterfacePatternError {
+    Empty,
+    /// The regex doesn't compile, with the message of `regerror(3)`
+    InvalidRegex(Box<str>),
+    InvalidMac,
+    /// Not a number or `0`, which is never an interface index
+    InvalidIndex,
+}
+
+impl fmt::Display for ParseInterfacePatternError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Empty => f.write_str("interface pattern is empty"),
+            Self::InvalidRegex(err) => write!(f, "invalid regex: {err}"),
+            Self::InvalidMac => f.write_str("invalid hardware address"),
+            Self::InvalidIndex => f.write_str("invalid interface index"),
+        }
+    }
+}
+
+impl std::error::Error for ParseInterfacePatternError {}
+
+/// POSIX extended regex compiled with `regcomp(3)`, anchored to match the
+/// whole name
+///
+/// Clones share the compiled regex.
+#[derive(Clone)]
+pub struct Regex {
+    source: Box<str>,
+    regex: Arc<Compiled>,
+}
+
+/// Regex compiled by `regcomp`, behind an [`Arc`] so it never moves
+struct Compiled(libc::regex_t);
+
+// SAFETY: `regexec` doesn't modify the compiled regex and is thread-safe
+unsafe impl Send for Compiled {}
+unsafe impl Sync for Compiled {}
+
+impl Regex {
+    /// Compiles `source`
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if `source` is empty or isn't a valid extended
+    /// regex.
+    pub fn new(source: &str) -> Result<Self, ParseInterfacePatternError> {
+        if source.is_empty() {
+            return Err(ParseInterfacePatternError::Empty);
+        }
+        let anchored = CString::new(format!("^({source})$")).map_err(|_| {
+            ParseInterfacePatternError::InvalidRegex("contains a NUL".into())
+        })?;
+
+        // SAFETY: An all zero `regex_t` is valid input for `regcomp`
+        let mut regex: libc::regex_t = unsafe { std::mem::zeroed() };
+        // SAFETY: `regex` and `anchored` are valid for the call
+        let ret = unsafe {
+            libc::regcomp(
+                &mut regex,
+                anchored.as_ptr(),
+                libc::REG_EXTENDED | libc::REG_NOSUB,
+            )
+        };
+        if ret != 0 {
+            return Err(ParseInterfacePatternError::InvalidRegex(
+                regerror(ret, &regex).into(),
+            ));
+        }
+
+        Ok(Self {
+            source: source.into(),
+            regex: Arc::new(Compiled(regex)),
+        })
+    }
+
+    /// Checks if the regex matches the whole `name`
+    #[must_use]
+    pub fn is_match(&self, name: &[u8]) -> bool {
+        let Ok(name) = CString::new(name) else {
+            return false;
+        };
+
+        // SAFETY: `self.regex` was compiled by `regcomp` and `name` is a
+        // valid C string
+        unsafe {
+            libc::regexec(&self.regex.0, name.as_ptr(), 0, ptr::null_mut(), 0)
+                == 0
+        }
+    }
+}
+
+/// Message of a `regcomp` error
+fn regerror(code: libc::c_int, regex: &libc::regex_t) -> String {
+    let mut buffer = [0 as libc::c_char; 128];
+    // SAFETY: `buffer` is valid for `buffer.len()` bytes, `regerror` always
+    // terminates the message
+    unsafe {
+        libc::regerror(code, regex, buffer.as_mut_ptr(), buffer.len());
+        CStr::from_ptr(buffer.as_ptr())
+            .to_string_lossy()
+            .into_owned()
+    }
+}
+
+impl Drop for Compiled {
+    fn drop(&mut self) {
+        // SAFETY: The regex was successfully compiled by `regcomp`
+        unsafe { libc::regfree(&mut self.0) };
+    }
+}
+
+impl fmt::Debug for Regex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Regex").field(&self.source).finish()
+    }
+}
+
+impl PartialEq for Regex {
+    fn eq(&self, other: &Self) -> bool {
+        self.source == other.source
+    }
+}
+
+impl Eq for Regex {}
+
+extern "C" {
+    // Part of glibc and musl, but not bound by the `libc` crate
+    fn fnmatch(
+        pattern: *const libc::c_char,
+        name: *const libc::c_char,
+        flags: libc::c_int,
+    ) -> libc::c_int;
+}
+
+/// Matches `name` against the shell glob `pattern` with `fnmatch(3)`
+fn glob_match(pattern: &[u8], name: &[u8]) -> bool {
+    let (Ok(pattern), Ok(name)) = (CString::new(pattern), CString::new(name))
+    else {
+        return false;
+    };
+
+    // SAFETY: `pattern` and `name` are valid C strings
+    unsafe { fnmatch(pattern.as_ptr(), name.as_ptr(), 0) == 0 }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn glob() {
+        for (pattern, name) in [
+            ("eth0", "eth0"),
+            ("en*", "enp3s0"),
+            ("en*", "en"),
+            ("wl?0", "wlp0"),
+            ("*", "veth1a2b3c"),
+            ("veth*c", "veth1a2b3c"),
+            ("*a*b*", "veth1a2b3c"),
+            ("eth[0-3]", "eth2"),
+            ("eth[!0-3]", "eth4"),
+            ("eth[^0]", "eth1"),
+            ("br[]x]", "br]"),
+            ("br[", "br["),
+            ("eth\\*", "eth*"),
+            ("eth[[:digit:]]", "eth0"),
+            ("[a-c]*[!a-z]", "br0"),
+        ] {
+            assert!(
+                glob_match(pattern.as_bytes(), name.as_bytes()),
+                "{pattern}"
+            );
+        }
+
+        for (pattern, name) in [
+            ("eth0", "eth1"),
+            ("en*", "eth0"),
+            ("wl?0", "wlp10"),
+            ("wl?0", "wl0"),
+            ("veth*c", "veth1a2b3"),
+            ("eth[0-3]", "eth4"),
+            ("eth[!0-3]", "eth0"),
+            ("eth\\*", "eth0"),
+            ("eth[[:digit:]]", "ethx"),
+        ] {
+            assert!(
+                !glob_match(pattern.as_bytes(), name.as_bytes()),
+                "{pattern}"
+            );
+        }
+    }
+
+    #[test]
+    fn parse() {
+        assert_eq!("eth0".parse(), Ok(InterfacePattern::Name("eth0".into())));
+        assert_eq!("en*".parse(), Ok(InterfacePattern::Glob("en*".into())));
+        assert_eq!(
+            "".parse::<InterfacePattern>(),
+            Err(ParseInterfacePatternError::Empty)
+        );
+        assert_eq!(
+            "re:".parse::<InterfacePattern>(),
+            Err(ParseInterfacePatternError::Empty)
+        );
+        assert!(matches!(
+            "re:veth(".parse::<InterfacePattern>(),
+            Err(ParseInterfacePatternError::InvalidRegex(_))
+        ));
+
+        assert_eq!(
+            "mac:02:FC:0:00:00:01".parse(),
+            Ok(InterfacePattern::Mac([0x02, 0xfc, 0, 0, 0, 0x01].into()))
+        );
+        assert_eq!(
+            "mac:02-fc-00-00-00-01".parse::<InterfacePattern>(),
+            "mac:02:fc:00:00:00:01".parse()
+        );
+        for pattern in ["mac:", "mac:02:fc:", "mac:020:fc", "mac:+2:fc"] {
+            assert_eq!(
+                pattern.parse::<InterfacePattern>(),
+                Err(ParseInterfacePatternError::InvalidMac),
+                "{pattern}"
+            );
+        }
+        assert_eq!("index:3".parse(), Ok(InterfacePattern::Index(3)));
+        for pattern in ["index:", "index:0", "index:eth0"] {
+            assert_eq!(
+                pattern.parse::<InterfacePattern>(),
+                Err(ParseInterfacePatternError::InvalidIndex),
+                "{pattern}"
+            );
+        }
+
+        for pattern in [
+            "eth0",
+            "wl?0",
+            "re:veth[0-9a-f]+",
+            "mac:02:fc:00:00:00:01",
+            "index:3",
+        ] {
+            assert_eq!(
+                pattern.parse::<InterfacePattern>().unwrap().to_string(),
+                pattern
+            );
+        }
+    }
+
+    #[test]
+    fn regex() {
+        let pattern: InterfacePattern =
+            "re:veth[0-9a-f]+|docker0".parse().unwrap();
+
+        assert!(pattern.matches(b"veth1a2b3c"));
+        assert!(pattern.matches(b"docker0"));
+        // Anchored
+        assert!(!pattern.matches(b"xveth1"));
+        assert!(!pattern.matches(b"veth1x"));
+        assert!(!pattern.matches(b"docker01"));
+        assert!(!pattern.matches(b"vet\0h1"));
+
+        let clone = pattern.clone();
+        drop(pattern);
+        assert!(clone.matches(b"veth0"));
+    }
+}
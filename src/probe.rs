@@ -0,0 +1,615 @@
+use std::{
+    cmp::min,
+    fmt, io,
+    num::NonZeroU16,
+    str::FromStr,
+    time::{Duration, Instant},
+};
+
+use crate::arguments::Args;
+
+mod dns;
+mod http;
+mod icmp;
+mod tcp;
+
+/// Longest a single probe attempt may take, so a lost packet doesn't use up
+/// the whole `--timeout`
+pub const ATTEMPT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Time an attempt ending at `deadline` or after [`ATTEMPT_TIMEOUT`] may
+/// take, `None` if `deadline` passed
+pub(crate) fn attempt_timeout(deadline: Option<Instant>) -> Option<Duration> {
+    match deadline {
+        Some(deadline) => deadline
+            .checked_duration_since(Instant::now())
+            .map(|time_to_deadline| min(time_to_deadline, ATTEMPT_TIMEOUT)),
+        None => Some(ATTEMPT_TIMEOUT),
+    }
+}
+
+/// Port of `http://` probes without an explicit port
+const HTTP_PORT: u16 = 80;
+/// Port of `dns://` probes without an explicit port
+const DNS_PORT: u16 = 53;
+/// Host of `icmp://` probes that pings the gateway of a default route instead
+const GATEWAY: &str = "gateway";
+
+/// Reachability probe, parsed from an URL like `tcp://example.com:443`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Probe {
+    /// Completes a TCP handshake with `host` on `port`
+    Tcp { host: Box<str>, port: u16 },
+    /// Sends `count` ICMP echo requests to `host`, at most `max_loss` percent
+    /// of them may be lost
+    ///
+    /// Parsed from `icmp://HOST[?count=COUNT][&max-loss=PERCENT]`, by default
+    /// a single echo request has to be answered. The host `gateway` is the
+    /// gateway of a usable default route, looked up on every attempt.
+    Icmp {
+        host: Box<str>,
+        count: NonZeroU16,
+        max_loss: u8,
+    },
+    /// Sends an HTTP/1.1 `GET path` request to `host` on `port`, the response
+    /// has to match the [`HttpExpectation`]
+    ///
+    /// Parsed from `http://HOST[:PORT][/PATH]`, the query string is part of
+    /// the path.
+    Http {
+        host: Box<str>,
+        port: u16,
+        path: Box<str>,
+    },
+    /// Sends a DNS query for `name` over UDP to `server` on `port`, any
+    /// well-formed `NOERROR` answer passes
+    ///
+    /// Parsed from `dns://SERVER[:PORT]/NAME`.
+    Dns {
+        server: Box<str>,
+        port: u16,
+        name: Box<str>,
+    },
+}
+
+/// Result of a single probe attempt
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProbeStatus {
+    Online,
+    Offline,
+    /// An HTTP probe got an unexpected response, e.g. a redirect to a login
+    /// page
+    CaptivePortal,
+}
+
+/// Response `http://` probes have to get
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct HttpExpectation<'a> {
+    /// Required status code, any `2xx` status if `None`
+    pub status: Option<u16>,
+    /// Required start of the body
+    pub body: Option<&'a str>,
+}
+
+impl<'a> From<&'a Args> for HttpExpectation<'a> {
+    fn from(args: &'a Args) -> Self {
+        Self {
+            status: args.expect_status,
+            body: args.expect_body.as_deref(),
+        }
+    }
+}
+
+impl Probe {
+    /// Checks if the probe can succeed at all, before waiting for it
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the probe can never succeed, e.g. because ping
+    /// sockets aren't allowed.
+    pub fn check(&self) -> Result<(), io::Error> {
+        match self {
+            Self::Icmp { host, .. } => icmp::check_allowed(host),
+            Self::Tcp { .. } | Self::Http { .. } | Self::Dns { .. } => Ok(()),
+        }
+    }
+
+    /// Runs the probe once, an attempt ends at `deadline` or after
+    /// [`ATTEMPT_TIMEOUT`], whichever comes first.
+    ///
+    /// Failures (e.g. `host` can't be resolved yet) are expected while
+    /// waiting and reported as [`ProbeStatus::Offline`], including ones
+    /// [`Probe::check`] reports up front.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if waiting for a reply fails unexpectedly.
+    pub fn run(
+        &self,
+        deadline: Option<Instant>,
+        expectation: HttpExpectation<'_>,
+    ) -> Result<ProbeStatus, io::Error> {
+        let Some(timeout) = attempt_timeout(deadline) else {
+            return Ok(ProbeStatus::Offline);
+        };
+
+        match self {
+            Self::Tcp { host, port } => {
+                Ok(tcp::connect(host, *port, timeout).into())
+            }
+            Self::Icmp {
+                host,
+                count,
+                max_loss,
+            } => icmp::ping(host, count.get(), *max_loss, timeout)
+                .map(ProbeStatus::from),
+            Self::Http { host, port, path } => {
+                Ok(http::get(host, *port, path, expectation, timeout))
+            }
+            Self::Dns { server, port, name } => {
+                Ok(dns::query(server, *port, name, timeout).into())
+            }
+        }
+    }
+}
+
+impl fmt::Display for Probe {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Tcp { host, port } => {
+                write!(f, "tcp://{}:{port}", Authority(host))
+            }
+            Self::Icmp {
+                host,
+                count,
+                max_loss,
+            } => {
+                write!(
+                    f,
+                    "icmp://{}?count={count}&max-loss={max_loss}",
+                    Authority(host)
+                )
+            }
+            Self::Http { host, port, path } => {
+                write!(f, "http://{}", Authority(host))?;
+                if *port != HTTP_PORT {
+                    write!(f, ":{port}")?;
+                }
+                f.write_str(path)
+            }
+            Self::Dns { server, port, name } => {
+                write!(f, "dns://{}", Authority(server))?;
+                if *port != DNS_PORT {
+                    write!(f, ":{port}")?;
+                }
+                write!(f, "/{name}")
+            }
+        }
+    }
+}
+
+impl From<bool> for ProbeStatus {
+    fn from(online: bool) -> Self {
+        if online {
+            Self::Online
+        } else {
+            Self::Offline
+        }
+    }
+}
+
+impl FromStr for Probe {
+    type Err = ParseProbeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (scheme, rest) =
+            s.split_once("://").ok_or(ParseProbeError::MissingScheme)?;
+        if scheme == "http" {
+            // The query string belongs to the path
+            let (authority, path) = match rest.find(['/', '?']) {
+                Some(i) if rest[i..].starts_with('?') => {
+                    (&rest[..i], format!("/{}", &rest[i..]).into())
+                }
+                Some(i) => (&rest[..i], rest[i..].into()),
+                None => (rest, "/".into()),
+            };
+            let (host, port) = split_host_port(authority)?;
+            return Ok(Self::Http {
+                host: host.into(),
+                port: port.unwrap_or(HTTP_PORT),
+                path,
+            });
+        }
+
+        let (authority, query) = match rest.split_once('?') {
+            Some((authority, query)) => (authority, Some(query)),
+            None => (rest, None),
+        };
+        let (authority, path) = match authority.split_once('/') {
+            Some((authority, path)) => (authority, Some(path)),
+            None => (authority, None),
+        };
+        let (host, port) = split_host_port(authority)?;
+        let mut parameters = Parameters::new(query)?;
+
+        if scheme == "dns" {
+            let name = path.filter(|name| !name.is_empty());
+            let name = name.ok_or(ParseProbeError::MissingName)?;
+            if dns::encode_name(name).is_none() {
+                return Err(ParseProbeError::InvalidName(name.into()));
+            }
+            parameters.finish()?;
+            return Ok(Self::Dns {
+                server: host.into(),
+                port: port.unwrap_or(DNS_PORT),
+                name: name.into(),
+            });
+        }
+        if path.is_some() {
+            return Err(ParseProbeError::UnexpectedPath);
+        }
+
+        let probe = match scheme {
+            "tcp" => Self::Tcp {
+                host: host.into(),
+                port: port.ok_or(ParseProbeError::MissingPort)?,
+            },
+            "icmp" => {
+                if port.is_some() {
+                    return Err(ParseProbeError::UnexpectedPort);
+                }
+                let max_loss = parameters.take("max-loss")?.unwrap_or(0);
+                if max_loss > 100 {
+                    return Err(ParseProbeError::InvalidParameter(
+                        "max-loss".into(),
+                    ));
+                }
+                Self::Icmp {
+                    host: host.into(),
+                    count: parameters.take("count")?.unwrap_or(NonZeroU16::MIN),
+                    max_loss,
+                }
+            }
+            _ => return Err(ParseProbeError::UnknownScheme(scheme.into())),
+        };
+        parameters.finish()?;
+
+        Ok(probe)
+    }
+}
+
+/// Error returned when parsing an invalid [`Probe`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseProbeError {
+    MissingScheme,
+    UnknownScheme(Box<str>),
+    MissingHost,
+    MissingPort,
+    UnexpectedPort,
+    InvalidPort(Box<str>),
+    UnexpectedPath,
+    MissingName,
+    InvalidName(Box<str>),
+    UnknownParameter(Box<str>),
+    InvalidParameter(Box<str>),
+}
+
+impl fmt::Display for ParseProbeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingScheme => {
+                f.write_str("missing scheme, expected e.g. `tcp://`")
+            }
+            Self::UnknownScheme(scheme) => {
+                write!(
+                    f,
+                    "unknown scheme `{scheme}`, expected tcp, icmp, http or dns"
+                )
+            }
+            Self::MissingHost => f.write_str("missing host"),
+            Self::MissingPort => f.write_str("missing port"),
+            Self::UnexpectedPort => f.write_str("unexpected port"),
+            Self::InvalidPort(port) => write!(f, "invalid port `{port}`"),
+            Self::UnexpectedPath => f.write_str("unexpected path"),
+            Self::MissingName => f.write_str("missing name to query"),
+            Self::InvalidName(name) => write!(f, "invalid name `{name}`"),
+            Self::UnknownParameter(name) => {
+                write!(f, "unknown parameter `{name}`")
+            }
+            Self::InvalidParameter(name) => {
+                write!(f, "invalid value for parameter `{name}`")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ParseProbeError {}
+
+/// Splits `HOST[:PORT]`, an IPv6 address has to be enclosed in brackets when
+/// followed by a port.
+fn split_host_port(
+    authority: &str,
+) -> Result<(&str, Option<u16>), ParseProbeError> {
+    let (host, port) = if let Some(rest) = authority.strip_prefix('[') {
+        let (host, rest) =
+            rest.split_once(']').ok_or(ParseProbeError::MissingHost)?;
+        match rest {
+            "" => (host, None),
+            _ => (
+                host,
+                Some(rest.strip_prefix(':').ok_or_else(|| {
+                    ParseProbeError::InvalidPort(rest.into())
+                })?),
+            ),
+        }
+    } else {
+        match authority.rsplit_once(':') {
+            // An IPv6 address without brackets
+            Some((host, _)) if host.contains(':') => (authority, None),
+            Some((host, port)) => (host, Some(port)),
+            None => (authority, None),
+        }
+    };
+
+    if host.is_empty() {
+        return Err(ParseProbeError::MissingHost);
+    }
+    let port = port
+        .map(|port| {
+            port.parse()
+                .map_err(|_| ParseProbeError::InvalidPort(port.into()))
+        })
+        .transpose()?;
+
+    Ok((host, port))
+}
+
+/// Query parameters (`NAME=VALUE&...`) of a probe URL
+///
+/// Every parameter has to be taken, [`Parameters::finish`] fails on the
+/// remaining unknown parameters.
+struct Parameters<'a> {
+    parameters: Vec<(&'a str, &'a str)>,
+}
+
+impl<'a> Parameters<'a> {
+    fn new(query: Option<&'a str>) -> Result<Self, ParseProbeError> {
+        let parameters = query
+            .into_iter()
+            .flat_map(|query| query.split('&'))
+            .map(|parameter| {
+                parameter.split_once('=').ok_or_else(|| {
+                    ParseProbeError::InvalidParameter(parameter.into())
+                })
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self { parameters })
+    }
+
+    /// Removes and parses the parameter `name`, if given
+    fn take<T: FromStr>(
+        &mut self,
+        name: &str,
+    ) -> Result<Option<T>, ParseProbeError> {
+        let Some(position) =
+            self.parameters.iter().position(|(key, _)| *key == name)
+        else {
+            return Ok(None);
+        };
+        let (_, value) = self.parameters.remove(position);
+
+        value
+            .parse()
+            .map(Some)
+            .map_err(|_| ParseProbeError::InvalidParameter(name.into()))
+    }
+
+    fn finish(self) -> Result<(), ParseProbeError> {
+        match self.parameters.first() {
+            Some((name, _)) => {
+                Err(ParseProbeError::UnknownParameter((*name).into()))
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+/// Displays a host, enclosing IPv6 addresses in brackets
+struct Authority<'a>(&'a str);
+
+impl fmt::Display for Authority<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0.contains(':') {
+            write!(f, "[{}]", self.0)
+        } else {
+            f.write_str(self.0)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn host_port() {
+        assert_eq!(split_host_port("example.com"), Ok(("example.com", None)));
+        assert_eq!(
+            split_host_port("192.0.2.1:80"),
+            Ok(("192.0.2.1", Some(80)))
+        );
+        assert_eq!(split_host_port("2001:db8::1"), Ok(("2001:db8::1", None)));
+        assert_eq!(split_host_port("[2001:db8::1]"), Ok(("2001:db8::1", None)));
+        assert_eq!(
+            split_host_port("[2001:db8::1]:443"),
+            Ok(("2001:db8::1", Some(443)))
+        );
+        assert_eq!(split_host_port(""), Err(ParseProbeError::MissingHost));
+        assert_eq!(split_host_port(":80"), Err(ParseProbeError::MissingHost));
+        assert_eq!(
+            split_host_port("[2001:db8::1"),
+            Err(ParseProbeError::MissingHost)
+        );
+        assert_eq!(
+            split_host_port("example.com:http"),
+            Err(ParseProbeError::InvalidPort("http".into()))
+        );
+        assert_eq!(
+            split_host_port("[::1]80"),
+            Err(ParseProbeError::InvalidPort("80".into()))
+        );
+    }
+
+    #[test]
+    fn parse() {
+        assert_eq!(
+            "tcp://example.com:443".parse(),
+            Ok(Probe::Tcp {
+                host: "example.com".into(),
+                port: 443
+            })
+        );
+        assert_eq!(
+            "tcp://example.com".parse::<Probe>(),
+            Err(ParseProbeError::MissingPort)
+        );
+        assert_eq!(
+            "example.com:443".parse::<Probe>(),
+            Err(ParseProbeError::MissingScheme)
+        );
+        assert_eq!(
+            "udp://example.com:443".parse::<Probe>(),
+            Err(ParseProbeError::UnknownScheme("udp".into()))
+        );
+
+        assert_eq!(
+            "tcp://example.com:443?count=1".parse::<Probe>(),
+            Err(ParseProbeError::UnknownParameter("count".into()))
+        );
+
+        for probe in [
+            "tcp://192.0.2.1:22",
+            "tcp://[2001:db8::1]:443",
+            "icmp://192.0.2.1?count=1&max-loss=0",
+            "icmp://[2001:db8::1]?count=5&max-loss=20",
+            "http://example.com/generate_204",
+            "http://[2001:db8::1]:8080/check?id=1",
+            "dns://192.0.2.53/example.com",
+            "dns://[2001:db8::53]:5353/example.com.",
+        ] {
+            assert_eq!(
+                probe.parse::<Probe>().map(|p| p.to_string()),
+                Ok(probe.into())
+            );
+        }
+    }
+
+    #[test]
+    fn parse_icmp() {
+        assert_eq!(
+            "icmp://192.0.2.1".parse(),
+            Ok(Probe::Icmp {
+                host: "192.0.2.1".into(),
+                count: NonZeroU16::MIN,
+                max_loss: 0,
+            })
+        );
+        assert_eq!(
+            "icmp://gateway?max-loss=50&count=4".parse(),
+            Ok(Probe::Icmp {
+                host: "gateway".into(),
+                count: NonZeroU16::new(4).unwrap(),
+                max_loss: 50,
+            })
+        );
+        assert_eq!(
+            "icmp://192.0.2.1:80".parse::<Probe>(),
+            Err(ParseProbeError::UnexpectedPort)
+        );
+        for invalid in ["count=0", "count=-1", "max-loss=101", "count"] {
+            assert!(matches!(
+                format!("icmp://192.0.2.1?{invalid}").parse::<Probe>(),
+                Err(ParseProbeError::InvalidParameter(_))
+            ));
+        }
+        assert_eq!(
+            "icmp://192.0.2.1?interval=1".parse::<Probe>(),
+            Err(ParseProbeError::UnknownParameter("interval".into()))
+        );
+    }
+
+    #[test]
+    fn parse_http() {
+        let http = |host: &str, port, path: &str| Probe::Http {
+            host: host.into(),
+            port,
+            path: path.into(),
+        };
+
+        assert_eq!(
+            "http://example.com".parse(),
+            Ok(http("example.com", 80, "/"))
+        );
+        assert_eq!(
+            "http://192.0.2.1:8080/generate_204".parse(),
+            Ok(http("192.0.2.1", 8080, "/generate_204"))
+        );
+        assert_eq!(
+            "http://example.com?count=1".parse(),
+            Ok(http("example.com", 80, "/?count=1"))
+        );
+        assert_eq!(
+            "http:///check".parse::<Probe>(),
+            Err(ParseProbeError::MissingHost)
+        );
+        assert_eq!(
+            "https://example.com".parse::<Probe>(),
+            Err(ParseProbeError::UnknownScheme("https".into()))
+        );
+    }
+
+    #[test]
+    fn parse_dns() {
+        assert_eq!(
+            "dns://192.0.2.53/example.com".parse(),
+            Ok(Probe::Dns {
+                server: "192.0.2.53".into(),
+                port: 53,
+                name: "example.com".into(),
+            })
+        );
+        for missing in ["dns://192.0.2.53", "dns://192.0.2.53/"] {
+            assert_eq!(
+                missing.parse::<Probe>(),
+                Err(ParseProbeError::MissingName)
+            );
+        }
+        assert_eq!(
+            "dns://192.0.2.53/example..com".parse::<Probe>(),
+            Err(ParseProbeError::InvalidName("example..com".into()))
+        );
+        assert_eq!(
+            "dns://192.0.2.53/example.com?type=AAAA".parse::<Probe>(),
+            Err(ParseProbeError::UnknownParameter("type".into()))
+        );
+        assert_eq!(
+            "tcp://example.com:443/".parse::<Probe>(),
+            Err(ParseProbeError::UnexpectedPath)
+        );
+    }
+
+    #[test]
+    fn deadline_passed() {
+        let probe = Probe::Tcp {
+            host: "127.0.0.1".into(),
+            port: 1,
+        };
+        let deadline = Instant::now().checked_sub(Duration::from_millis(1));
+
+        assert_eq!(
+            probe.run(deadline, HttpExpectation::default()).unwrap(),
+            ProbeStatus::Offline
+        );
+    }
+}
@@ -0,0 +1,267 @@
+use std::{
+    net::{SocketAddr, UdpSocket},
+    process,
+    time::{Duration, Instant, SystemTime},
+};
+
+use crate::dns::resolve;
+
+/// Size of the DNS message header
+const HEADER_LEN: usize = 12;
+
+/// `QR` bit of the header flags, set in responses
+const FLAG_RESPONSE: u16 = 0x8000;
+/// `RD` bit of the header flags, asks the server to recurse
+const FLAG_RECURSION_DESIRED: u16 = 0x0100;
+const OPCODE_MASK: u16 = 0x7800;
+const RCODE_MASK: u16 = 0x000f;
+const RCODE_NOERROR: u16 = 0;
+
+const TYPE_A: u16 = 1;
+const CLASS_IN: u16 = 1;
+
+/// Longest label and name, in octets of the wire format
+const MAX_LABEL_LEN: usize = 63;
+const MAX_NAME_LEN: usize = 255;
+
+/// Sends an `A` query for `name` to `server` on `port` within `timeout`
+///
+/// Returns `true` once a well-formed `NOERROR` answer to the query is
+/// received, the answer doesn't have to contain any records.
+pub(super) fn query(
+    server: &str,
+    port: u16,
+    name: &str,
+    timeout: Duration,
+) -> bool {
+    let deadline = Instant::now() + timeout;
+    let Some(query) = build_query(query_id(), name) else {
+        return false;
+    };
+    let Some(addresses) = resolve(server, port, timeout) else {
+        return false;
+    };
+
+    addresses
+        .into_iter()
+        .any(|address| exchange(address, &query, deadline).unwrap_or(false))
+}
+
+/// Sends `query` to `address` and waits until `deadline` for the answer
+fn exchange(
+    address: SocketAddr,
+    query: &[u8],
+    deadline: Instant,
+) -> Option<bool> {
+    let local: SocketAddr = match address {
+        SocketAddr::V4(_) => ([0; 4], 0).into(),
+        SocketAddr::V6(_) => ([0; 16], 0).into(),
+    };
+    let socket = UdpSocket::bind(local).ok()?;
+    socket.connect(address).ok()?;
+    socket.send(query).ok()?;
+
+    let mut buffer = [0; 512];
+    loop {
+        let timeout = deadline
+            .checked_duration_since(Instant::now())
+            .filter(|timeout| !timeout.is_zero())?;
+        socket.set_read_timeout(Some(timeout)).ok()?;
+
+        // Anything else, like a late answer to an earlier query, is skipped
+        let len = socket.recv(&mut buffer).ok()?;
+        if is_answer(&buffer[..len], query) {
+            return Some(true);
+        }
+    }
+}
+
+/// Picks a query ID that differs between runs and attempts
+fn query_id() -> u16 {
+    let nanos = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0, |time| time.subsec_nanos());
+    // Truncating is fine, only the low bits have to vary
+    #[allow(clippy::cast_possible_truncation)]
+    let id = (nanos ^ process::id().rotate_left(16)) as u16;
+    id
+}
+
+/// Encodes `name` as a sequence of length-prefixed labels
+///
+/// Returns `None` if `name` isn't a valid domain name, a trailing dot is
+/// optional.
+pub(super) fn encode_name(name: &str) -> Option<Vec<u8>> {
+    let name = name.strip_suffix('.').unwrap_or(name);
+    if name.is_empty() {
+        return None;
+    }
+
+    let mut encoded = Vec::with_capacity(name.len() + 2);
+    for label in name.split('.') {
+        if label.is_empty() || label.len() > MAX_LABEL_LEN {
+            return None;
+        }
+        // Checked above
+        #[allow(clippy::cast_possible_truncation)]
+        encoded.push(label.len() as u8);
+        encoded.extend_from_slice(label.as_bytes());
+    }
+    encoded.push(0);
+
+    (encoded.len() <= MAX_NAME_LEN).then_some(encoded)
+}
+
+/// Builds a recursive `A IN` query for `name`
+fn build_query(id: u16, name: &str) -> Option<Vec<u8>> {
+    let mut query = Vec::with_capacity(HEADER_LEN + name.len() + 6);
+    query.extend(id.to_be_bytes());
+    query.extend(FLAG_RECURSION_DESIRED.to_be_bytes());
+    // QDCOUNT, ANCOUNT, NSCOUNT, ARCOUNT
+    query.extend([0, 1, 0, 0, 0, 0, 0, 0]);
+    query.extend(encode_name(name)?);
+    query.extend(TYPE_A.to_be_bytes());
+    query.extend(CLASS_IN.to_be_bytes());
+
+    Some(query)
+}
+
+/// Checks if `response` is a `NOERROR` answer to `query`
+///
+/// The ID and the question have to match the query.
+fn is_answer(response: &[u8], query: &[u8]) -> bool {
+    if response.len() < query.len() {
+        return false;
+    }
+    let flags = u16::from_be_bytes([response[2], response[3]]);
+
+    response[..2] == query[..2]
+        && flags & FLAG_RESPONSE != 0
+        && flags & OPCODE_MASK == 0
+        && flags & RCODE_MASK == RCODE_NOERROR
+        // QDCOUNT
+        && response[4..6] == query[4..6]
+        // The question, names are compared case-insensitively
+        && response[HEADER_LEN..query.len()]
+            .eq_ignore_ascii_case(&query[HEADER_LEN..])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::thread;
+
+    /// Answers a single query with `rcode`, returning the query
+    fn respond(rcode: u8) -> (u16, thread::JoinHandle<Vec<u8>>) {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let port = socket.local_addr().unwrap().port();
+
+        let responder = thread::spawn(move || {
+            let mut buffer = [0; 512];
+            let (len, peer) = socket.recv_from(&mut buffer).unwrap();
+            let query = buffer[..len].to_vec();
+
+            let mut response = query.clone();
+            response[2] |= 0x80;
+            response[3] = 0x80 | rcode;
+            socket.send_to(&response, peer).unwrap();
+            query
+        });
+
+        (port, responder)
+    }
+
+    #[test]
+    fn name() {
+        assert_eq!(
+            encode_name("example.com"),
+            Some(b"\x07example\x03com\x00".to_vec())
+        );
+        assert_eq!(encode_name("example.com."), encode_name("example.com"));
+        assert_eq!(encode_name(""), None);
+        assert_eq!(encode_name("."), None);
+        assert_eq!(encode_name("example..com"), None);
+        assert_eq!(encode_name(&"a".repeat(64)), None);
+        assert!(encode_name(&"a".repeat(63)).is_some());
+        assert_eq!(encode_name(&["a"; 128].join(".")), None);
+    }
+
+    #[test]
+    fn build() {
+        assert_eq!(
+            build_query(0x1234, "example.com").unwrap(),
+            b"\x12\x34\x01\x00\x00\x01\x00\x00\x00\x00\x00\x00\
+              \x07example\x03com\x00\x00\x01\x00\x01"
+        );
+    }
+
+    #[test]
+    fn answer() {
+        let query = build_query(0x1234, "example.com").unwrap();
+        let mut response = query.clone();
+        response[2] = 0x81;
+        response[3] = 0x80;
+        // An answer record
+        response.extend_from_slice(b"\xc0\x0c\x00\x01\x00\x01");
+
+        assert!(is_answer(&response, &query));
+        assert!(!is_answer(&query, &query));
+        assert!(!is_answer(&response[..HEADER_LEN], &query));
+
+        let mut nxdomain = response.clone();
+        nxdomain[3] |= 3;
+        assert!(!is_answer(&nxdomain, &query));
+
+        let mut other_id = response.clone();
+        other_id[1] = 0;
+        assert!(!is_answer(&other_id, &query));
+
+        let mut upper = response.clone();
+        upper[HEADER_LEN + 1..HEADER_LEN + 8].make_ascii_uppercase();
+        assert!(is_answer(&upper, &query));
+
+        let mut other_name = response;
+        other_name[HEADER_LEN + 1] = b'x';
+        assert!(!is_answer(&other_name, &query));
+    }
+
+    #[test]
+    fn query_responder() {
+        let (port, responder) = respond(0);
+        assert!(query(
+            "127.0.0.1",
+            port,
+            "example.com",
+            Duration::from_secs(1)
+        ));
+        assert_eq!(
+            responder.join().unwrap()[HEADER_LEN..],
+            b"\x07example\x03com\x00\x00\x01\x00\x01"[..]
+        );
+
+        // SERVFAIL
+        let (port, responder) = respond(2);
+        assert!(!query(
+            "127.0.0.1",
+            port,
+            "example.com",
+            Duration::from_secs(1)
+        ));
+        responder.join().unwrap();
+    }
+
+    #[test]
+    fn query_timeout() {
+        // Never answers
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let port = socket.local_addr().unwrap().port();
+
+        assert!(!query(
+            "127.0.0.1",
+            port,
+            "example.com",
+            Duration::from_millis(100)
+        ));
+    }
+}
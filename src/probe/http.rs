@@ -0,0 +1,540 @@
+use std::{
+    io::{Read, Write},
+    net::TcpStream,
+    str,
+    time::{Duration, Instant},
+};
+
+use super::{Authority, HttpExpectation, ProbeStatus};
+use crate::dns;
+
+/// Responses are only read up to this size, a connectivity check answers
+/// with (almost) no body
+const MAX_RESPONSE_LEN: u64 = 64 * 1024;
+
+/// Sends `GET path` to `host` within `timeout` and compares the response to
+/// `expectation`
+///
+/// Any complete response that doesn't match, like a redirect to a login page,
+/// is a [`ProbeStatus::CaptivePortal`].
+pub(super) fn get(
+    host: &str,
+    port: u16,
+    path: &str,
+    expectation: HttpExpectation<'_>,
+    timeout: Duration,
+) -> ProbeStatus {
+    match fetch(host, port, path, timeout)
+        .and_then(|(response, closed)| parse_response(&response, closed))
+    {
+        Some(response) => response.status(expectation),
+        None => ProbeStatus::Offline,
+    }
+}
+
+/// Sends the request and reads the response until it's complete, the server
+/// closes the connection or `timeout` elapses
+///
+/// What was read before `timeout` is returned as well, together with whether
+/// the server closed the connection. [`parse_response`] decides if it's
+/// enough.
+fn fetch(
+    host: &str,
+    port: u16,
+    path: &str,
+    timeout: Duration,
+) -> Option<(Vec<u8>, bool)> {
+    let deadline = Instant::now() + timeout;
+    let remaining = || {
+        deadline
+            .checked_duration_since(Instant::now())
+            .filter(|timeout| !timeout.is_zero())
+    };
+
+    let mut stream = dns::resolve(host, port, remaining()?)?
+        .into_iter()
+        .find_map(|address| {
+            TcpStream::connect_timeout(&address, remaining()?).ok()
+        })?;
+
+    stream.set_write_timeout(remaining()).ok()?;
+    stream
+        .write_all(request(host, port, path).as_bytes())
+        .ok()?;
+
+    let mut response = Vec::new();
+    let mut buffer = [0; 4096];
+    let mut closed = false;
+    while (response.len() as u64) < MAX_RESPONSE_LEN && !is_complete(&response)
+    {
+        let Some(timeout) = remaining() else {
+            break;
+        };
+        stream.set_read_timeout(Some(timeout)).ok()?;
+        match stream.read(&mut buffer) {
+            Ok(0) => {
+                closed = true;
+                break;
+            }
+            Ok(len) => response.extend_from_slice(&buffer[..len]),
+            Err(_) => break,
+        }
+    }
+
+    Some((response, closed))
+}
+
+fn request(host: &str, port: u16, path: &str) -> String {
+    let host = Authority(host);
+    let host = if port == super::HTTP_PORT {
+        host.to_string()
+    } else {
+        format!("{host}:{port}")
+    };
+
+    format!(
+        "GET {path} HTTP/1.1\r\n\
+         Host: {host}\r\n\
+         User-Agent: wait-online\r\n\
+         Accept: */*\r\n\
+         Connection: close\r\n\
+         \r\n"
+    )
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct Response {
+    status: u16,
+    body: Vec<u8>,
+}
+
+impl Response {
+    fn status(&self, expectation: HttpExpectation<'_>) -> ProbeStatus {
+        let status_matches = match expectation.status {
+            Some(status) => self.status == status,
+            None => (200..300).contains(&self.status),
+        };
+        let body_matches = expectation
+            .body
+            .map_or(true, |body| self.body.starts_with(body.as_bytes()));
+
+        if status_matches && body_matches {
+            ProbeStatus::Online
+        } else {
+            ProbeStatus::CaptivePortal
+        }
+    }
+}
+
+/// How the end of a response body is determined
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Framing {
+    Length(usize),
+    Chunked,
+    /// The body ends when the server closes the connection
+    Close,
+}
+
+/// Status line and headers of a response
+struct Head {
+    status: u16,
+    framing: Framing,
+    /// Length of the head including the empty line ending it
+    len: usize,
+}
+
+/// Parses the head of an HTTP/1.x response
+///
+/// Returns `None` if the head is malformed or incomplete.
+fn parse_head(response: &[u8]) -> Option<Head> {
+    let header_len = response.windows(4).position(|w| w == b"\r\n\r\n")?;
+    let head = str::from_utf8(&response[..header_len]).ok()?;
+
+    let mut lines = head.split("\r\n");
+    let mut status_line = lines.next()?.splitn(3, ' ');
+    if !status_line.next()?.starts_with("HTTP/1.") {
+        return None;
+    }
+    let status = status_line.next()?.parse().ok()?;
+
+    let mut content_length = None;
+    let mut chunked = false;
+    for line in lines {
+        let (name, value) = line.split_once(':')?;
+        let value = value.trim();
+        if name.eq_ignore_ascii_case("content-length") {
+            content_length = Some(value.parse::<usize>().ok()?);
+        } else if name.eq_ignore_ascii_case("transfer-encoding") {
+            chunked = value.eq_ignore_ascii_case("chunked");
+        }
+    }
+
+    let framing = match (chunked, content_length) {
+        // These never have a body
+        _ if matches!(status, 100..=199 | 204 | 304) => Framing::Length(0),
+        (true, _) => Framing::Chunked,
+        (false, Some(content_length)) => Framing::Length(content_length),
+        (false, None) => Framing::Close,
+    };
+
+    Some(Head {
+        status,
+        framing,
+        len: header_len + 4,
+    })
+}
+
+/// Checks if `response` is complete without the server closing the
+/// connection
+fn is_complete(response: &[u8]) -> bool {
+    parse_head(response).is_some_and(|head| {
+        let body = &response[head.len..];
+        match head.framing {
+            Framing::Length(len) => body.len() >= len,
+            Framing::Chunked => decode_chunked(body).is_some(),
+            Framing::Close => false,
+        }
+    })
+}
+
+/// Parses a complete HTTP/1.x response, decoding a chunked body
+///
+/// Returns `None` if the response is malformed or was cut off, a body without
+/// `Content-Length` or chunked encoding is only complete if the server
+/// `closed` the connection.
+fn parse_response(response: &[u8], closed: bool) -> Option<Response> {
+    let head = parse_head(response)?;
+    let body = &response[head.len..];
+
+    let body = match head.framing {
+        Framing::Length(len) => body.get(..len)?.to_vec(),
+        Framing::Chunked => decode_chunked(body)?,
+        Framing::Close if closed => body.to_vec(),
+        Framing::Close => return None,
+    };
+
+    Some(Response {
+        status: head.status,
+        body,
+    })
+}
+
+/// Decodes a `Transfer-Encoding: chunked` body, trailers are ignored
+fn decode_chunked(mut body: &[u8]) -> Option<Vec<u8>> {
+    let mut decoded = Vec::new();
+    loop {
+        let line_len = body.windows(2).position(|w| w == b"\r\n")?;
+        let size = str::from_utf8(&body[..line_len]).ok()?;
+        // Chunk extensions follow a `;`
+        let size = size.split(';').next()?.trim();
+        let size = usize::from_str_radix(size, 16).ok()?;
+        body = &body[line_len + 2..];
+
+        if size == 0 {
+            return Some(decoded);
+        }
+        decoded.extend_from_slice(body.get(..size)?);
+        body = body.get(size..)?.strip_prefix(b"\r\n")?;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::{net::TcpListener, thread};
+
+    const NO_EXPECTATION: HttpExpectation<'static> = HttpExpectation {
+        status: None,
+        body: None,
+    };
+
+    /// Serves `response` to a single request, returning the request
+    ///
+    /// Unless the server should `close` the connection after the response,
+    /// it's kept open until the client closes it.
+    fn serve(
+        response: &'static str,
+        close: bool,
+    ) -> (u16, thread::JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = Vec::new();
+            let mut buffer = [0; 1024];
+            while !request.ends_with(b"\r\n\r\n") {
+                let len = stream.read(&mut buffer).unwrap();
+                request.extend_from_slice(&buffer[..len]);
+            }
+            stream.write_all(response.as_bytes()).unwrap();
+            if !close {
+                // Until the client closes the connection
+                let _ = stream.read(&mut buffer);
+            }
+            String::from_utf8(request).unwrap()
+        });
+
+        (port, server)
+    }
+
+    #[test]
+    fn parse() {
+        assert_eq!(
+            parse_response(b"HTTP/1.1 204 No Content\r\n\r\n", true),
+            Some(Response {
+                status: 204,
+                body: Vec::new()
+            })
+        );
+        assert_eq!(
+            parse_response(
+                b"HTTP/1.0 200 OK\r\nContent-Length: 7\r\n\r\nsuccess\n",
+                true
+            ),
+            Some(Response {
+                status: 200,
+                body: b"success".to_vec()
+            })
+        );
+        assert_eq!(
+            parse_response(
+                b"HTTP/1.1 200 OK\r\ntransfer-encoding: chunked\r\n\r\n\
+                  4\r\nsucc\r\n3;ext=1\r\ness\r\n0\r\n\r\n",
+                true
+            ),
+            Some(Response {
+                status: 200,
+                body: b"success".to_vec()
+            })
+        );
+
+        // Cut off
+        assert_eq!(parse_response(b"HTTP/1.1 204 No Content\r\n", true), None);
+        assert_eq!(
+            parse_response(
+                b"HTTP/1.1 200 OK\r\nContent-Length: 7\r\n\r\nsucc",
+                true
+            ),
+            None
+        );
+        assert_eq!(
+            parse_response(
+                b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n4\r\nsucc", true
+            ),
+            None
+        );
+        // Not HTTP
+        assert_eq!(parse_response(b"SSH-2.0-OpenSSH\r\n\r\n", true), None);
+        assert_eq!(parse_response(b"HTTP/1.1 OK\r\n\r\n", true), None);
+
+        // Only the server closing the connection ends the body
+        assert_eq!(
+            parse_response(b"HTTP/1.0 200 OK\r\n\r\nsuccess", true),
+            Some(Response {
+                status: 200,
+                body: b"success".to_vec()
+            })
+        );
+        assert_eq!(parse_response(b"HTTP/1.0 200 OK\r\n\r\nsucc", false), None);
+        // Complete without it
+        assert!(parse_response(
+            b"HTTP/1.1 200 OK\r\nContent-Length: 7\r\n\r\nsuccess",
+            false
+        )
+        .is_some());
+    }
+
+    #[test]
+    fn complete() {
+        assert!(is_complete(b"HTTP/1.1 204 No Content\r\n\r\n"));
+        assert!(is_complete(
+            b"HTTP/1.1 200 OK\r\nContent-Length: 7\r\n\r\nsuccess"
+        ));
+        assert!(is_complete(
+            b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n\
+              7\r\nsuccess\r\n0\r\n\r\n"
+        ));
+
+        assert!(!is_complete(b"HTTP/1.1 204 No Content\r\n"));
+        assert!(!is_complete(
+            b"HTTP/1.1 200 OK\r\nContent-Length: 7\r\n\r\nsucc"
+        ));
+        assert!(!is_complete(
+            b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n\
+              7\r\nsuccess\r\n"
+        ));
+        // Only the server closing the connection ends the body
+        assert!(!is_complete(b"HTTP/1.0 200 OK\r\n\r\nsuccess"));
+    }
+
+    #[test]
+    fn expectation() {
+        let response = |status| Response {
+            status,
+            body: b"NetworkManager is online\n".to_vec(),
+        };
+        let expect_204 = HttpExpectation {
+            status: Some(204),
+            body: None,
+        };
+        let expect_body = HttpExpectation {
+            status: None,
+            body: Some("NetworkManager is online"),
+        };
+
+        assert_eq!(response(200).status(NO_EXPECTATION), ProbeStatus::Online);
+        assert_eq!(
+            response(302).status(NO_EXPECTATION),
+            ProbeStatus::CaptivePortal
+        );
+        assert_eq!(response(204).status(expect_204), ProbeStatus::Online);
+        assert_eq!(
+            response(200).status(expect_204),
+            ProbeStatus::CaptivePortal
+        );
+        assert_eq!(response(200).status(expect_body), ProbeStatus::Online);
+        assert_eq!(
+            Response {
+                status: 200,
+                body: b"<html>Login</html>".to_vec()
+            }
+            .status(expect_body),
+            ProbeStatus::CaptivePortal
+        );
+    }
+
+    #[test]
+    fn get_online() {
+        let (port, server) = serve("HTTP/1.1 204 No Content\r\n\r\n", false);
+        let expectation = HttpExpectation {
+            status: Some(204),
+            body: None,
+        };
+
+        assert_eq!(
+            get(
+                "127.0.0.1",
+                port,
+                "/generate_204",
+                expectation,
+                Duration::from_secs(1)
+            ),
+            ProbeStatus::Online
+        );
+        assert_eq!(
+            server.join().unwrap(),
+            format!(
+                "GET /generate_204 HTTP/1.1\r\nHost: 127.0.0.1:{port}\r\n\
+                 User-Agent: wait-online\r\nAccept: */*\r\n\
+                 Connection: close\r\n\r\n"
+            )
+        );
+    }
+
+    #[test]
+    fn get_captive_portal() {
+        let (port, server) = serve(
+            "HTTP/1.1 302 Found\r\nLocation: http://portal.example/\r\n\
+             Content-Length: 0\r\n\r\n",
+            false,
+        );
+
+        assert_eq!(
+            get(
+                "127.0.0.1",
+                port,
+                "/",
+                NO_EXPECTATION,
+                Duration::from_secs(1)
+            ),
+            ProbeStatus::CaptivePortal
+        );
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn get_chunked() {
+        let (port, server) = serve(
+            "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n\
+             7\r\nsuccess\r\n0\r\n\r\n",
+            false,
+        );
+        let expectation = HttpExpectation {
+            status: None,
+            body: Some("success"),
+        };
+
+        let start = Instant::now();
+        assert_eq!(
+            get("127.0.0.1", port, "/", expectation, Duration::from_secs(5)),
+            ProbeStatus::Online
+        );
+        // Doesn't wait for the server to close the connection
+        assert!(start.elapsed() < Duration::from_secs(5));
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn get_until_close() {
+        let (port, server) = serve("HTTP/1.0 200 OK\r\n\r\nsuccess", true);
+        let expectation = HttpExpectation {
+            status: None,
+            body: Some("success"),
+        };
+
+        assert_eq!(
+            get("127.0.0.1", port, "/", expectation, Duration::from_secs(1)),
+            ProbeStatus::Online
+        );
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn get_until_timeout() {
+        // Cut off by the timeout, the rest of the body could differ
+        let (port, server) =
+            serve("HTTP/1.0 200 OK\r\n\r\n<html>Login</html>", false);
+        let expectation = HttpExpectation {
+            status: None,
+            body: Some("success"),
+        };
+
+        // Not a captive portal
+        assert_eq!(
+            get(
+                "127.0.0.1",
+                port,
+                "/",
+                expectation,
+                Duration::from_millis(200)
+            ),
+            ProbeStatus::Offline
+        );
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn get_offline() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        drop(listener);
+
+        assert_eq!(
+            get(
+                "127.0.0.1",
+                port,
+                "/",
+                NO_EXPECTATION,
+                Duration::from_secs(1)
+            ),
+            ProbeStatus::Offline
+        );
+    }
+
+    #[test]
+    fn request_host() {
+        assert_eq!(request("::1", 80, "/").lines().nth(1), Some("Host: [::1]"));
+    }
+}
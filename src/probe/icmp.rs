@@ -0,0 +1,307 @@
+use std::{
+    cmp::min,
+    io,
+    net::{IpAddr, SocketAddr, SocketAddrV6},
+    os::fd::{AsFd, AsRawFd, OwnedFd},
+    time::{Duration, Instant},
+};
+
+use nix::{
+    errno::Errno,
+    poll::{poll, PollFd, PollFlags, PollTimeout},
+    sys::socket::{
+        connect, recv, send, socket, AddressFamily, MsgFlags, SockFlag,
+        SockProtocol, SockType, SockaddrStorage,
+    },
+};
+
+use super::GATEWAY;
+use crate::{
+    dns,
+    netlink::{self, Routes},
+};
+
+const ICMP_ECHO_REQUEST: u8 = 8;
+const ICMP_ECHO_REPLY: u8 = 0;
+const ICMPV6_ECHO_REQUEST: u8 = 128;
+const ICMPV6_ECHO_REPLY: u8 = 129;
+
+/// Size of the ICMP echo header (type, code, checksum, id, sequence)
+const ECHO_HEADER_LEN: usize = 8;
+
+/// Payload of the echo requests, replies have to echo it back
+const PAYLOAD: &[u8; 8] = b"wait-onl";
+
+/// Checks if ping sockets for `host` can be created
+///
+/// # Errors
+///
+/// Will return `Err` if the group isn't allowed by
+/// `net.ipv4.ping_group_range`, which also applies to IPv6.
+pub(super) fn check_allowed(host: &str) -> Result<(), io::Error> {
+    // Hosts that have to be resolved first are most likely reached over IPv4
+    let (family, protocol) = match host.parse() {
+        Ok(IpAddr::V6(_)) => (AddressFamily::Inet6, SockProtocol::IcmpV6),
+        _ => (AddressFamily::Inet, SockProtocol::Icmp),
+    };
+
+    match socket(family, SockType::Datagram, SockFlag::SOCK_CLOEXEC, protocol) {
+        Err(Errno::EACCES | Errno::EPERM) => Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            "ping sockets aren't allowed for this group \
+             (see `net.ipv4.ping_group_range`)",
+        )),
+        // E.g. `EAFNOSUPPORT` without IPv6, `ping` reports these as lost
+        Ok(_) | Err(_) => Ok(()),
+    }
+}
+
+/// Sends `count` echo requests to `host` within `timeout`
+///
+/// Returns `true` if no more than `max_loss` percent of them were lost.
+/// Whether ping sockets are allowed is checked up front with
+/// [`check_allowed`], a socket that can't be created counts as lost.
+///
+/// # Errors
+///
+/// Will return `Err` if waiting for a reply fails. Failing to reach the host
+/// isn't an error.
+pub(super) fn ping(
+    host: &str,
+    count: u16,
+    max_loss: u8,
+    timeout: Duration,
+) -> Result<bool, io::Error> {
+    let deadline = Instant::now() + timeout;
+    let address = if host == GATEWAY {
+        netlink::dump_routes().ok().as_ref().and_then(gateway)
+    } else {
+        dns::resolve(host, 0, timeout)
+            .and_then(|addresses| addresses.into_iter().next())
+    };
+    let Some(address) = address else {
+        return Ok(false);
+    };
+
+    // E.g. `ENETUNREACH` while there is no route yet
+    let Ok(fd) = open(address) else {
+        return Ok(false);
+    };
+
+    let echo_timeout = deadline.saturating_duration_since(Instant::now())
+        / u32::from(count.max(1));
+    let mut replies: u16 = 0;
+    for sequence in 0..count {
+        let echo_deadline = min(Instant::now() + echo_timeout, deadline);
+        if echo(&fd, address.ip(), sequence, echo_deadline)? {
+            replies += 1;
+        }
+    }
+
+    Ok(within_loss(count, replies, max_loss))
+}
+
+/// Address of the gateway of the first usable default route, a link-local
+/// IPv6 gateway is scoped to the route's interface
+fn gateway(routes: &Routes) -> Option<SocketAddr> {
+    routes.default_routes(None).find_map(|route| {
+        Some(match route.gateway? {
+            IpAddr::V4(gateway) => SocketAddr::from((gateway, 0)),
+            IpAddr::V6(gateway) => {
+                SocketAddrV6::new(gateway, 0, 0, route.oif.unwrap_or(0)).into()
+            }
+        })
+    })
+}
+
+/// Checks if no more than `max_loss` percent of `count` echoes were lost
+pub(super) fn within_loss(count: u16, replies: u16, max_loss: u8) -> bool {
+    let lost = u32::from(count.saturating_sub(replies));
+    lost * 100 <= u32::from(max_loss) * u32::from(count)
+}
+
+/// Opens a ping socket (`SOCK_DGRAM`, `IPPROTO_ICMP`/`IPPROTO_ICMPV6`)
+/// connected to `address`
+fn open(address: SocketAddr) -> Result<OwnedFd, Errno> {
+    let (family, protocol) = match address {
+        SocketAddr::V4(_) => (AddressFamily::Inet, SockProtocol::Icmp),
+        SocketAddr::V6(_) => (AddressFamily::Inet6, SockProtocol::IcmpV6),
+    };
+    let fd =
+        socket(family, SockType::Datagram, SockFlag::SOCK_CLOEXEC, protocol)?;
+    connect(fd.as_raw_fd(), &SockaddrStorage::from(address))?;
+
+    Ok(fd)
+}
+
+/// Sends one echo request and waits until `deadline` for its reply
+fn echo(
+    fd: &OwnedFd,
+    address: IpAddr,
+    sequence: u16,
+    deadline: Instant,
+) -> Result<bool, io::Error> {
+    if send(
+        fd.as_raw_fd(),
+        &echo_request(address, sequence),
+        MsgFlags::empty(),
+    )
+    .is_err()
+    {
+        // E.g. `EHOSTUNREACH`, counts as lost
+        return Ok(false);
+    }
+
+    let mut buffer = [0u8; 128];
+    loop {
+        let Some(timeout) = deadline.checked_duration_since(Instant::now())
+        else {
+            return Ok(false);
+        };
+        let timeout =
+            PollTimeout::try_from(timeout).unwrap_or(PollTimeout::MAX);
+        let mut fds = [PollFd::new(fd.as_fd(), PollFlags::POLLIN)];
+
+        match poll(&mut fds, timeout) {
+            Ok(0) => return Ok(false),
+            Ok(_) | Err(Errno::EINTR) => {}
+            Err(err) => return Err(err.into()),
+        }
+
+        match recv(fd.as_raw_fd(), &mut buffer, MsgFlags::MSG_DONTWAIT) {
+            Ok(len) if is_echo_reply(&buffer[..len], address, sequence) => {
+                return Ok(true)
+            }
+            // A reply to an earlier request or an ICMP error
+            Ok(_) | Err(Errno::EAGAIN | Errno::EINTR) => {}
+            Err(_) => return Ok(false),
+        }
+    }
+}
+
+/// Builds an echo request, the kernel fills in the identifier and checksum
+fn echo_request(address: IpAddr, sequence: u16) -> Vec<u8> {
+    let kind = match address {
+        IpAddr::V4(_) => ICMP_ECHO_REQUEST,
+        IpAddr::V6(_) => ICMPV6_ECHO_REQUEST,
+    };
+
+    let mut request = vec![kind, 0, 0, 0, 0, 0];
+    request.extend(sequence.to_be_bytes());
+    request.extend(PAYLOAD);
+    request
+}
+
+/// Checks if `packet` is the reply to the echo request `sequence`
+fn is_echo_reply(packet: &[u8], address: IpAddr, sequence: u16) -> bool {
+    let kind = match address {
+        IpAddr::V4(_) => ICMP_ECHO_REPLY,
+        IpAddr::V6(_) => ICMPV6_ECHO_REPLY,
+    };
+
+    packet.len() == ECHO_HEADER_LEN + PAYLOAD.len()
+        && packet[0] == kind
+        && packet[6..8] == sequence.to_be_bytes()
+        && packet[ECHO_HEADER_LEN..] == PAYLOAD[..]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::net::{Ipv4Addr, Ipv6Addr};
+
+    use crate::libc;
+
+    const V4: IpAddr = IpAddr::V4(Ipv4Addr::LOCALHOST);
+    const V6: IpAddr = IpAddr::V6(Ipv6Addr::LOCALHOST);
+
+    #[test]
+    fn default_gateway() {
+        let route = |destination: &str, gateway: &str, oif| {
+            netlink::tests::route_message(
+                destination.parse().unwrap(),
+                gateway.parse().ok(),
+                oif,
+                libc::RTN_UNICAST,
+                0,
+            )
+        };
+
+        let mut bytes = route("192.0.2.0/24", "192.0.2.1", 2);
+        assert_eq!(gateway(&Routes::parse(&bytes)), None);
+
+        bytes.extend(route("::/0", "fe80::1", 3));
+        assert_eq!(
+            gateway(&Routes::parse(&bytes)),
+            Some(SocketAddrV6::new("fe80::1".parse().unwrap(), 0, 0, 3).into())
+        );
+
+        let mut bytes = route("0.0.0.0/0", "192.0.2.1", 2);
+        bytes.extend(route("::/0", "fe80::1", 3));
+        assert_eq!(
+            gateway(&Routes::parse(&bytes)),
+            Some("192.0.2.1:0".parse().unwrap())
+        );
+    }
+
+    #[test]
+    fn request() {
+        assert_eq!(
+            echo_request(V4, 258),
+            [
+                8, 0, 0, 0, 0, 0, 1, 2, b'w', b'a', b'i', b't', b'-', b'o',
+                b'n', b'l'
+            ]
+        );
+        assert_eq!(echo_request(V6, 1)[0], ICMPV6_ECHO_REQUEST);
+    }
+
+    #[test]
+    fn reply() {
+        let mut reply = echo_request(V4, 7);
+        reply[0] = ICMP_ECHO_REPLY;
+        // The identifier and checksum don't matter
+        reply[2..6].copy_from_slice(&[0xab, 0xcd, 0x12, 0x34]);
+
+        assert!(is_echo_reply(&reply, V4, 7));
+        assert!(!is_echo_reply(&reply, V4, 8));
+        assert!(!is_echo_reply(&reply, V6, 7));
+        assert!(!is_echo_reply(&reply[..ECHO_HEADER_LEN], V4, 7));
+        assert!(!is_echo_reply(&echo_request(V4, 7), V4, 7));
+
+        let mut reply = echo_request(V6, 7);
+        reply[0] = ICMPV6_ECHO_REPLY;
+        assert!(is_echo_reply(&reply, V6, 7));
+    }
+
+    #[test]
+    fn loss() {
+        assert!(within_loss(1, 1, 0));
+        assert!(!within_loss(1, 0, 0));
+        assert!(within_loss(1, 0, 100));
+        assert!(within_loss(3, 2, 34));
+        assert!(!within_loss(3, 2, 33));
+        assert!(within_loss(4, 2, 50));
+        assert!(!within_loss(4, 1, 50));
+        assert!(within_loss(0, 0, 0));
+    }
+
+    #[test]
+    fn ping_loopback() {
+        match check_allowed("127.0.0.1") {
+            Ok(()) => {
+                assert!(
+                    ping("127.0.0.1", 2, 0, Duration::from_secs(1)).unwrap()
+                );
+            }
+            // Ping sockets aren't allowed on this host
+            Err(err) => {
+                assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+                assert!(
+                    !ping("127.0.0.1", 2, 0, Duration::from_secs(1)).unwrap()
+                );
+            }
+        }
+    }
+}
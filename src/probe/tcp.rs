@@ -0,0 +1,54 @@
+use std::{
+    net::TcpStream,
+    time::{Duration, Instant},
+};
+
+use crate::dns;
+
+/// Tries to complete a TCP handshake with any address of `host` within
+/// `timeout`
+///
+/// Resolving `host` and the connects are bounded, see [`dns::resolve`] and
+/// [`TcpStream::connect_timeout`].
+pub(super) fn connect(host: &str, port: u16, timeout: Duration) -> bool {
+    let deadline = Instant::now() + timeout;
+    let Some(addresses) = dns::resolve(host, port, timeout) else {
+        return false;
+    };
+
+    addresses.into_iter().any(|address| {
+        deadline
+            .checked_duration_since(Instant::now())
+            .filter(|timeout| !timeout.is_zero())
+            .is_some_and(|timeout| {
+                TcpStream::connect_timeout(&address, timeout).is_ok()
+            })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::net::TcpListener;
+
+    #[test]
+    fn connect_listener() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        assert!(connect("127.0.0.1", port, Duration::from_secs(1)));
+        assert!(connect("localhost", port, Duration::from_secs(1)));
+
+        drop(listener);
+        assert!(!connect("127.0.0.1", port, Duration::from_secs(1)));
+    }
+
+    #[test]
+    fn connect_timeout_zero() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        assert!(!connect("127.0.0.1", port, Duration::ZERO));
+    }
+}
@@ -1,21 +1,289 @@
+use std::{
+    fmt,
+    net::{AddrParseError, IpAddr, Ipv4Addr, Ipv6Addr},
+    str::FromStr,
+};
+
+#[cfg(feature = "clap")]
+use clap::ValueEnum;
+
+use crate::libc;
+
 // Re-export in case we need a wrapper later
 pub use crate::libc::sockaddr;
 pub use nix::sys::socket::AddressFamily;
 
+/// Scope of an address, ordered from the narrowest to the widest scope
+///
+/// Matches the kernel's `RT_SCOPE_*` address scopes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "clap", derive(ValueEnum))]
+pub enum AddressScope {
+    /// Only valid on this host, e.g. `127.0.0.1` or `::1`
+    Host,
+    /// Only valid on the local link, e.g. `169.254.0.0/16` or `fe80::/10`
+    Link,
+    /// Only valid within the site, e.g. unique local `fc00::/7` or the
+    /// deprecated `fec0::/10`
+    Site,
+    /// Globally valid
+    Global,
+}
+
+impl AddressScope {
+    /// Scope of an address based on its range
+    ///
+    /// Unlike the kernel, which gives them a global scope, unique local
+    /// addresses are site scoped.
+    #[must_use]
+    pub const fn from_ip(address: IpAddr) -> Self {
+        match address {
+            IpAddr::V4(address) => Self::from_ipv4(address),
+            IpAddr::V6(address) => Self::from_ipv6(address),
+        }
+    }
+
+    const fn from_ipv4(address: Ipv4Addr) -> Self {
+        if address.is_loopback() {
+            Self::Host
+        } else if address.is_link_local() {
+            Self::Link
+        } else {
+            Self::Global
+        }
+    }
+
+    const fn from_ipv6(address: Ipv6Addr) -> Self {
+        let first = address.segments()[0];
+        match first & 0xffc0 {
+            _ if address.is_loopback() => Self::Host,
+            0xfe80 => Self::Link,
+            0xfec0 => Self::Site,
+            _ if first & 0xfe00 == 0xfc00 => Self::Site,
+            _ => Self::Global,
+        }
+    }
+
+    /// Converts a `RT_SCOPE_*` scope
+    ///
+    /// Scopes between `RT_SCOPE_UNIVERSE` and `RT_SCOPE_SITE` are user defined
+    /// and treated as [`AddressScope::Site`], `RT_SCOPE_NOWHERE` as
+    /// [`AddressScope::Host`].
+    #[must_use]
+    pub const fn from_rt_scope(scope: u8) -> Self {
+        match scope {
+            libc::RT_SCOPE_UNIVERSE => Self::Global,
+            libc::RT_SCOPE_LINK => Self::Link,
+            libc::RT_SCOPE_HOST | libc::RT_SCOPE_NOWHERE => Self::Host,
+            _ => Self::Site,
+        }
+    }
+}
+
+/// An IPv4 or IPv6 prefix, parsed from `ADDRESS[/PREFIX_LEN]`
+///
+/// Without a prefix length the prefix only contains `ADDRESS`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Cidr {
+    address: IpAddr,
+    prefix_len: u8,
+}
+
+impl Cidr {
+    /// Returns `None` if `prefix_len` is too long for the address family
+    #[must_use]
+    pub const fn new(address: IpAddr, prefix_len: u8) -> Option<Self> {
+        if prefix_len > Self::max_prefix_len(address) {
+            return None;
+        }
+        Some(Self {
+            address,
+            prefix_len,
+        })
+    }
+
+    /// The address the prefix was created with, host bits aren't cleared
+    #[must_use]
+    pub const fn address(&self) -> IpAddr {
+        self.address
+    }
+
+    #[must_use]
+    pub const fn prefix_len(&self) -> u8 {
+        self.prefix_len
+    }
+
+    const fn max_prefix_len(address: IpAddr) -> u8 {
+        match address {
+            IpAddr::V4(_) => 32,
+            IpAddr::V6(_) => 128,
+        }
+    }
+
+    /// Checks if `address` is part of the prefix
+    #[must_use]
+    pub fn contains_address(&self, address: IpAddr) -> bool {
+        let host_bits =
+            |max_prefix_len: u8| u32::from(max_prefix_len - self.prefix_len);
+        match (self.address, address) {
+            (IpAddr::V4(network), IpAddr::V4(address)) => {
+                let shift = host_bits(32);
+                u32::from(network).checked_shr(shift).unwrap_or(0)
+                    == u32::from(address).checked_shr(shift).unwrap_or(0)
+            }
+            (IpAddr::V6(network), IpAddr::V6(address)) => {
+                let shift = host_bits(128);
+                u128::from(network).checked_shr(shift).unwrap_or(0)
+                    == u128::from(address).checked_shr(shift).unwrap_or(0)
+            }
+            _ => false,
+        }
+    }
+
+    /// Checks if all of `other` is part of the prefix
+    #[must_use]
+    pub fn contains(&self, other: &Self) -> bool {
+        self.prefix_len <= other.prefix_len
+            && self.contains_address(other.address)
+    }
+}
+
+impl fmt::Display for Cidr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.address, self.prefix_len)
+    }
+}
+
+impl FromStr for Cidr {
+    type Err = ParseCidrError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (address, prefix_len) = match s.split_once('/') {
+            Some((address, prefix_len)) => (address, Some(prefix_len)),
+            None => (s, None),
+        };
+        let address: IpAddr =
+            address.parse().map_err(ParseCidrError::Address)?;
+        let prefix_len = match prefix_len {
+            Some(prefix_len) => prefix_len
+                .parse()
+                .map_err(|_| ParseCidrError::PrefixLen(prefix_len.into()))?,
+            None => Self::max_prefix_len(address),
+        };
+
+        Self::new(address, prefix_len).ok_or_else(|| {
+            ParseCidrError::PrefixLen(prefix_len.to_string().into())
+        })
+    }
+}
+
+/// Error returned when parsing an invalid [`Cidr`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseCidrError {
+    Address(AddrParseError),
+    PrefixLen(Box<str>),
+}
+
+impl fmt::Display for ParseCidrError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Address(err) => err.fmt(f),
+            Self::PrefixLen(prefix_len) => {
+                write!(f, "invalid prefix length `{prefix_len}`")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ParseCidrError {}
+
+/// State of the duplicate address detection (DAD) of an address
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DadState {
+    /// DAD hasn't finished, the address is tentative or optimistic and can't
+    /// be relied on yet
+    Tentative,
+    /// DAD found the address is already in use on the link
+    Failed,
+    /// DAD finished or wasn't needed
+    Done,
+}
+
+impl DadState {
+    /// Gets the DAD state from the `IFA_F_*` flags of an address
+    #[must_use]
+    pub const fn from_ifa_flags(flags: u32) -> Self {
+        if flags & libc::IFA_F_DADFAILED != 0 {
+            Self::Failed
+        } else if flags & (libc::IFA_F_TENTATIVE | libc::IFA_F_OPTIMISTIC) != 0
+        {
+            Self::Tentative
+        } else {
+            Self::Done
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct InterfacesFamilyTypeArgument {
     pub(crate) ipv4: bool,
     pub(crate) ipv6: bool,
+    /// One of the address families is enough, see `--ipv4-or-ipv6`
+    pub(crate) either: bool,
+    /// Narrowest scope of an address that counts
+    pub(crate) scope: AddressScope,
 }
 
 impl InterfacesFamilyTypeArgument {
+    /// Scope used when none is given, global and site/ULA addresses count
+    pub(crate) const DEFAULT_SCOPE: AddressScope = AddressScope::Site;
+
     #[must_use]
     pub(crate) const fn from_args(ipv4: bool, ipv6: bool) -> Option<Self> {
         match (ipv4, ipv6) {
             (false, false) => None,
-            (ipv4, ipv6) => Some(Self { ipv4, ipv6 }),
+            (ipv4, ipv6) => Some(Self {
+                ipv4,
+                ipv6,
+                either: false,
+                scope: Self::DEFAULT_SCOPE,
+            }),
+        }
+    }
+
+    #[must_use]
+    pub(crate) const fn either(mut self, either: bool) -> Self {
+        self.either = either;
+        self
+    }
+
+    #[must_use]
+    pub(crate) const fn scope(mut self, scope: AddressScope) -> Self {
+        self.scope = scope;
+        self
+    }
+
+    /// Checks if addresses of the families `has_ipv4` and `has_ipv6` are
+    /// enough
+    #[must_use]
+    pub(crate) const fn satisfied(
+        self,
+        has_ipv4: bool,
+        has_ipv6: bool,
+    ) -> bool {
+        if self.either {
+            self.ipv4 && has_ipv4 || self.ipv6 && has_ipv6
+        } else {
+            (!self.ipv4 || has_ipv4) && (!self.ipv6 || has_ipv6)
         }
     }
+
+    /// Checks if an address with `scope` counts, entries without an IPv4 or
+    /// IPv6 address always do
+    #[must_use]
+    pub(crate) fn in_scope(self, scope: Option<AddressScope>) -> bool {
+        scope.map_or(true, |scope| scope >= self.scope)
+    }
 }
 
 /// # Safety
@@ -31,6 +299,28 @@ pub(crate) unsafe fn get_addres_family(
         .flatten()
 }
 
+/// # Safety
+///
+/// A valid `libc::sockaddr` ptr must be provided,
+/// this ptr can be null.
+/// For `AF_INET` and `AF_INET6` it must point to a complete `sockaddr_in` or
+/// `sockaddr_in6`.
+pub(crate) unsafe fn get_address(ifa_addr: *mut sockaddr) -> Option<IpAddr> {
+    match get_addres_family(ifa_addr)? {
+        AddressFamily::Inet => {
+            let addr = ifa_addr.cast::<libc::sockaddr_in>().read_unaligned();
+            Some(IpAddr::V4(Ipv4Addr::from(u32::from_be(
+                addr.sin_addr.s_addr,
+            ))))
+        }
+        AddressFamily::Inet6 => {
+            let addr = ifa_addr.cast::<libc::sockaddr_in6>().read_unaligned();
+            Some(IpAddr::V6(Ipv6Addr::from(addr.sin6_addr.s6_addr)))
+        }
+        _ => None,
+    }
+}
+
 /// Checks if waiting is required for given `ifa_addr`
 ///
 /// # Safety
@@ -53,7 +343,161 @@ pub unsafe fn check_family_type(
 mod tests {
     use super::*;
 
-    use crate::libc;
+    #[test]
+    fn scope_ipv4() {
+        let scope =
+            |address: &str| AddressScope::from_ip(address.parse().unwrap());
+
+        assert_eq!(scope("127.0.0.1"), AddressScope::Host);
+        assert_eq!(scope("169.254.1.1"), AddressScope::Link);
+        assert_eq!(scope("10.0.0.1"), AddressScope::Global);
+        assert_eq!(scope("192.0.2.1"), AddressScope::Global);
+    }
+
+    #[test]
+    fn scope_ipv6() {
+        let scope =
+            |address: &str| AddressScope::from_ip(address.parse().unwrap());
+
+        assert_eq!(scope("::1"), AddressScope::Host);
+        assert_eq!(scope("fe80::1"), AddressScope::Link);
+        assert_eq!(scope("febf::1"), AddressScope::Link);
+        assert_eq!(scope("fec0::1"), AddressScope::Site);
+        assert_eq!(scope("fd00::1"), AddressScope::Site);
+        assert_eq!(scope("fc00::1"), AddressScope::Site);
+        assert_eq!(scope("fe00::1"), AddressScope::Global);
+        assert_eq!(scope("2001:db8::1"), AddressScope::Global);
+    }
+
+    #[test]
+    fn scope_rt() {
+        assert_eq!(
+            AddressScope::from_rt_scope(libc::RT_SCOPE_UNIVERSE),
+            AddressScope::Global
+        );
+        assert_eq!(AddressScope::from_rt_scope(100), AddressScope::Site);
+        assert_eq!(
+            AddressScope::from_rt_scope(libc::RT_SCOPE_SITE),
+            AddressScope::Site
+        );
+        assert_eq!(
+            AddressScope::from_rt_scope(libc::RT_SCOPE_LINK),
+            AddressScope::Link
+        );
+        assert_eq!(
+            AddressScope::from_rt_scope(libc::RT_SCOPE_HOST),
+            AddressScope::Host
+        );
+        assert!(AddressScope::Host < AddressScope::Global);
+    }
+
+    #[test]
+    fn parse_cidr() {
+        let cidr = |s: &str| s.parse::<Cidr>();
+
+        assert_eq!(
+            cidr("192.0.2.0/24").map(|cidr| cidr.to_string()),
+            Ok("192.0.2.0/24".into())
+        );
+        assert_eq!(
+            cidr("2001:db8::1").map(|cidr| cidr.to_string()),
+            Ok("2001:db8::1/128".into())
+        );
+        assert_eq!(cidr("0.0.0.0/0").map(|cidr| cidr.prefix_len()), Ok(0));
+        assert!(cidr("192.0.2.0/33").is_err());
+        assert!(cidr("2001:db8::/129").is_err());
+        assert!(cidr("192.0.2.0/").is_err());
+        assert!(cidr("192.0.2/24").is_err());
+        assert!(cidr("eth0").is_err());
+    }
+
+    #[test]
+    fn cidr_contains() {
+        let cidr = |s: &str| s.parse::<Cidr>().unwrap();
+        let ip = |s: &str| s.parse::<IpAddr>().unwrap();
+
+        let prefix = cidr("192.0.2.0/24");
+        assert!(prefix.contains_address(ip("192.0.2.0")));
+        assert!(prefix.contains_address(ip("192.0.2.255")));
+        assert!(!prefix.contains_address(ip("192.0.3.1")));
+        assert!(!prefix.contains_address(ip("::ffff:192.0.2.1")));
+        assert!(prefix.contains(&cidr("192.0.2.128/25")));
+        assert!(!prefix.contains(&cidr("192.0.0.0/16")));
+
+        assert!(cidr("0.0.0.0/0").contains(&cidr("198.51.100.0/24")));
+        assert!(!cidr("0.0.0.0/0").contains(&cidr("2001:db8::/32")));
+        assert!(cidr("::/0").contains(&cidr("2001:db8::/32")));
+        assert!(cidr("2001:db8::/32").contains_address(ip("2001:db8:1::1")));
+        assert!(!cidr("2001:db8::/32").contains_address(ip("2001:db9::1")));
+        assert!(cidr("2001:db8::1").contains_address(ip("2001:db8::1")));
+        assert!(!cidr("2001:db8::1").contains_address(ip("2001:db8::2")));
+    }
+
+    #[test]
+    fn dad_state() {
+        assert_eq!(DadState::from_ifa_flags(0), DadState::Done);
+        assert_eq!(
+            DadState::from_ifa_flags(libc::IFA_F_PERMANENT),
+            DadState::Done
+        );
+        assert_eq!(
+            DadState::from_ifa_flags(libc::IFA_F_TENTATIVE),
+            DadState::Tentative
+        );
+        assert_eq!(
+            DadState::from_ifa_flags(libc::IFA_F_OPTIMISTIC),
+            DadState::Tentative
+        );
+        // A failed address stays tentative
+        assert_eq!(
+            DadState::from_ifa_flags(
+                libc::IFA_F_TENTATIVE | libc::IFA_F_DADFAILED
+            ),
+            DadState::Failed
+        );
+    }
+
+    #[test]
+    fn address() {
+        let mut storage: libc::sockaddr_storage = unsafe { std::mem::zeroed() };
+        let sockaddr_ptr: *mut sockaddr =
+            (&mut storage as *mut libc::sockaddr_storage).cast();
+
+        let sockaddr_in = libc::sockaddr_in {
+            sin_family: libc::sa_family_t::try_from(libc::AF_INET).unwrap(),
+            sin_port: 0,
+            sin_addr: libc::in_addr {
+                s_addr: u32::from(Ipv4Addr::new(192, 0, 2, 1)).to_be(),
+            },
+            sin_zero: [0; 8],
+        };
+        unsafe { sockaddr_ptr.cast::<libc::sockaddr_in>().write(sockaddr_in) };
+        assert_eq!(
+            unsafe { get_address(sockaddr_ptr) },
+            Some(IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1)))
+        );
+
+        let ip = Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1);
+        let sockaddr_in6 = libc::sockaddr_in6 {
+            sin6_family: libc::sa_family_t::try_from(libc::AF_INET6).unwrap(),
+            sin6_port: 0,
+            sin6_flowinfo: 0,
+            sin6_addr: libc::in6_addr {
+                s6_addr: ip.octets(),
+            },
+            sin6_scope_id: 0,
+        };
+        unsafe {
+            sockaddr_ptr
+                .cast::<libc::sockaddr_in6>()
+                .write(sockaddr_in6)
+        };
+        assert_eq!(unsafe { get_address(sockaddr_ptr) }, Some(IpAddr::V6(ip)));
+
+        let mut sockaddr = create_sockaddr(AddressFamily::Packet);
+        assert_eq!(unsafe { get_address(&mut sockaddr) }, None);
+        assert_eq!(unsafe { get_address(std::ptr::null_mut()) }, None);
+    }
 
     #[test]
     fn family_type_arg() {
@@ -62,24 +506,67 @@ mod tests {
             InterfacesFamilyTypeArgument::from_args(true, false),
             Some(InterfacesFamilyTypeArgument {
                 ipv4: true,
-                ipv6: false
+                ipv6: false,
+                either: false,
+                scope: AddressScope::Site,
             })
         );
         assert_eq!(
             InterfacesFamilyTypeArgument::from_args(false, true),
             Some(InterfacesFamilyTypeArgument {
                 ipv4: false,
-                ipv6: true
+                ipv6: true,
+                either: false,
+                scope: AddressScope::Site,
             })
         );
         assert_eq!(
             InterfacesFamilyTypeArgument::from_args(true, true),
             Some(InterfacesFamilyTypeArgument {
                 ipv4: true,
-                ipv6: true
+                ipv6: true,
+                either: false,
+                scope: AddressScope::Site,
             })
         );
     }
+    #[test]
+    fn family_type_in_scope() {
+        let arg = InterfacesFamilyTypeArgument::from_args(true, true).unwrap();
+        assert!(arg.in_scope(None));
+        assert!(arg.in_scope(Some(AddressScope::Global)));
+        assert!(arg.in_scope(Some(AddressScope::Site)));
+        assert!(!arg.in_scope(Some(AddressScope::Link)));
+        assert!(!arg.in_scope(Some(AddressScope::Host)));
+
+        let arg = arg.scope(AddressScope::Global);
+        assert!(!arg.in_scope(Some(AddressScope::Site)));
+
+        let arg = arg.scope(AddressScope::Link);
+        assert!(arg.in_scope(Some(AddressScope::Link)));
+        assert!(!arg.in_scope(Some(AddressScope::Host)));
+    }
+
+    #[test]
+    fn family_type_satisfied() {
+        let both = InterfacesFamilyTypeArgument::from_args(true, true).unwrap();
+        assert!(both.satisfied(true, true));
+        assert!(!both.satisfied(true, false));
+        assert!(!both.satisfied(false, true));
+
+        let either = both.either(true);
+        assert!(either.satisfied(true, false));
+        assert!(either.satisfied(false, true));
+        assert!(!either.satisfied(false, false));
+
+        let ipv4 =
+            InterfacesFamilyTypeArgument::from_args(true, false).unwrap();
+        assert!(ipv4.satisfied(true, false));
+        assert!(!ipv4.satisfied(false, true));
+        assert!(ipv4.either(true).satisfied(true, false));
+        assert!(!ipv4.either(true).satisfied(false, true));
+    }
+
     #[test]
     fn check_family_type_ipv4() {
         let combinations = vec![
@@ -0,0 +1,66 @@
+use std::{fs, path::Path};
+
+/// Where udev keeps the properties of the devices it processed
+const RUN_UDEV_DATA: &str = "/run/udev/data";
+
+/// Properties with the predictable names udev generated for a network
+/// interface, see `systemd.net-naming-scheme(7)`
+const NET_NAME_PROPERTIES: [&[u8]; 2] =
+    [b"ID_NET_NAME_PATH", b"ID_NET_NAME_MAC"];
+
+/// Predictable names of the network interface `index` from the udev database
+/// (`ID_NET_NAME_PATH` and `ID_NET_NAME_MAC`)
+///
+/// These stay the same when the interface gets renamed. Empty if udev doesn't
+/// run or hasn't processed the interface (yet).
+#[must_use]
+pub fn net_names(index: u32) -> Vec<Box<[u8]>> {
+    net_names_at(Path::new(RUN_UDEV_DATA), index)
+}
+
+fn net_names_at(root: &Path, index: u32) -> Vec<Box<[u8]>> {
+    // Network interfaces are stored as `n<ifindex>`
+    let Ok(data) = fs::read(root.join(format!("n{index}"))) else {
+        return Vec::new();
+    };
+
+    data.split(|&c| c == b'\n')
+        .filter_map(|line| {
+            let property = line.strip_prefix(b"E:")?;
+            let separator = property.iter().position(|&c| c == b'=')?;
+            let (key, value) =
+                (&property[..separator], &property[separator + 1..]);
+            (NET_NAME_PROPERTIES.contains(&key) && !value.is_empty())
+                .then(|| value.into())
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::{env, process};
+
+    #[test]
+    fn database() {
+        let root =
+            env::temp_dir().join(format!("wait-online-udev-{}", process::id()));
+        fs::create_dir_all(&root).unwrap();
+        fs::write(
+            root.join("n3"),
+            "I:1234\nE:ID_NET_NAMING_SCHEME=v255\nE:ID_NET_NAME_MAC=\
+             enx02fc00000001\nE:ID_NET_NAME_PATH=enp3s0\nE:ID_PATH=pci-0000:03:00.0\
+             \nG:systemd\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            net_names_at(&root, 3),
+            [&b"enx02fc00000001"[..], &b"enp3s0"[..]].map(Box::from)
+        );
+        assert!(net_names_at(&root, 4).is_empty());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
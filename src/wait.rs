@@ -0,0 +1,100 @@
+use std::{
+    cmp::min,
+    io,
+    thread::sleep,
+    time::{Duration, Instant},
+};
+
+use crate::netlink::Subscription;
+
+/// Waits between two network checks
+#[derive(Debug)]
+pub enum Waiter {
+    /// Sleep for a fixed interval
+    Poll(Duration),
+    /// Wait until the kernel reports a link or address change
+    Event(Subscription),
+}
+
+impl Waiter {
+    /// Creates a [`Waiter::Event`] unless `poll` is set,
+    /// falls back to [`Waiter::Poll`] if subscribing to the kernel's
+    /// notifications fails.
+    #[must_use]
+    pub fn new(poll: bool, interval: Duration) -> Self {
+        if poll {
+            return Self::Poll(interval);
+        }
+
+        Subscription::new().map_or(Self::Poll(interval), Self::Event)
+    }
+
+    /// Waits for the next check.
+    ///
+    /// Returns `false` without waiting if `deadline` has already passed,
+    /// a `deadline` of `None` never passes.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if waiting for kernel notifications fails.
+    pub fn wait(&self, deadline: Option<Instant>) -> Result<bool, io::Error> {
+        let time_to_deadline = match deadline {
+            Some(deadline) => {
+                match deadline.checked_duration_since(Instant::now()) {
+                    Some(time_to_deadline) => Some(time_to_deadline),
+                    // Timeout
+                    None => return Ok(false),
+                }
+            }
+            None => None,
+        };
+
+        match self {
+            Self::Poll(interval) => {
+                // Sleep for interval or untill the deadline whichever is
+                // faster
+                sleep(time_to_deadline.map_or(*interval, |time_to_deadline| {
+                    min(*interval, time_to_deadline)
+                }));
+            }
+            Self::Event(subscription) => {
+                _ = subscription.wait(time_to_deadline)?;
+            }
+        }
+
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn poll_deadline_passed() {
+        let waiter = Waiter::new(true, Duration::from_secs(10));
+        let deadline = Instant::now().checked_sub(Duration::from_millis(1));
+
+        assert!(!waiter.wait(deadline).unwrap());
+    }
+
+    #[test]
+    fn poll_until_deadline() {
+        let waiter = Waiter::new(true, Duration::from_secs(10));
+        let start = Instant::now();
+        let deadline = start + Duration::from_millis(10);
+
+        assert!(waiter.wait(Some(deadline)).unwrap());
+        assert!(start.elapsed() < Duration::from_secs(10));
+        assert!(!waiter.wait(Some(deadline)).unwrap());
+    }
+
+    #[test]
+    fn poll_interval() {
+        let waiter = Waiter::new(true, Duration::from_millis(10));
+        let start = Instant::now();
+
+        assert!(waiter.wait(None).unwrap());
+        assert!(start.elapsed() >= Duration::from_millis(10));
+    }
+}
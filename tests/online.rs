@@ -1,11 +1,15 @@
-use std::{ffi::CString, ptr, slice};
+use std::{ffi::CString, fmt, mem, net::IpAddr, ptr, slice};
 
 use nix::{net::if_::InterfaceFlags, sys::socket::AddressFamily};
 
 use wait_online::{
-    arguments::Args,
-    libc::{self, c_char, ifaddrs, sa_family_t, sockaddr},
-    network_online, NetworkArgument,
+    arguments::{Args, FamilyScope},
+    ifaddrs::Ifaddr,
+    interface_matches,
+    libc::{self, ifaddrs, sa_family_t, sockaddr, sockaddr_storage},
+    missing_addresses, network_online, offline_groups, requirement_met,
+    sockaddr::AddressScope,
+    AddressArgument, NetworkArgument,
 };
 
 const FLAGS_LOOPBACK: i32 = InterfaceFlags::IFF_UP.bits()
@@ -454,6 +458,70 @@ mod online {
         assert!(network_online(MockIfaddrsIterator::new(&v), n_args));
     }
 
+    #[test]
+    fn min_online() {
+        let v = vec![
+            MockIfaddrs::new().name("lo").flags(FLAGS_LOOPBACK),
+            MockIfaddrs::new()
+                .name("eth0")
+                .flags(FLAGS_UP)
+                .sockaddr(AddressFamily::Inet),
+            MockIfaddrs::new()
+                .name("eth1")
+                .flags(FLAGS_LOWER_LAYWER_DOWN)
+                .sockaddr(AddressFamily::Inet),
+            MockIfaddrs::new()
+                .name("eth2")
+                .flags(FLAGS_UP)
+                .sockaddr(AddressFamily::Inet6),
+            MockIfaddrs::new()
+                .name("eth3")
+                .flags(FLAGS_LOWER_LAYWER_DOWN),
+        ];
+        let online = |args: &Args| {
+            network_online(MockIfaddrsIterator::new(&v), args.into())
+        };
+        let min = |min_online: &str| {
+            Args::new().min_online(Some(min_online.parse().unwrap()))
+        };
+
+        // 2 out of 4 interfaces are online
+        assert!(!online(&Args::new()));
+        assert!(online(&min("2")));
+        assert!(!online(&min("3")));
+        assert!(online(&min("50%")));
+        assert!(!online(&min("51%")));
+
+        // Missing required interfaces count against the quorum
+        let interfaces = |interfaces: &[&str]| {
+            interfaces
+                .iter()
+                .map(|&interface| interface.into())
+                .collect()
+        };
+        assert!(online(&min("2").interface(interfaces(&["eth0", "eth2"]))));
+        assert!(!online(
+            &min("2").interface(interfaces(&["eth0", "eth1", "eth9"]))
+        ));
+        assert!(online(
+            &min("2").interface(interfaces(&["eth0", "eth2", "eth9"]))
+        ));
+        assert!(!online(
+            &min("67%").interface(interfaces(&["eth0", "eth2", "eth9"]))
+        ));
+        let glob = vec!["eth*".parse().unwrap()];
+        assert!(online(&min("2").interface(glob)));
+
+        // Ignored interfaces don't count at all
+        let ignored = || vec!["eth1".into(), "eth3".into()];
+        assert!(online(&min("100%").ignore(ignored())));
+        assert!(!online(&min("3").ignore(ignored())));
+
+        // Every interface is checked as without `--any`
+        assert!(!online(&min("2").ipv4(true)));
+        assert!(online(&min("1").ipv4(true)));
+    }
+
     #[test]
     fn any_ignore() {
         let args = Args::new().any(true).ignore(vec!["eth1".into()]);
@@ -531,12 +599,704 @@ mod online {
         let n_args = NetworkArgument::from(&args);
         assert!(network_online(MockIfaddrsIterator::new(&v), n_args));
     }
+
+    #[test]
+    fn address_scope() {
+        let args = Args::new().ipv6(true);
+
+        let mut v = vec![MockIfaddrs::new()
+            .name("eth0")
+            .flags(FLAGS_UP)
+            .address("fe80::1")];
+        assert!(!network_online(
+            MockIfaddrsIterator::new(&v),
+            (&args).into()
+        ));
+
+        let args = args.address_scope(AddressScope::Link);
+        assert!(network_online(MockIfaddrsIterator::new(&v), (&args).into()));
+
+        v.push(
+            MockIfaddrs::new()
+                .name("eth0")
+                .flags(FLAGS_UP)
+                .address("fd00::1"),
+        );
+        let args = args.address_scope(AddressScope::Site);
+        assert!(network_online(MockIfaddrsIterator::new(&v), (&args).into()));
+
+        let args = args.address_scope(AddressScope::Global);
+        assert!(!network_online(
+            MockIfaddrsIterator::new(&v),
+            (&args).into()
+        ));
+
+        v.push(
+            MockIfaddrs::new()
+                .name("eth0")
+                .flags(FLAGS_UP)
+                .address("2001:db8::1"),
+        );
+        assert!(network_online(MockIfaddrsIterator::new(&v), (&args).into()));
+    }
+
+    #[test]
+    fn address_scope_ipv4() {
+        let args = Args::new().ipv4(true).any(true);
+
+        let mut v = vec![MockIfaddrs::new()
+            .name("eth0")
+            .flags(FLAGS_UP)
+            .address("169.254.1.1")];
+        assert!(!network_online(
+            MockIfaddrsIterator::new(&v),
+            (&args).into()
+        ));
+
+        v.push(
+            MockIfaddrs::new()
+                .name("eth1")
+                .flags(FLAGS_UP)
+                .address("192.0.2.2"),
+        );
+        assert!(network_online(MockIfaddrsIterator::new(&v), (&args).into()));
+
+        let args = args.interface(vec!["eth0".into()]);
+        assert!(!network_online(
+            MockIfaddrsIterator::new(&v),
+            (&args).into()
+        ));
+    }
+
+    #[test]
+    fn operational_state() {
+        let routable = Some("routable".parse().expect(""));
+        let args = Args::new().operational_state(routable);
+
+        let mut v = vec![
+            MockIfaddrs::new().name("lo").flags(FLAGS_LOOPBACK),
+            MockIfaddrs::new()
+                .name("eth0")
+                .flags(FLAGS_UP)
+                .sockaddr(AddressFamily::Packet),
+            MockIfaddrs::new()
+                .name("eth0")
+                .flags(FLAGS_UP)
+                .address("fe80::1"),
+        ];
+        assert!(!network_online(
+            MockIfaddrsIterator::new(&v),
+            (&args).into()
+        ));
+
+        let degraded = Some("degraded".parse().expect(""));
+        let args = args.operational_state(degraded);
+        assert!(network_online(MockIfaddrsIterator::new(&v), (&args).into()));
+
+        v.push(
+            MockIfaddrs::new()
+                .name("eth0")
+                .flags(FLAGS_UP)
+                .address("192.0.2.2"),
+        );
+        assert!(network_online(MockIfaddrsIterator::new(&v), (&args).into()));
+
+        v.push(
+            MockIfaddrs::new()
+                .name("eth1")
+                .flags(FLAGS_LOWER_LAYWER_DOWN)
+                .sockaddr(AddressFamily::Packet),
+        );
+        assert!(!network_online(
+            MockIfaddrsIterator::new(&v),
+            (&args).into()
+        ));
+
+        let args = args.ignore(vec!["eth1".into()]);
+        assert!(network_online(MockIfaddrsIterator::new(&v), (&args).into()));
+
+        let no_carrier = Some("no-carrier:carrier".parse().expect(""));
+        let args = Args::new()
+            .interface(vec!["eth1".into()])
+            .operational_state(no_carrier);
+        assert!(network_online(MockIfaddrsIterator::new(&v), (&args).into()));
+    }
+
+    #[test]
+    fn operational_state_per_interface() {
+        let v = vec![
+            MockIfaddrs::new()
+                .name("eth0")
+                .flags(FLAGS_UP)
+                .address("192.0.2.2"),
+            MockIfaddrs::new()
+                .name("br0")
+                .flags(FLAGS_UP)
+                .sockaddr(AddressFamily::Packet),
+        ];
+
+        let interfaces = vec![
+            "eth0:routable".parse().expect(""),
+            "br0:carrier".parse().expect(""),
+        ];
+        let args = Args::new().interface(interfaces);
+        assert!(network_online(MockIfaddrsIterator::new(&v), (&args).into()));
+
+        let interfaces = vec![
+            "eth0:routable".parse().expect(""),
+            "br0:routable".parse().expect(""),
+        ];
+        let args = Args::new().interface(interfaces);
+        assert!(!network_online(
+            MockIfaddrsIterator::new(&v),
+            (&args).into()
+        ));
+
+        // Interfaces without their own state use `--operational-state`
+        let interfaces = vec!["eth0".into(), "br0:carrier".parse().expect("")];
+        let degraded = Some("degraded".parse().expect(""));
+        let args = Args::new()
+            .interface(interfaces)
+            .operational_state(degraded);
+        assert!(network_online(MockIfaddrsIterator::new(&v), (&args).into()));
+
+        let carrier = Some("carrier:degraded".parse().expect(""));
+        let args = args.operational_state(carrier);
+        assert!(!network_online(
+            MockIfaddrsIterator::new(&v),
+            (&args).into()
+        ));
+
+        let interfaces = vec!["eth0:routable".parse().expect("")];
+        let args = Args::new().interface(interfaces).ipv6(true);
+        assert!(!network_online(
+            MockIfaddrsIterator::new(&v),
+            (&args).into()
+        ));
+    }
+
+    #[test]
+    fn operational_state_missing() {
+        let v = vec![MockIfaddrs::new()
+            .name("eth0")
+            .flags(FLAGS_UP)
+            .sockaddr(AddressFamily::Packet)];
+
+        let args = Args::new().interface(vec!["eth1".into()]);
+        assert!(!network_online(
+            MockIfaddrsIterator::new(&v),
+            (&args).into()
+        ));
+
+        let missing = Some("missing".parse().expect(""));
+        let args = args.operational_state(missing);
+        assert!(network_online(MockIfaddrsIterator::new(&v), (&args).into()));
+
+        let carrier = Some("carrier".parse().expect(""));
+        let args = args.operational_state(carrier).any(true);
+        assert!(!network_online(
+            MockIfaddrsIterator::new(&v),
+            (&args).into()
+        ));
+
+        let args = args.interface(vec!["eth0".into(), "eth1".into()]);
+        assert!(network_online(MockIfaddrsIterator::new(&v), (&args).into()));
+    }
+
+    #[test]
+    fn interface_and_ignore() {
+        let mut v = vec![
+            MockIfaddrs::new().name("lo").flags(FLAGS_LOOPBACK),
+            MockIfaddrs::new()
+                .name("eth0")
+                .flags(FLAGS_UP)
+                .sockaddr(AddressFamily::Inet),
+            MockIfaddrs::new()
+                .name("docker0")
+                .flags(FLAGS_LOWER_LAYWER_DOWN)
+                .sockaddr(AddressFamily::Packet),
+        ];
+
+        // Every other interface is taken into account unless it's ignored
+        let args = Args {
+            interface: Some(vec!["eth0".into()]),
+            ignore: Some(vec!["docker*".parse().expect("")]),
+            ..Args::new()
+        };
+        assert!(network_online(MockIfaddrsIterator::new(&v), (&args).into()));
+
+        v.push(
+            MockIfaddrs::new()
+                .name("wlan0")
+                .flags(FLAGS_LOWER_LAYWER_DOWN)
+                .sockaddr(AddressFamily::Packet),
+        );
+        assert!(!network_online(
+            MockIfaddrsIterator::new(&v),
+            (&args).into()
+        ));
+
+        // A required interface is never ignored
+        let args = Args {
+            ignore: Some(vec![
+                "docker*".parse().expect(""),
+                "*0".parse().expect(""),
+            ]),
+            ..args
+        };
+        assert!(network_online(MockIfaddrsIterator::new(&v), (&args).into()));
+        v[1] = MockIfaddrs::new()
+            .name("eth0")
+            .flags(FLAGS_LOWER_LAYWER_DOWN)
+            .sockaddr(AddressFamily::Inet);
+        assert!(!network_online(
+            MockIfaddrsIterator::new(&v),
+            (&args).into()
+        ));
+
+        // Required interfaces still have to exist
+        let args = Args {
+            interface: Some(vec!["eth1".into()]),
+            ..args
+        };
+        assert!(!network_online(
+            MockIfaddrsIterator::new(&v),
+            (&args).into()
+        ));
+    }
+
+    #[test]
+    fn interface_family() {
+        let v = vec![
+            MockIfaddrs::new().name("lo").flags(FLAGS_LOOPBACK),
+            MockIfaddrs::new().name("eth0").flags(FLAGS_UP),
+            MockIfaddrs::new()
+                .name("eth0")
+                .flags(FLAGS_UP)
+                .sockaddr(AddressFamily::Inet),
+            MockIfaddrs::new().name("wg0").flags(FLAGS_UP),
+            MockIfaddrs::new()
+                .name("wg0")
+                .flags(FLAGS_UP)
+                .sockaddr(AddressFamily::Inet6),
+        ];
+        let interfaces = |interfaces: &[&str]| {
+            interfaces
+                .iter()
+                .map(|interface| interface.parse().expect(""))
+                .collect()
+        };
+
+        let args =
+            Args::new().interface(interfaces(&["eth0:ipv4", "wg0:ipv6"]));
+        assert!(network_online(MockIfaddrsIterator::new(&v), (&args).into()));
+
+        let args =
+            Args::new().interface(interfaces(&["eth0:ipv6", "wg0:ipv6"]));
+        assert!(!network_online(
+            MockIfaddrsIterator::new(&v),
+            (&args).into()
+        ));
+
+        let args =
+            Args::new().interface(interfaces(&["eth0:ipv4+ipv6", "wg0"]));
+        assert!(!network_online(
+            MockIfaddrsIterator::new(&v),
+            (&args).into()
+        ));
+
+        // Overrides `--ipv4`/`--ipv6` for the matching interfaces only
+        let args = Args::new().interface(interfaces(&["eth0:ipv4", "wg0"]));
+        assert!(network_online(
+            MockIfaddrsIterator::new(&v),
+            (&args.ipv6(true)).into()
+        ));
+        let args = Args::new().interface(interfaces(&["eth0", "wg0:ipv6"]));
+        assert!(!network_online(
+            MockIfaddrsIterator::new(&v),
+            (&args.ipv6(true)).into()
+        ));
+    }
+
+    #[test]
+    fn family_scope() {
+        let mut v = vec![
+            MockIfaddrs::new().name("lo").flags(FLAGS_LOOPBACK),
+            MockIfaddrs::new()
+                .name("eth0")
+                .flags(FLAGS_UP)
+                .address("192.0.2.2"),
+            MockIfaddrs::new()
+                .name("wg0")
+                .flags(FLAGS_UP)
+                .address("2001:db8::2"),
+        ];
+        let online = |v: &[MockIfaddrs], args: &Args| {
+            network_online(MockIfaddrsIterator::new(v), args.into())
+        };
+
+        let both = || Args::new().ipv4(true).ipv6(true);
+        let either = || Args::new().ipv4_or_ipv6(true);
+        let host = FamilyScope::Host;
+
+        // Every interface needs both, one of them or the host needs both, one
+        // of them
+        assert!(!online(&v, &both()));
+        assert!(online(&v, &either()));
+        assert!(online(&v, &both().family_scope(host)));
+        assert!(online(&v, &either().family_scope(host)));
+
+        // Without the IPv6 address only one family is left on the host
+        v[2] = MockIfaddrs::new()
+            .name("wg0")
+            .flags(FLAGS_UP)
+            .address("fe80::2");
+        assert!(!online(&v, &either()));
+        assert!(!online(&v, &both().family_scope(host)));
+        assert!(online(&v, &either().family_scope(host)));
+
+        // Addresses of interfaces that are down don't count for the host
+        v[1] = MockIfaddrs::new()
+            .name("eth0")
+            .flags(FLAGS_LOWER_LAYWER_DOWN)
+            .address("192.0.2.2");
+        let any = either().family_scope(host).any(true);
+        assert!(!online(&v, &any));
+        v.push(
+            MockIfaddrs::new()
+                .name("wlan0")
+                .flags(FLAGS_UP)
+                .address("192.0.2.3"),
+        );
+        assert!(online(&v, &any));
+        assert!(!online(&v, &either().family_scope(host)));
+
+        // Only the interfaces taken into account count
+        let args = || Args::new().ipv4(true).family_scope(host);
+        assert!(!online(&v, &args().interface(vec!["wg0".into()])));
+        assert!(online(
+            &v,
+            &args().interface(vec!["wg0".into(), "wlan0".into()])
+        ));
+    }
+
+    #[test]
+    fn groups() {
+        let mut v = vec![
+            MockIfaddrs::new().name("lo").flags(FLAGS_LOOPBACK),
+            MockIfaddrs::new()
+                .name("eth0")
+                .flags(FLAGS_LOWER_LAYWER_DOWN)
+                .sockaddr(AddressFamily::Inet),
+            MockIfaddrs::new()
+                .name("wlan0")
+                .flags(FLAGS_UP)
+                .sockaddr(AddressFamily::Inet),
+            MockIfaddrs::new()
+                .name("eno1")
+                .flags(FLAGS_UP)
+                .sockaddr(AddressFamily::Inet6),
+            MockIfaddrs::new()
+                .name("eno2")
+                .flags(FLAGS_UP)
+                .sockaddr(AddressFamily::Inet6),
+            MockIfaddrs::new()
+                .name("docker0")
+                .flags(FLAGS_LOWER_LAYWER_DOWN),
+        ];
+        let groups = |groups: &[&str]| {
+            Args::new().group(
+                groups.iter().map(|group| group.parse().unwrap()).collect(),
+            )
+        };
+        let offline = |v: &[MockIfaddrs], args: &Args| {
+            offline_groups(MockIfaddrsIterator::new(v), args.into())
+                .into_iter()
+                .map(String::from)
+                .collect::<Vec<_>>()
+        };
+
+        // Interfaces outside of the groups don't count
+        let args = groups(&["uplink=any:eth0,wlan0", "mgmt=all:eno1,eno2"]);
+        assert!(network_online(MockIfaddrsIterator::new(&v), (&args).into()));
+        assert!(offline(&v, &args).is_empty());
+
+        v[4] = MockIfaddrs::new()
+            .name("eno2")
+            .flags(FLAGS_LOWER_LAYWER_DOWN)
+            .sockaddr(AddressFamily::Inet6);
+        assert!(!network_online(
+            MockIfaddrsIterator::new(&v),
+            (&args).into()
+        ));
+        assert_eq!(offline(&v, &args), ["mgmt"]);
+
+        let args = groups(&["uplink=all:eth0,wlan0", "mgmt=any:eno1,eno2"]);
+        assert_eq!(offline(&v, &args), ["uplink"]);
+
+        // Quorum, missing interfaces count against it
+        let args = groups(&["wan=2:eth0,wlan0,eno1"]);
+        assert!(offline(&v, &args).is_empty());
+        let args = groups(&["wan=2:eth0,wlan0,eno3"]);
+        assert_eq!(offline(&v, &args), ["wan"]);
+
+        // Address families apply per interface and globally
+        let args = groups(&["uplink=any:eth0,wlan0:ipv6"]);
+        assert_eq!(offline(&v, &args), ["uplink"]);
+        let args = groups(&["mgmt=any:eno1,eno2"]);
+        assert!(offline(&v, &args).is_empty());
+        assert_eq!(offline(&v, &args.ipv4(true)), ["mgmt"]);
+    }
+
+    #[test]
+    fn require() {
+        let v = vec![
+            MockIfaddrs::new().name("lo").flags(FLAGS_LOOPBACK),
+            MockIfaddrs::new()
+                .name("eth0")
+                .flags(FLAGS_UP)
+                .sockaddr(AddressFamily::Inet),
+            MockIfaddrs::new()
+                .name("eth0")
+                .flags(FLAGS_UP)
+                .sockaddr(AddressFamily::Inet6),
+            MockIfaddrs::new()
+                .name("wlan0")
+                .flags(FLAGS_UP)
+                .sockaddr(AddressFamily::Inet6),
+            MockIfaddrs::new()
+                .name("docker0")
+                .flags(FLAGS_LOWER_LAYWER_DOWN)
+                .sockaddr(AddressFamily::Inet),
+        ];
+        let require = |expression: &str| {
+            Args::new().require(Some(expression.parse().unwrap()))
+        };
+        let online = |args: &Args| {
+            // The expression replaces the interface requirements
+            assert!(network_online(MockIfaddrsIterator::new(&v), args.into()));
+            requirement_met(MockIfaddrsIterator::new(&v), args.into()).unwrap()
+        };
+
+        assert!(requirement_met(
+            MockIfaddrsIterator::new(&v),
+            (&Args::new()).into()
+        )
+        .unwrap());
+        assert!(online(&require("eth0 & ipv4 | wlan0 & ipv4")));
+        assert!(online(&require("eth0 & wlan0 & ipv6")));
+        assert!(!online(&require("eth0 & wlan0 & ipv4")));
+        assert!(!online(&require("docker0")));
+        assert!(online(&require("eth0 & !docker0")));
+        assert!(!online(&require("missing0 | lo")));
+
+        // Any interface will do without a pattern
+        assert!(online(&require("ipv4 & ipv6")));
+        assert!(online(&require("eth* & ipv4 & ipv6")));
+        assert!(!online(&require("wlan0 & ipv4 & ipv6")));
+
+        // `ipv6` only counts addresses in `--address-scope`
+        let link_local = vec![MockIfaddrs::new()
+            .name("eth0")
+            .flags(FLAGS_UP)
+            .address("fe80::1")];
+        let met = |args: &Args| {
+            requirement_met(MockIfaddrsIterator::new(&link_local), args.into())
+                .unwrap()
+        };
+        assert!(!met(&require("eth0 & ipv6")));
+        assert!(met(
+            &require("eth0 & ipv6").address_scope(AddressScope::Link)
+        ));
+
+        // Ignored interfaces are left out
+        let args = require("ipv4").ignore(vec!["eth0".into()]);
+        assert!(!online(&args));
+        let args = require("ipv6").ignore(vec!["eth0".into()]);
+        assert!(online(&args));
+    }
+
+    #[test]
+    fn presence_only() {
+        let mut v = vec![
+            MockIfaddrs::new().name("lo").flags(FLAGS_LOOPBACK),
+            MockIfaddrs::new().name("eth0").flags(FLAGS_UP),
+            MockIfaddrs::new()
+                .name("usb0")
+                .flags(FLAGS_LOWER_LAYWER_DOWN),
+        ];
+        let args = Args::new()
+            .interface(vec!["usb0".into(), "usb1".into()])
+            .presence_only(true);
+        assert!(!network_online(
+            MockIfaddrsIterator::new(&v),
+            (&args).into()
+        ));
+
+        // Neither the link state nor the addresses matter
+        v.push(
+            MockIfaddrs::new()
+                .name("usb1")
+                .flags(InterfaceFlags::IFF_BROADCAST.bits()),
+        );
+        assert!(network_online(MockIfaddrsIterator::new(&v), (&args).into()));
+        let args = args.presence_only(false);
+        assert!(!network_online(
+            MockIfaddrsIterator::new(&v),
+            (&args).into()
+        ));
+
+        let args = Args::new()
+            .interface(vec!["usb*".parse().unwrap(), "usb2".into()])
+            .presence_only(true);
+        assert!(!network_online(
+            MockIfaddrsIterator::new(&v),
+            (&args).into()
+        ));
+        let args = args.any(true);
+        assert!(network_online(MockIfaddrsIterator::new(&v), (&args).into()));
+
+        let args = Args::new()
+            .group(vec!["usb=all:usb0,usb1".parse().unwrap()])
+            .presence_only(true);
+        assert!(network_online(MockIfaddrsIterator::new(&v), (&args).into()));
+    }
+
+    #[test]
+    fn addresses() {
+        let v = vec![
+            MockIfaddrs::new()
+                .name("lo")
+                .flags(FLAGS_LOOPBACK)
+                .address("10.0.0.5"),
+            MockIfaddrs::new().name("eth0").flags(FLAGS_UP),
+            MockIfaddrs::new()
+                .name("eth0")
+                .flags(FLAGS_UP)
+                .address("192.168.10.7"),
+            MockIfaddrs::new()
+                .name("wlan0")
+                .flags(FLAGS_LOWER_LAYWER_DOWN)
+                .address("fd00::5"),
+        ];
+        let missing = |args: &Args| {
+            missing_addresses(MockIfaddrsIterator::new(&v), args.into())
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+        };
+        let address = |addresses: &[&str]| {
+            Args::new().address(
+                addresses.iter().map(|ip| ip.parse().unwrap()).collect(),
+            )
+        };
+        let address_in = |addresses_in: &[&str]| {
+            Args::new().address_in(
+                addresses_in.iter().map(|ip| ip.parse().unwrap()).collect(),
+            )
+        };
+
+        assert!(AddressArgument::from(&Args::new()).is_empty());
+        assert!(missing(&Args::new()).is_empty());
+
+        // Loopback interfaces and interfaces that are down count as well
+        assert!(missing(&address(&["10.0.0.5", "fd00::5"])).is_empty());
+        assert_eq!(
+            missing(&address(&["10.0.0.6", "192.168.10.7", "fd00::6"])),
+            ["address 10.0.0.6", "address fd00::6"]
+        );
+
+        assert!(missing(&address_in(&["192.168.10.0/24@eth*", "fd00::/8"]))
+            .is_empty());
+        assert_eq!(
+            missing(&address_in(&["192.168.10.0/24@wlan0", "10.0.0.0/8"])),
+            ["an address in 192.168.10.0/24@wlan0"]
+        );
+
+        let args = address(&["10.0.0.6"]).address_in(vec![
+            "192.168.11.0/24".parse().unwrap(),
+            "192.168.10.0/24".parse().unwrap(),
+        ]);
+        assert_eq!(
+            missing(&args),
+            ["address 10.0.0.6", "an address in 192.168.11.0/24"]
+        );
+    }
+
+    #[test]
+    fn interface_patterns() {
+        let mut v = vec![
+            MockIfaddrs::new().name("lo").flags(FLAGS_LOOPBACK),
+            MockIfaddrs::new()
+                .name("enp3s0")
+                .flags(FLAGS_UP)
+                .sockaddr(AddressFamily::Inet),
+            MockIfaddrs::new()
+                .name("veth1a2b3c")
+                .flags(FLAGS_LOWER_LAYWER_DOWN)
+                .sockaddr(AddressFamily::Packet),
+        ];
+        let patterns = |patterns: &[&str]| {
+            patterns
+                .iter()
+                .map(|pattern| pattern.parse().expect(""))
+                .collect()
+        };
+
+        let args = Args::new().interface(patterns(&["en*"]));
+        assert!(network_online(MockIfaddrsIterator::new(&v), (&args).into()));
+
+        // At least one interface has to match
+        let args = Args::new().interface(patterns(&["en*", "wl?0"]));
+        assert!(!network_online(
+            MockIfaddrsIterator::new(&v),
+            (&args).into()
+        ));
+        let args = args.any(true);
+        assert!(network_online(MockIfaddrsIterator::new(&v), (&args).into()));
+
+        // Every match is required
+        let args = Args::new().interface(patterns(&["re:(en|veth).*"]));
+        assert!(!network_online(
+            MockIfaddrsIterator::new(&v),
+            (&args).into()
+        ));
+
+        let args = Args::new().ignore(vec!["veth*".parse().expect("")]);
+        assert!(network_online(MockIfaddrsIterator::new(&v), (&args).into()));
+
+        v.push(
+            MockIfaddrs::new()
+                .name("enp4s0")
+                .flags(FLAGS_LOWER_LAYWER_DOWN)
+                .sockaddr(AddressFamily::Inet),
+        );
+        assert!(!network_online(
+            MockIfaddrsIterator::new(&v),
+            (&args).into()
+        ));
+
+        let args =
+            Args::new().interface(patterns(&["en*", "re:veth.*", "wl?0"]));
+        let matches =
+            interface_matches(MockIfaddrsIterator::new(&v), (&args).into());
+        let matches: Vec<String> =
+            matches.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            matches,
+            [
+                "`en*` matches enp3s0, enp4s0",
+                "`re:veth.*` matches veth1a2b3c",
+                "`wl?0` matches no interface",
+            ]
+        );
+    }
 }
 
-#[derive(Default, Debug)]
+/// Large enough for every address family, unlike `sockaddr`
+#[derive(Clone, Copy)]
 struct MockSockaddr {
-    sa_family: sa_family_t,
-    sa_data: [c_char; 14],
+    storage: sockaddr_storage,
 }
 
 #[allow(unused)]
@@ -552,42 +1312,91 @@ struct MockIfaddrs {
 
 struct MockIfaddrsIterator<'a> {
     iterator: slice::Iter<'a, MockIfaddrs>,
-    raw_scokaddrs: Vec<*mut sockaddr>,
 }
 
 impl<'a> Iterator for MockIfaddrsIterator<'a> {
-    type Item = ifaddrs;
+    type Item = Ifaddr<'a>;
 
     fn next(&mut self) -> Option<Self::Item> {
         self.iterator.next().map(|mock_ifaddrs| {
             let ifaddrs =
                 mock_ifaddrs.make_ifaddrs(ptr::null::<ifaddrs>().cast_mut());
-            self.raw_scokaddrs.push(ifaddrs.ifa_addr);
-            self.raw_scokaddrs.push(ifaddrs.ifa_netmask);
-            self.raw_scokaddrs.push(ifaddrs.ifa_ifu);
-            ifaddrs
+            // SAFETY: The name and the address point into the mock, which
+            // lives for `'a`
+            unsafe { Ifaddr::new(ifaddrs) }
         })
     }
 }
 
 impl<'a> MockIfaddrsIterator<'a> {
     fn new(v: &'a [MockIfaddrs]) -> Self {
-        Self {
-            iterator: v.iter(),
-            raw_scokaddrs: Vec::new(),
-        }
+        Self { iterator: v.iter() }
     }
 }
 
 impl MockSockaddr {
-    const fn as_sockaddr(&self) -> sockaddr {
-        sockaddr {
-            sa_family: self.sa_family,
-            sa_data: self.sa_data,
+    fn new(family: AddressFamily) -> Self {
+        let mut mock = Self::default();
+        mock.storage.ss_family =
+            sa_family_t::try_from(family as i32).expect("");
+        mock
+    }
+
+    fn from_ip(address: IpAddr) -> Self {
+        let mut mock = Self::default();
+        let storage_ptr: *mut sockaddr_storage = &mut mock.storage;
+        match address {
+            IpAddr::V4(address) => {
+                let sockaddr_in = libc::sockaddr_in {
+                    sin_family: sa_family_t::try_from(libc::AF_INET).expect(""),
+                    sin_port: 0,
+                    sin_addr: libc::in_addr {
+                        s_addr: u32::from(address).to_be(),
+                    },
+                    sin_zero: [0; 8],
+                };
+                unsafe {
+                    storage_ptr.cast::<libc::sockaddr_in>().write(sockaddr_in);
+                };
+            }
+            IpAddr::V6(address) => {
+                let sockaddr_in6 = libc::sockaddr_in6 {
+                    sin6_family: sa_family_t::try_from(libc::AF_INET6)
+                        .expect(""),
+                    sin6_port: 0,
+                    sin6_flowinfo: 0,
+                    sin6_addr: libc::in6_addr {
+                        s6_addr: address.octets(),
+                    },
+                    sin6_scope_id: 0,
+                };
+                unsafe {
+                    storage_ptr
+                        .cast::<libc::sockaddr_in6>()
+                        .write(sockaddr_in6);
+                };
+            }
+        }
+        mock
+    }
+}
+
+impl Default for MockSockaddr {
+    fn default() -> Self {
+        Self {
+            storage: unsafe { mem::zeroed() },
         }
     }
 }
 
+impl fmt::Debug for MockSockaddr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MockSockaddr")
+            .field("ss_family", &self.storage.ss_family)
+            .finish_non_exhaustive()
+    }
+}
+
 impl<'a> MockIfaddrs {
     fn new() -> Self {
         Self::default()
@@ -604,10 +1413,12 @@ impl<'a> MockIfaddrs {
     }
 
     fn sockaddr(mut self, family: AddressFamily) -> Self {
-        self.ifa_addr = MockSockaddr {
-            sa_family: libc::sa_family_t::try_from(family as i32).expect(""),
-            sa_data: [libc::c_char::default(); 14],
-        };
+        self.ifa_addr = MockSockaddr::new(family);
+        self
+    }
+
+    fn address(mut self, address: &str) -> Self {
+        self.ifa_addr = MockSockaddr::from_ip(address.parse().expect(""));
         self
     }
 
@@ -617,7 +1428,9 @@ impl<'a> MockIfaddrs {
             ifa_name: self.ifa_name.as_c_str().as_ptr().cast_mut(),
             #[allow(clippy::cast_sign_loss)]
             ifa_flags: self.ifa_flags as u32,
-            ifa_addr: Box::into_raw(Box::new(self.ifa_addr.as_sockaddr())),
+            ifa_addr: ptr::addr_of!(self.ifa_addr.storage)
+                .cast::<sockaddr>()
+                .cast_mut(),
             ifa_netmask: ptr::null_mut::<sockaddr>(),
             ifa_ifu: ptr::null_mut::<sockaddr>(),
             ifa_data: ptr::null_mut::<libc::c_void>(),
@@ -630,15 +1443,3 @@ impl<'a> MockIfaddrs {
         }
     }
 }
-
-impl<'a> Drop for MockIfaddrsIterator<'a> {
-    fn drop(&mut self) {
-        for sockaddr in &self.raw_scokaddrs {
-            if !sockaddr.is_null() {
-                let sockaddr: Box<sockaddr> =
-                    unsafe { Box::from_raw(*sockaddr) };
-                drop(sockaddr);
-            }
-        }
-    }
-}